@@ -19,7 +19,6 @@ from typing import Optional, Union
 import pandas as pd
 
 from .dataset import LanceDataset, __version__, write_dataset
-from .util import sanitize_ts
 
 __all__ = [
     "LanceDataset",
@@ -47,18 +46,4 @@ def dataset(
         If specified, find the latest version created on or earlier than the given argument value.
         If a version is already specified, this arg is ignored.
     """
-    ds = LanceDataset(uri, version)
-    if version is None and asof is not None:
-        ts_cutoff = sanitize_ts(asof)
-        ver_cutoff = max(
-            [v["version"] for v in ds.versions() if v["timestamp"] < ts_cutoff],
-            default=None,
-        )
-        if ver_cutoff is None:
-            raise ValueError(
-                f"{ts_cutoff} is earlier than the first version of this dataset"
-            )
-        else:
-            return LanceDataset(uri, ver_cutoff)
-    else:
-        return ds
+    return LanceDataset(uri, version, asof)
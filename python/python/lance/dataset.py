@@ -28,15 +28,26 @@ from pyarrow import RecordBatch, Schema
 from pyarrow._compute import Expression
 
 from .lance import __version__, _Dataset, _Scanner, _write_dataset
+from .util import sanitize_ts
 
 
 class LanceDataset(pa.dataset.Dataset):
     """A dataset in Lance format where the data is stored at the given uri"""
 
-    def __init__(self, uri: Union[str, Path], version: Optional[int] = None):
+    def __init__(
+        self,
+        uri: Union[str, Path],
+        version: Optional[int] = None,
+        asof: Optional[Union[datetime, pd.Timestamp, str]] = None,
+    ):
         uri = os.fspath(uri) if isinstance(uri, Path) else uri
         self._uri = uri
-        self._ds = _Dataset(uri, version)
+        asof_nanos = None
+        if asof is not None:
+            # python datetime supports only microsecond precision.
+            # Only the versions committed strictly before `asof` are considered.
+            asof_nanos = int(sanitize_ts(asof).timestamp() * 1e6) * 1000 - 1
+        self._ds = _Dataset(uri, version, asof_nanos)
 
     @property
     def uri(self) -> str:
@@ -354,9 +365,9 @@ class LanceDataset(pa.dataset.Dataset):
                 raise TypeError(
                     f"Vector column {c} must be FixedSizeListArray, got {field.type}"
                 )
-            if not pa.types.is_float32(field.type.value_type):
+            if not pa.types.is_floating(field.type.value_type):
                 raise TypeError(
-                    f"Vector column {c} must have float32 value type, got {field.type.value_type}"
+                    f"Vector column {c} must have floating point value type, got {field.type.value_type}"
                 )
             if field.type.list_size % 8 != 0:
                 if not kwargs.get("force_build", False):
@@ -581,6 +592,7 @@ def write_dataset(
     mode: str = "create",
     max_rows_per_file: int = 1024 * 1024,
     max_rows_per_group: int = 1024,
+    unsafe_commit: bool = False,
 ) -> LanceDataset:
     """Write a given data_obj to the given uri
 
@@ -603,6 +615,10 @@ def write_dataset(
         The max number of rows to write before starting a new file
     max_rows_per_group: int, default 1024
         The max number of rows before starting a new group (in the same file)
+    unsafe_commit: bool, default False
+        Commit the new version without an atomic check that it does not exist.
+        Required on S3, which can not commit atomically, and only safe if there
+        is a single writer.
 
     """
     if isinstance(data_obj, pd.DataFrame):
@@ -623,6 +639,7 @@ def write_dataset(
         "mode": mode,
         "max_rows_per_file": max_rows_per_file,
         "max_rows_per_group": max_rows_per_group,
+        "unsafe_commit": unsafe_commit,
     }
 
     uri = os.fspath(uri) if isinstance(uri, Path) else uri
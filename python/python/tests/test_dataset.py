@@ -64,6 +64,8 @@ def test_asof_checkout(tmp_path: Path):
     table = pa.Table.from_pydict({"colA": [1, 2, 3], "colB": [4, 5, 6]})
     base_dir = tmp_path / "test"
 
+    ts_0 = datetime.now()
+    time.sleep(0.01)
     lance.write_dataset(table, base_dir)
     assert len(lance.dataset(base_dir).versions()) == 1
     ts_1 = datetime.now()
@@ -93,6 +95,14 @@ def test_asof_checkout(tmp_path: Path):
     assert ds.version == 3
     assert len(ds.to_table()) == 9
 
+    # no version was committed before the dataset was created
+    with pytest.raises(ValueError):
+        lance.dataset(base_dir, asof=ts_0)
+
+    # a version committed exactly at the given time is not included
+    v2_ts = next(v["timestamp"] for v in ds.versions() if v["version"] == 2)
+    assert lance.dataset(base_dir, asof=v2_ts).version == 1
+
 
 def test_take(tmp_path: Path):
     table1 = pa.Table.from_pylist([{"a": 1, "b": 2}, {"a": 10, "b": 20}])
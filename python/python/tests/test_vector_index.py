@@ -144,3 +144,30 @@ def test_use_index(dataset, tmp_path):
     )["id"].to_numpy()
 
     assert np.all(expected == actual)
+
+
+@pytest.mark.skipif(
+    (os.uname().sysname == "Darwin") and (os.uname().machine != "arm64"),
+    reason="no neon on GHA",
+)
+@pytest.mark.parametrize("value_type", [pa.float16(), pa.float64()])
+def test_non_float32_vectors(tmp_path, value_type):
+    nvec, ndim = 1000, 32
+    mat = np.random.randn(nvec, ndim).astype(value_type.to_pandas_dtype())
+    vectors = pa.FixedSizeListArray.from_arrays(pa.array(mat.ravel()), ndim)
+    tbl = pa.Table.from_arrays([vectors, pa.array(range(nvec))], names=["vector", "id"])
+    dataset = lance.write_dataset(tbl, tmp_path)
+    assert dataset.schema.field("vector").type.value_type == value_type
+
+    q = mat[42]
+    rs = dataset.to_table(columns=["id"], nearest={"column": "vector", "q": q, "k": 5})
+    assert rs["id"][0].as_py() == 42
+
+    dataset = dataset.create_index(
+        "vector", index_type="IVF_PQ", num_partitions=4, num_sub_vectors=4
+    )
+    rs = dataset.to_table(
+        columns=["id"],
+        nearest={"column": "vector", "q": q, "k": 5, "refine_factor": 10},
+    )
+    assert rs["id"][0].as_py() == 42
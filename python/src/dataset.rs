@@ -19,9 +19,10 @@ use std::sync::Arc;
 
 use arrow::ffi_stream::ArrowArrayStreamReader;
 use arrow::pyarrow::*;
-use arrow_array::{Float32Array, RecordBatchReader};
+use arrow_array::{make_array, RecordBatchReader};
 use arrow_data::ArrayData;
 use arrow_schema::Schema as ArrowSchema;
+use chrono::{TimeZone, Utc};
 use pyo3::exceptions::{PyIOError, PyKeyError, PyValueError};
 use pyo3::prelude::*;
 use pyo3::types::{IntoPyDict, PyBool, PyDict, PyInt, PyLong};
@@ -36,6 +37,7 @@ use lance::index::{
     vector::{MetricType, VectorIndexParams},
     IndexType,
 };
+use lance::io::commit::UnsafeCommitHandler;
 
 const DEFAULT_NPROBS: usize = 1;
 
@@ -52,11 +54,13 @@ pub struct Dataset {
 #[pymethods]
 impl Dataset {
     #[new]
-    fn new(uri: String, version: Option<u64>) -> PyResult<Self> {
+    fn new(uri: String, version: Option<u64>, asof: Option<i64>) -> PyResult<Self> {
         let rt = Runtime::new()?;
         let dataset = rt.block_on(async {
             if let Some(ver) = version {
                 LanceDataset::checkout(uri.as_str(), ver).await
+            } else if let Some(ts_nanos) = asof {
+                LanceDataset::checkout_as_of(uri.as_str(), Utc.timestamp_nanos(ts_nanos)).await
             } else {
                 LanceDataset::open(uri.as_str()).await
             }
@@ -110,7 +114,7 @@ impl Dataset {
                 .get_item("q")
                 .ok_or_else(|| PyKeyError::new_err("Need q for nearest"))?;
             let data = ArrayData::from_pyarrow(qval)?;
-            let q = Float32Array::from(data);
+            let q = make_array(data);
 
             let k: usize = if let Some(k) = nearest.get_item("k") {
                 if k.is_none() {
@@ -165,7 +169,7 @@ impl Dataset {
             };
 
             scanner
-                .nearest(column.as_str(), &q, k)
+                .nearest(column.as_str(), q.as_ref(), k)
                 .map(|s| {
                     let mut s = s.nprobs(nprobes);
                     if let Some(factor) = refine_factor {
@@ -322,6 +326,11 @@ fn get_write_params(options: &PyDict) -> PyResult<Option<WriteParams>> {
         if let Some(maybe_nrows) = options.get_item("max_rows_per_group") {
             p.max_rows_per_group = usize::extract(maybe_nrows)?;
         }
+        if let Some(unsafe_commit) = options.get_item("unsafe_commit") {
+            if bool::extract(unsafe_commit)? {
+                p.commit_handler = Some(Arc::new(UnsafeCommitHandler));
+            }
+        }
         Some(p)
     };
     Ok(params)
@@ -24,11 +24,13 @@ use arrow_array::{
     LargeListArray, ListArray, RecordBatch, UInt8Array,
 };
 use arrow_data::ArrayDataBuilder;
-use arrow_schema::{DataType, Field, Schema};
+use arrow_schema::{DataType, Field, IntervalUnit, Schema};
 
+mod floats;
 mod kernels;
 mod record_batch;
 use crate::error::{Error, Result};
+pub use floats::*;
 pub use kernels::*;
 pub use record_batch::*;
 
@@ -59,6 +61,19 @@ pub trait DataTypeExt {
     /// Returns true if the [DataType] is a dictionary type.
     fn is_dictionary(&self) -> bool;
 
+    /// Returns true if the values are integers, including the date and time types
+    /// that are stored as integers.
+    ///
+    /// ```
+    /// use lance::arrow::*;
+    /// use arrow_schema::{DataType, TimeUnit};
+    ///
+    /// assert!(DataType::UInt16.is_integer_like());
+    /// assert!(DataType::Timestamp(TimeUnit::Microsecond, None).is_integer_like());
+    /// assert!(!DataType::Float32.is_integer_like());
+    /// ```
+    fn is_integer_like(&self) -> bool;
+
     fn byte_width(&self) -> usize;
 }
 
@@ -93,6 +108,12 @@ impl DataTypeExt for DataType {
                 | FixedSizeList(_, _)
                 | FixedSizeBinary(_)
                 | Duration(_)
+                | Date32
+                | Date64
+                | Time32(_)
+                | Time64(_)
+                | Timestamp(_, _)
+                | Interval(_)
         )
     }
 
@@ -100,6 +121,27 @@ impl DataTypeExt for DataType {
         matches!(self, Self::Dictionary(_, _))
     }
 
+    fn is_integer_like(&self) -> bool {
+        use DataType::*;
+        matches!(
+            self,
+            UInt8
+                | UInt16
+                | UInt32
+                | UInt64
+                | Int8
+                | Int16
+                | Int32
+                | Int64
+                | Date32
+                | Date64
+                | Time32(_)
+                | Time64(_)
+                | Timestamp(_, _)
+                | Duration(_)
+        )
+    }
+
     fn byte_width(&self) -> usize {
         match self {
             Self::Int8 => 1,
@@ -118,6 +160,12 @@ impl DataTypeExt for DataType {
             Self::Time32(_) => 4,
             Self::Time64(_) => 8,
             Self::Duration(_) => 8,
+            Self::Timestamp(_, _) => 8,
+            Self::Interval(unit) => match unit {
+                IntervalUnit::YearMonth => 4,
+                IntervalUnit::DayTime => 8,
+                IntervalUnit::MonthDayNano => 16,
+            },
             Self::Decimal128(_, _) => 16,
             Self::Decimal256(_, _) => 32,
             Self::FixedSizeBinary(s) => *s as usize,
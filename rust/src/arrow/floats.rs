@@ -0,0 +1,264 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Float types of the vectors, that the vector kernels are generic over.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use arrow_array::{
+    cast::as_primitive_array,
+    types::{Float16Type, Float32Type, Float64Type},
+    Array, ArrayRef, FixedSizeBinaryArray, Float16Array, Float32Array, Float64Array,
+};
+use arrow_buffer::Buffer;
+use arrow_data::ArrayDataBuilder;
+use arrow_schema::DataType;
+use half::{bf16, f16};
+use num_traits::{Float, FromPrimitive};
+
+use crate::utils::distance::{cosine::Cosine, l2::L2};
+use crate::{Error, Result};
+
+/// The float types of the vector values.
+///
+/// Arrow has no `bfloat16` data type, so the `bfloat16` values are stored as
+/// 2-byte fixed size binaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatType {
+    BFloat16,
+    Float16,
+    Float32,
+    Float64,
+}
+
+impl TryFrom<&DataType> for FloatType {
+    type Error = Error;
+
+    /// The float type of the values of an array with `data_type`.
+    ///
+    /// The 2-byte fixed size binary values are `bfloat16`.
+    fn try_from(data_type: &DataType) -> Result<Self> {
+        match data_type {
+            DataType::FixedSizeBinary(2) => Ok(Self::BFloat16),
+            DataType::Float16 => Ok(Self::Float16),
+            DataType::Float32 => Ok(Self::Float32),
+            DataType::Float64 => Ok(Self::Float64),
+            dt => Err(Error::Schema(format!("Expect a float array, got: {dt}"))),
+        }
+    }
+}
+
+/// A float type of the vector values, with its native type and its array type.
+pub trait ArrowFloatType: Debug + Send + Sync + 'static {
+    type Native: Float + FromPrimitive + Into<f64> + Debug + Send + Sync + L2 + Cosine + 'static;
+
+    type ArrayType: FloatArray<Self>;
+
+    const FLOAT_TYPE: FloatType;
+}
+
+/// An array of the values of the float type `T`.
+pub trait FloatArray<T: ArrowFloatType + ?Sized>: Debug + Clone + Send + Sync + 'static {
+    /// Downcast `array`, which must have the values of `T`.
+    fn try_from_array(array: &dyn Array) -> Result<Self>;
+
+    fn from_values(values: Vec<T::Native>) -> Self;
+
+    /// The values in the array, without the null slots.
+    fn as_slice(&self) -> &[T::Native];
+
+    fn into_array_ref(self) -> ArrayRef;
+}
+
+impl ArrowFloatType for Float16Type {
+    type Native = f16;
+    type ArrayType = Float16Array;
+    const FLOAT_TYPE: FloatType = FloatType::Float16;
+}
+
+impl ArrowFloatType for Float32Type {
+    type Native = f32;
+    type ArrayType = Float32Array;
+    const FLOAT_TYPE: FloatType = FloatType::Float32;
+}
+
+impl ArrowFloatType for Float64Type {
+    type Native = f64;
+    type ArrayType = Float64Array;
+    const FLOAT_TYPE: FloatType = FloatType::Float64;
+}
+
+macro_rules! impl_primitive_float_array {
+    ($float_type:ty, $array_type:ty) => {
+        impl FloatArray<$float_type> for $array_type {
+            fn try_from_array(array: &dyn Array) -> Result<Self> {
+                if FloatType::try_from(array.data_type())? != <$float_type>::FLOAT_TYPE {
+                    return Err(Error::Schema(format!(
+                        "Expect an array of {:?}, got: {}",
+                        <$float_type>::FLOAT_TYPE,
+                        array.data_type()
+                    )));
+                }
+                Ok(as_primitive_array::<$float_type>(array).clone())
+            }
+
+            fn from_values(values: Vec<<$float_type as ArrowFloatType>::Native>) -> Self {
+                Self::from_iter_values(values)
+            }
+
+            fn as_slice(&self) -> &[<$float_type as ArrowFloatType>::Native] {
+                self.values()
+            }
+
+            fn into_array_ref(self) -> ArrayRef {
+                Arc::new(self)
+            }
+        }
+    };
+}
+
+impl_primitive_float_array!(Float16Type, Float16Array);
+impl_primitive_float_array!(Float32Type, Float32Array);
+impl_primitive_float_array!(Float64Type, Float64Array);
+
+/// The `bfloat16` float type.
+#[derive(Debug)]
+pub struct BFloat16Type {}
+
+impl ArrowFloatType for BFloat16Type {
+    type Native = bf16;
+    type ArrayType = BFloat16Array;
+    const FLOAT_TYPE: FloatType = FloatType::BFloat16;
+}
+
+/// An array of `bfloat16` values, stored in a [FixedSizeBinaryArray] of 2 bytes.
+#[derive(Debug, Clone)]
+pub struct BFloat16Array {
+    inner: FixedSizeBinaryArray,
+}
+
+impl FloatArray<BFloat16Type> for BFloat16Array {
+    fn try_from_array(array: &dyn Array) -> Result<Self> {
+        if array.data_type() != &DataType::FixedSizeBinary(2) {
+            return Err(Error::Schema(format!(
+                "Expect an array of BFloat16, got: {}",
+                array.data_type()
+            )));
+        }
+        let inner = array
+            .as_any()
+            .downcast_ref::<FixedSizeBinaryArray>()
+            .unwrap()
+            .clone();
+        Ok(Self { inner })
+    }
+
+    fn from_values(values: Vec<bf16>) -> Self {
+        let len = values.len();
+        let bytes = values
+            .into_iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect::<Vec<_>>();
+        let builder = ArrayDataBuilder::new(DataType::FixedSizeBinary(2))
+            .len(len)
+            .add_buffer(Buffer::from_slice_ref(&bytes));
+        // SAFETY: the buffer has exactly 2 bytes for each value.
+        let data = unsafe { builder.build_unchecked() };
+        Self {
+            inner: FixedSizeBinaryArray::from(data),
+        }
+    }
+
+    fn as_slice(&self) -> &[bf16] {
+        if self.inner.is_empty() {
+            return &[];
+        }
+        let values = self.inner.value(0).as_ptr();
+        debug_assert_eq!(values as usize % std::mem::align_of::<bf16>(), 0);
+        // SAFETY: the values are contiguous, 2 bytes each, and Arrow buffers are aligned.
+        unsafe { std::slice::from_raw_parts(values as *const bf16, self.inner.len()) }
+    }
+
+    fn into_array_ref(self) -> ArrayRef {
+        Arc::new(self.inner)
+    }
+}
+
+/// Convert the float values in `array` to the float type `T`.
+///
+/// It is for the query vectors, which are converted to the float type of the vector column.
+/// The array is returned as is if it already has the values of `T`.
+///
+/// ```
+/// use arrow_array::{types::Float16Type, Float64Array};
+/// use half::f16;
+/// use lance::arrow::{cast_float_array, FloatArray};
+///
+/// let arr = Float64Array::from(vec![1.0, 2.5, -3.0]);
+/// let f16_arr = cast_float_array::<Float16Type>(&arr).unwrap();
+/// let expected = [1.0, 2.5, -3.0].map(f16::from_f32);
+/// assert_eq!(f16_arr.as_slice(), &expected);
+/// ```
+pub fn cast_float_array<T: ArrowFloatType>(array: &dyn Array) -> Result<T::ArrayType> {
+    match FloatType::try_from(array.data_type())? {
+        t if t == T::FLOAT_TYPE => T::ArrayType::try_from_array(array),
+        FloatType::BFloat16 => cast_values::<BFloat16Type, T>(array),
+        FloatType::Float16 => cast_values::<Float16Type, T>(array),
+        FloatType::Float32 => cast_values::<Float32Type, T>(array),
+        FloatType::Float64 => cast_values::<Float64Type, T>(array),
+    }
+}
+
+fn cast_values<S: ArrowFloatType, T: ArrowFloatType>(array: &dyn Array) -> Result<T::ArrayType> {
+    let values = S::ArrayType::try_from_array(array)?
+        .as_slice()
+        .iter()
+        .map(|v| {
+            let v: f64 = (*v).into();
+            T::Native::from_f64(v).ok_or_else(|| {
+                Error::Arrow(format!("{v} can not be converted to {:?}", T::FLOAT_TYPE))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(T::ArrayType::from_values(values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bfloat16_array() {
+        let values = vec![
+            bf16::from_f32(1.0),
+            bf16::from_f32(-2.5),
+            bf16::from_f32(3.0),
+        ];
+        let arr = BFloat16Array::from_values(values.clone());
+        assert_eq!(arr.as_slice(), values.as_slice());
+
+        let arr_ref = arr.into_array_ref();
+        assert_eq!(arr_ref.data_type(), &DataType::FixedSizeBinary(2));
+        let sliced = BFloat16Array::try_from_array(arr_ref.slice(1, 2).as_ref()).unwrap();
+        assert_eq!(sliced.as_slice(), &values[1..]);
+
+        let f32_arr = cast_float_array::<Float32Type>(arr_ref.as_ref()).unwrap();
+        assert_eq!(f32_arr, Float32Array::from(vec![1.0, -2.5, 3.0]));
+        assert!(BFloat16Array::try_from_array(&f32_arr).is_err());
+    }
+}
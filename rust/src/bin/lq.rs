@@ -109,6 +109,16 @@ async fn main() -> Result<()> {
             );
             println!("Total records: {}", dataset.count_rows().await.unwrap());
             println!("Schema:\n{}", dataset.schema());
+            let aux_metadata = dataset
+                .version_aux_metadata(dataset.version().version)
+                .await
+                .unwrap();
+            if !aux_metadata.is_empty() {
+                println!("Version metadata:");
+                for (key, value) in aux_metadata.iter() {
+                    println!("  {}: {}", key, String::from_utf8_lossy(value));
+                }
+            }
 
             Ok(())
         }
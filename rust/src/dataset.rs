@@ -15,44 +15,67 @@
 //! Lance Dataset
 //!
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
+use std::iter::Peekable;
 use std::sync::Arc;
 use std::time::SystemTime;
 
 use arrow_array::{
     cast::as_struct_array, RecordBatch, RecordBatchReader, StructArray, UInt64Array,
 };
-use arrow_schema::{DataType, Schema as ArrowSchema};
+use arrow_schema::{ArrowError, DataType, Schema as ArrowSchema};
 use arrow_select::{concat::concat_batches, take::take};
 use chrono::prelude::*;
 use futures::stream::{self, StreamExt, TryStreamExt};
 use object_store::path::Path;
 use uuid::Uuid;
 
+mod cleanup;
+mod compaction;
+mod delete;
+mod diff;
+pub(crate) mod fragment;
+mod merge;
 pub mod scanner;
+mod schema_evolution;
+mod tags;
 mod write;
+mod writer;
 
+use self::fragment::FragmentReader;
 use self::scanner::Scanner;
 use crate::arrow::*;
-use crate::datatypes::Schema;
+use crate::datatypes::{Field, Schema};
 use crate::format::{pb, Fragment, Index, Manifest};
 use crate::index::{
     vector::{ivf::IvfPqIndexBuilder, VectorIndexParams},
     IndexBuilder, IndexParams, IndexType,
 };
 use crate::io::{
+    deletion::{read_deletion_file, DeletionVector, DeletionVectorCache},
     object_reader::{read_message, read_struct},
-    read_manifest, read_metadata_offset, write_manifest, FileReader, FileWriter, ObjectStore,
+    read_manifest, read_metadata_offset, write_manifest, CommitHandler, FileWriter, ObjectStore,
 };
 use crate::utils::distance::simd_alignment;
 use crate::{Error, Result};
+pub use cleanup::RemovalStats;
+pub(crate) use compaction::RowIdRemap;
+pub use diff::DatasetDiff;
+pub use fragment::{FileFragment, FragmentMetadata};
 pub use scanner::ROW_ID;
+pub use schema_evolution::ColumnAlteration;
 pub use write::*;
+pub use writer::DatasetWriter;
 
 const LATEST_MANIFEST_NAME: &str = "_latest.manifest";
 const VERSIONS_DIR: &str = "_versions";
 const INDICES_DIR: &str = "_indices";
+const DELETIONS_DIR: &str = "_deletions";
 const DATA_DIR: &str = "data";
+const TAGS_DIR: &str = "_tags";
+
+/// Max number of times to rebase and retry a commit that conflicts with other writers.
+const MAX_COMMIT_RETRIES: usize = 10;
 
 /// Lance Dataset
 #[derive(Debug, Clone)]
@@ -60,6 +83,10 @@ pub struct Dataset {
     pub(crate) object_store: Arc<ObjectStore>,
     base: Path,
     pub(crate) manifest: Arc<Manifest>,
+    commit_handler: Arc<dyn CommitHandler>,
+
+    /// Deletion vectors of this version, shared by the clones of the dataset.
+    deletion_vectors: Arc<DeletionVectorCache>,
 }
 
 /// Dataset Version
@@ -86,11 +113,11 @@ impl From<&Manifest> for Version {
 }
 
 /// Create a new [FileWriter] with the related `data_file_path` under `<DATA_DIR>`.
-async fn new_file_writer<'a>(
-    object_store: &'a ObjectStore,
+async fn new_file_writer(
+    object_store: &ObjectStore,
     data_file_path: &str,
-    schema: &'a Schema,
-) -> Result<FileWriter<'a>> {
+    schema: &Schema,
+) -> Result<FileWriter> {
     let full_path = object_store
         .base_path()
         .child(DATA_DIR)
@@ -98,24 +125,420 @@ async fn new_file_writer<'a>(
     FileWriter::try_new(object_store, &full_path, schema).await
 }
 
+/// The schema of the batches to write, with the dictionary values of the first batch.
+fn peek_schema(
+    batches: &mut Peekable<impl Iterator<Item = std::result::Result<RecordBatch, ArrowError>>>,
+) -> Result<Schema> {
+    match batches.peek() {
+        Some(Ok(batch)) => {
+            let mut schema = Schema::try_from(batch.schema().as_ref())?;
+            schema.set_dictionary(batch)?;
+            Ok(schema)
+        }
+        Some(Err(e)) => Err(Error::from(e)),
+        None => Err(Error::IO(
+            "Attempt to write empty record batches".to_string(),
+        )),
+    }
+}
+
+/// Match the columns of the appended data to the fields of the dataset by name.
+///
+/// Returns the projection of `dataset_schema` over the columns in `schema`, which keeps
+/// the existing field IDs and the field order of the dataset. The dataset columns missing from
+/// the appended data must be nullable, and they are read as nulls from the new fragments.
+///
+/// The errors are reported for `operation`, i.e., "Append".
+fn project_schema_for_append(
+    dataset_schema: &Schema,
+    schema: &Schema,
+    operation: &str,
+) -> Result<Schema> {
+    for field in schema.fields.iter() {
+        let Some(dataset_field) = dataset_schema.field(&field.name) else {
+            return Err(Error::Schema(format!(
+                "{operation} with different schema: column '{}' does not exist in the dataset",
+                field.name
+            )));
+        };
+        if dataset_field.data_type() != field.data_type() {
+            return Err(Error::Schema(format!(
+                "{operation} with different schema: column '{}' has type {:?} in the dataset, but {:?} in the new data",
+                field.name,
+                dataset_field.data_type(),
+                field.data_type()
+            )));
+        }
+        if field.data_type().is_dictionary() {
+            let values = |f: &Field| f.dictionary.as_ref().and_then(|d| d.values.clone());
+            if values(dataset_field) != values(field) {
+                return Err(Error::Schema(format!(
+                    "{operation} with different schema: the dictionary values of column '{}' do not match",
+                    field.name
+                )));
+            }
+        }
+    }
+
+    let mut columns = vec![];
+    for field in dataset_schema.fields.iter() {
+        if schema.field(&field.name).is_some() {
+            columns.push(field.name.as_str());
+        } else if !field.nullable {
+            return Err(Error::Schema(format!(
+                "{operation} with different schema: non-nullable column '{}' is missing in the new data",
+                field.name
+            )));
+        }
+    }
+    dataset_schema.project(&columns)
+}
+
+/// Rebase the `new_fragments` onto the `base` version that was committed concurrently.
+///
+/// `batch_schema` is the schema of the appended data, and the `new_fragments` were written
+/// with its projection `write_schema` over the dataset.
+fn rebase_append(
+    base: &Manifest,
+    batch_schema: &Schema,
+    write_schema: &Schema,
+    new_fragments: &[Fragment],
+) -> Result<Manifest> {
+    let conflict = |reason: String| Error::CommitConflict {
+        version: base.version,
+        message: format!(
+            "can not rebase the append onto version {}: {reason}",
+            base.version
+        ),
+    };
+    let projected = project_schema_for_append(&base.schema, batch_schema, "Append")
+        .map_err(|e| conflict(e.to_string()))?;
+    if projected.field_ids() != write_schema.field_ids() {
+        return Err(conflict("the schema has changed".to_string()));
+    }
+
+    let mut fragments = base.fragments.as_ref().clone();
+    let mut fragment_id = base.next_fragment_id();
+    for fragment in new_fragments {
+        // The new fragments are not referenced by the indices yet, so they can be renumbered.
+        let mut fragment = fragment.clone();
+        fragment.id = fragment_id;
+        fragment_id += 1;
+        fragments.push(fragment);
+    }
+    let mut manifest = Manifest::new(&base.schema, Arc::new(fragments));
+    manifest.version = base.version + 1;
+    manifest.max_fragment_id = base.max_fragment_id;
+    Ok(manifest)
+}
+
+/// A data file that is open in [FragmentWriter].
+struct OpenFile {
+    writer: FileWriter,
+
+    /// The next group of the file, written together with the groups of the other open files.
+    pending: Option<RecordBatch>,
+}
+
+/// Number the fragments written by [Fragment::create] from `fragment_id`, after checking
+/// that their fields are in `schema`.
+fn assign_fragment_ids(
+    fragments: &[FragmentMetadata],
+    schema: &Schema,
+    mut fragment_id: u64,
+) -> Result<Vec<Fragment>> {
+    let field_ids: HashSet<i32> = schema.field_ids().into_iter().collect();
+    let mut assigned = vec![];
+    for metadata in fragments {
+        if metadata.files.is_empty() {
+            return Err(Error::IO(
+                "Commit: the fragment does not have any data file".to_string(),
+            ));
+        }
+        if let Some(field_id) = metadata
+            .files
+            .iter()
+            .flat_map(|f| f.fields.iter())
+            .find(|id| !field_ids.contains(id))
+        {
+            return Err(Error::Schema(format!(
+                "Commit: field {field_id} of the fragment does not exist in the dataset schema"
+            )));
+        }
+        let mut fragment = Fragment::new(fragment_id);
+        fragment.files = metadata.files.clone();
+        assigned.push(fragment);
+        fragment_id += 1;
+    }
+    Ok(assigned)
+}
+
+/// Write batches into new data files, one fragment per file, with the fragment IDs
+/// starting from `fragment_id`.
+///
+/// The batches are split into groups of at most `max_rows_per_group` rows. Up to
+/// `max_concurrent_files` files are open at a time, and the groups are dealt to them in turn,
+/// so the next group of every open file is written concurrently. A file is closed, and
+/// replaced by a new one, once it has `max_rows_per_file` rows or `max_bytes_per_file` bytes.
+pub(crate) struct FragmentWriter {
+    object_store: ObjectStore,
+    schema: Schema,
+    params: WriteParams,
+    fragment_id: u64,
+
+    /// The fragments of the files that have been opened.
+    fragments: Vec<Fragment>,
+
+    /// The open files, `None` if the next file has not been opened yet.
+    open_files: Vec<Option<OpenFile>>,
+    /// The index in `open_files` of the file for the next group.
+    next_file: usize,
+
+    /// The rows of the next group.
+    buffer: RecordBatchBuffer,
+    buffered_bytes: usize,
+}
+
+impl FragmentWriter {
+    pub(crate) fn new(
+        object_store: &ObjectStore,
+        schema: &Schema,
+        fragment_id: u64,
+        params: &WriteParams,
+    ) -> Self {
+        let mut open_files = vec![];
+        open_files.resize_with(params.max_concurrent_files.max(1), || None);
+        Self {
+            object_store: object_store.clone(),
+            schema: schema.clone(),
+            params: params.clone(),
+            fragment_id,
+            fragments: vec![],
+            open_files,
+            next_file: 0,
+            buffer: RecordBatchBuffer::empty(),
+            buffered_bytes: 0,
+        }
+    }
+
+    /// Write a batch. The full groups are written to the files as they fill up.
+    pub(crate) async fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        let max_bytes_per_group = self.params.max_bytes_per_group.unwrap_or(usize::MAX);
+        // Estimated from the Arrow buffers, which may be shared with other batches.
+        let batch_bytes = batch
+            .columns()
+            .iter()
+            .map(|c| c.get_array_memory_size())
+            .sum::<usize>();
+        let bytes_per_row = (batch_bytes / batch.num_rows().max(1)).max(1);
+
+        // Split the batch, so that each group and file has at most the max number of rows.
+        // The file of the next group never has a pending group, see `flush`.
+        let mut offset = 0;
+        while offset < batch.num_rows() {
+            let rows_in_file = self.open_files[self.next_file]
+                .as_ref()
+                .map_or(0, |f| f.writer.len());
+            let num_rows = (self.params.max_rows_per_group - self.buffer.num_rows())
+                .min(self.params.max_rows_per_file - rows_in_file - self.buffer.num_rows())
+                .min(
+                    (max_bytes_per_group.saturating_sub(self.buffered_bytes) / bytes_per_row)
+                        .max(1),
+                )
+                .min(batch.num_rows() - offset);
+            self.buffer.batches.push(batch.slice(offset, num_rows));
+            self.buffered_bytes += num_rows * bytes_per_row;
+            offset += num_rows;
+
+            if self.buffer.num_rows() >= self.params.max_rows_per_group
+                || rows_in_file + self.buffer.num_rows() >= self.params.max_rows_per_file
+                || self.buffered_bytes >= max_bytes_per_group
+            {
+                self.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the buffered rows as a group to the next file.
+    ///
+    /// The groups are written once every open file has one, so that at most one group per
+    /// file is kept in memory.
+    pub(crate) async fn flush(&mut self) -> Result<()> {
+        if self.buffer.num_rows() == 0 {
+            return Ok(());
+        }
+        let slot = &mut self.open_files[self.next_file];
+        if slot.is_none() {
+            let file_path = format!("{}.lance", Uuid::new_v4());
+            let writer = new_file_writer(&self.object_store, &file_path, &self.schema).await?;
+            self.fragments.push(Fragment::with_file(
+                self.fragment_id,
+                &file_path,
+                &self.schema,
+            ));
+            self.fragment_id += 1;
+            *slot = Some(OpenFile {
+                writer,
+                pending: None,
+            });
+        }
+        let group = std::mem::replace(&mut self.buffer, RecordBatchBuffer::empty()).finish()?;
+        self.buffered_bytes = 0;
+        slot.as_mut().unwrap().pending = Some(group);
+
+        self.next_file = (self.next_file + 1) % self.open_files.len();
+        if self.open_files[self.next_file]
+            .as_ref()
+            .map_or(false, |f| f.pending.is_some())
+        {
+            self.write_pending_groups().await?;
+        }
+        Ok(())
+    }
+
+    /// Write the pending groups concurrently, and close the files that are full.
+    async fn write_pending_groups(&mut self) -> Result<()> {
+        let writes = self.open_files.iter_mut().flatten().filter_map(|file| {
+            let group = file.pending.take()?;
+            Some(async move { file.writer.write(&group).await })
+        });
+        futures::future::try_join_all(writes).await?;
+
+        let max_bytes_per_file = self.params.max_bytes_per_file.unwrap_or(usize::MAX);
+        let mut full_files = vec![];
+        for slot in self.open_files.iter_mut() {
+            if slot.as_ref().map_or(false, |f| {
+                f.writer.len() >= self.params.max_rows_per_file
+                    || f.writer.tell() >= max_bytes_per_file
+            }) {
+                full_files.extend(slot.take());
+            }
+        }
+        close_files(full_files).await
+    }
+
+    /// Write the remaining rows and close all the files. Returns the new fragments.
+    pub(crate) async fn finish(mut self) -> Result<Vec<Fragment>> {
+        self.flush().await?;
+        self.write_pending_groups().await?;
+        close_files(self.open_files.into_iter().flatten().collect()).await?;
+        Ok(self.fragments)
+    }
+}
+
+/// Finish writing the files concurrently.
+async fn close_files(mut files: Vec<OpenFile>) -> Result<()> {
+    futures::future::try_join_all(files.iter_mut().map(|f| f.writer.finish())).await?;
+    Ok(())
+}
+
+/// Write `batches` into new data files, one fragment per file, with the fragment IDs
+/// starting from `fragment_id`.
+async fn write_fragments(
+    object_store: &ObjectStore,
+    schema: &Schema,
+    batches: impl Iterator<Item = std::result::Result<RecordBatch, ArrowError>>,
+    fragment_id: u64,
+    params: &WriteParams,
+) -> Result<Vec<Fragment>> {
+    let mut writer = FragmentWriter::new(object_store, schema, fragment_id, params);
+    for batch in batches {
+        writer.write(&batch?).await?;
+    }
+    writer.finish().await
+}
+
+/// Collect the fields and their nested fields in pre-order.
+fn flatten_fields(fields: &[Field]) -> Vec<&Field> {
+    fields
+        .iter()
+        .flat_map(|f| {
+            let mut flattened = vec![f];
+            flattened.extend(flatten_fields(&f.children));
+            flattened
+        })
+        .collect()
+}
+
 /// Get the manifest file path for a version.
 fn manifest_path(base: &Path, version: u64) -> Path {
     base.child(VERSIONS_DIR)
         .child(format!("{version}.manifest"))
 }
 
-/// Get the latest manifest path
-fn latest_manifest_path(base: &Path) -> Path {
+/// Get the path of `_latest.manifest`, the copy of the latest manifest for the older readers.
+fn latest_manifest_link_path(base: &Path) -> Path {
     base.child(LATEST_MANIFEST_NAME)
 }
 
+/// Get the manifest path of the latest version. Returns `None` if the dataset does not exist.
+///
+/// `_latest.manifest` is updated after the commit and may lag behind, so the versions after
+/// it are probed in `_versions/`. Without `_latest.manifest`, i.e., if the first commit did
+/// not finish, the committed manifests are listed instead.
+async fn latest_manifest_path(object_store: &ObjectStore) -> Result<Option<Path>> {
+    let base = object_store.base_path();
+    let latest_manifest = latest_manifest_link_path(base);
+    if !object_store.exists(&latest_manifest).await? {
+        return list_latest_manifest_path(object_store).await;
+    }
+    let mut version = read_manifest(object_store, &latest_manifest).await?.version;
+    while object_store
+        .exists(&manifest_path(base, version + 1))
+        .await?
+    {
+        version += 1;
+    }
+    Ok(Some(manifest_path(base, version)))
+}
+
+/// Get the manifest path of the latest version, by listing the committed manifests in
+/// `_versions/`.
+async fn list_latest_manifest_path(object_store: &ObjectStore) -> Result<Option<Path>> {
+    Ok(list_manifest_paths(object_store)
+        .await?
+        .pop()
+        .map(|(_, path)| path))
+}
+
+/// List the committed versions in `_versions/`, with their manifest paths, in ascending order.
+async fn list_manifest_paths(object_store: &ObjectStore) -> Result<Vec<(u64, Path)>> {
+    let versions_dir = object_store.base_path().child(VERSIONS_DIR);
+    let mut versions = object_store
+        .inner
+        .list_with_delimiter(Some(&versions_dir))
+        .await?
+        .objects
+        .into_iter()
+        .filter_map(|obj| {
+            let version = obj
+                .location
+                .filename()?
+                .strip_suffix(".manifest")?
+                .parse::<u64>()
+                .ok()?;
+            Some((version, obj.location))
+        })
+        .collect::<Vec<_>>();
+    versions.sort_by_key(|(version, _)| *version);
+    Ok(versions)
+}
+
+/// Get the path of the tag `name`.
+fn tag_path(base: &Path, name: &str) -> Path {
+    base.child(TAGS_DIR).child(format!("{name}.txt"))
+}
+
 impl Dataset {
     /// Open an existing dataset.
     pub async fn open(uri: &str) -> Result<Self> {
         let object_store = Arc::new(ObjectStore::new(uri).await?);
 
         let base_path = object_store.base_path().clone();
-        let latest_manifest_path = latest_manifest_path(&base_path);
+        let Some(latest_manifest_path) = latest_manifest_path(&object_store).await? else {
+            return Err(Error::IO(format!("Dataset does not exist: {uri}")));
+        };
         Self::checkout_manifest(object_store, base_path, &latest_manifest_path).await
     }
 
@@ -128,6 +551,34 @@ impl Dataset {
         Self::checkout_manifest(object_store, base_path, &manifest_file).await
     }
 
+    /// Check out the latest version of the dataset committed at or before `timestamp`.
+    ///
+    /// The versions are committed in order, and so are their timestamps, so only the manifests
+    /// of a binary search over the versions in `_versions/` are read.
+    pub async fn checkout_as_of(uri: &str, timestamp: DateTime<Utc>) -> Result<Self> {
+        let object_store = Arc::new(ObjectStore::new(uri).await?);
+
+        let base_path = object_store.base_path().clone();
+        let versions = list_manifest_paths(&object_store).await?;
+        // The number of versions committed at or before `timestamp`.
+        let (mut low, mut high) = (0, versions.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            let manifest = read_manifest(&object_store, &versions[mid].1).await?;
+            if manifest.timestamp() <= timestamp {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        let Some((_, manifest_file)) = low.checked_sub(1).map(|idx| &versions[idx]) else {
+            return Err(Error::IO(format!(
+                "No version of the dataset {uri} was committed at or before {timestamp}"
+            )));
+        };
+        Self::checkout_manifest(object_store, base_path, manifest_file).await
+    }
+
     async fn checkout_manifest(
         object_store: Arc<ObjectStore>,
         base_path: Path,
@@ -141,145 +592,123 @@ impl Dataset {
             .schema
             .load_dictionary(object_reader.as_ref())
             .await?;
+        let commit_handler = object_store.commit_handler();
         Ok(Self {
             object_store,
             base: base_path,
             manifest: Arc::new(manifest),
+            commit_handler,
+            deletion_vectors: Default::default(),
         })
     }
 
     /// Write to or Create a [Dataset] with a stream of [RecordBatch]s.
     ///
     /// Returns the newly created [`Dataset`]. Returns [Error] if the dataset already exists.
+    ///
+    /// In [WriteMode::Append], the columns are matched to the dataset schema by name,
+    /// so they can be in a different order. The nullable columns can be absent from the
+    /// appended data, and they are read as nulls.
     pub async fn write(
         batches: &mut Box<dyn RecordBatchReader>,
         uri: &str,
         params: Option<WriteParams>,
     ) -> Result<Self> {
-        let object_store = Arc::new(ObjectStore::new(uri).await?);
-        let params = params.unwrap_or_default();
-
-        let latest_manifest_path = latest_manifest_path(object_store.base_path());
-        let latest_manifest = if matches!(params.mode, WriteMode::Create) {
-            if object_store.exists(&latest_manifest_path).await? {
-                return Err(Error::IO(format!("Dataset already exists: {uri}")));
-            }
-            None
-        } else {
-            Some(read_manifest(&object_store, &latest_manifest_path).await?)
-        };
-
-        let mut peekable = batches.peekable();
-        let mut schema: Schema;
-        if let Some(batch) = peekable.peek() {
-            if let Ok(b) = batch {
-                schema = Schema::try_from(b.schema().as_ref())?;
-                schema.set_dictionary(b)?;
-            } else {
-                return Err(Error::from(batch.as_ref().unwrap_err()));
-            }
-        } else {
-            return Err(Error::IO(
-                "Attempt to write empty record batches".to_string(),
-            ));
-        }
-
-        if matches!(params.mode, WriteMode::Append) {
-            if let Some(m) = latest_manifest.as_ref() {
-                if schema != m.schema {
-                    return Err(Error::IO(format!(
-                        "Append with different schema: original={} new={}",
-                        m.schema, schema
-                    )));
-                }
-            }
+        let mut writer = DatasetWriter::try_new(uri, params).await?;
+        for batch in batches {
+            writer.write(&batch?).await?;
         }
+        writer.commit().await
+    }
 
-        let mut fragment_id = if matches!(params.mode, WriteMode::Append) {
-            latest_manifest.as_ref().map_or(0, |m| {
-                m.fragments
-                    .iter()
-                    .map(|f| f.id)
-                    .max()
-                    .map(|id| id + 1)
-                    .unwrap_or(0)
-            })
-        } else {
-            // Create or Overwrite.
-            // Overwrite resets the fragment ID to zero.
-            0
-        };
+    /// Commit the fragments written by [Fragment::create] as a new version of the dataset.
+    ///
+    /// The fragment IDs are assigned in the order of the fragments. If another writer has
+    /// committed the same version concurrently, the operation is applied on top of it.
+    ///
+    /// The version is committed by `commit_handler`, or by the default of the object store.
+    pub async fn commit(
+        uri: &str,
+        operation: Operation,
+        commit_handler: Option<Arc<dyn CommitHandler>>,
+    ) -> Result<Self> {
+        let object_store = Arc::new(ObjectStore::new(uri).await?);
+        let commit_handler = commit_handler.unwrap_or_else(|| object_store.commit_handler());
 
-        let mut fragments: Vec<Fragment> = if matches!(params.mode, WriteMode::Append) {
-            latest_manifest
-                .as_ref()
-                .map_or(vec![], |m| m.fragments.as_ref().clone())
-        } else {
-            // Create or Overwrite create new fragments.
-            vec![]
+        let mut base_manifest = match latest_manifest_path(&object_store).await? {
+            Some(path) => Some(read_manifest_with_dictionary(&object_store, &path).await?),
+            None => None,
         };
-
-        macro_rules! new_writer {
-            () => {{
-                let file_path = format!("{}.lance", Uuid::new_v4());
-                let fragment = Fragment::with_file(fragment_id, &file_path, &schema);
-                fragments.push(fragment);
-                fragment_id += 1;
-                Some(new_file_writer(&object_store, &file_path, &schema).await?)
-            }};
-        }
-
-        let mut writer = None;
-        let mut buffer = RecordBatchBuffer::empty();
-        for batch_result in peekable {
-            let batch = batch_result?;
-            buffer.batches.push(batch);
-            if buffer.num_rows() >= params.max_rows_per_group {
-                // TODO: the max rows per group boundary is not accurately calculated yet.
-                if writer.is_none() {
-                    writer = new_writer!();
-                };
-                writer.as_mut().unwrap().write(&buffer.finish()?).await?;
-                buffer = RecordBatchBuffer::empty();
-            }
-            if let Some(w) = writer.as_mut() {
-                if w.len() >= params.max_rows_per_file {
-                    w.finish().await?;
-                    writer = None;
+        let mut num_retries = 0;
+        loop {
+            let mut manifest = match &operation {
+                Operation::Append(fragments) => {
+                    let Some(base) = base_manifest.as_ref() else {
+                        return Err(Error::IO(format!("Dataset does not exist: {uri}")));
+                    };
+                    let fragments =
+                        assign_fragment_ids(fragments, &base.schema, base.next_fragment_id())?;
+                    let mut all_fragments = base.fragments.as_ref().clone();
+                    all_fragments.extend(fragments);
+                    let mut manifest = Manifest::new(&base.schema, Arc::new(all_fragments));
+                    manifest.max_fragment_id = base.max_fragment_id;
+                    manifest
+                }
+                Operation::Overwrite { fragments, schema } => {
+                    let schema = Schema::try_from(schema)?;
+                    if let Some(field) = flatten_fields(&schema.fields)
+                        .into_iter()
+                        .find(|f| f.data_type().is_dictionary())
+                    {
+                        return Err(Error::Schema(format!(
+                            "Commit: dictionary column '{}' is not supported",
+                            field.name
+                        )));
+                    }
+                    // The fragment IDs continue from the previous versions, so that the row
+                    // IDs in the old indices are never reused.
+                    let fragment_id = base_manifest.as_ref().map_or(0, |m| m.next_fragment_id());
+                    let fragments = assign_fragment_ids(fragments, &schema, fragment_id)?;
+                    let mut manifest = Manifest::new(&schema, Arc::new(fragments));
+                    manifest.max_fragment_id =
+                        base_manifest.as_ref().and_then(|m| m.max_fragment_id);
+                    manifest
                 }
-            }
-        }
-        if buffer.num_rows() > 0 {
-            if writer.is_none() {
-                writer = new_writer!();
             };
-            writer.as_mut().unwrap().write(&buffer.finish()?).await?;
-        }
-        if let Some(w) = writer.as_mut() {
-            // Drop the last writer.
-            w.finish().await?;
-            drop(writer);
-        };
-
-        let mut manifest = Manifest::new(&schema, Arc::new(fragments));
-        manifest.version = latest_manifest.map_or(1, |m| m.version + 1);
-        if matches!(params.mode, WriteMode::Overwrite) {
-            // If overwrite, invalidate index
-            manifest.index_section = None;
+            manifest.version = base_manifest.as_ref().map_or(1, |m| m.version + 1);
+
+            let result = write_manifest_file(
+                &object_store,
+                commit_handler.as_ref(),
+                &mut manifest,
+                None,
+                None,
+            )
+            .await;
+            match result {
+                Err(Error::CommitConflict { version, .. }) if num_retries < MAX_COMMIT_RETRIES => {
+                    num_retries += 1;
+                    base_manifest = Some(
+                        read_manifest_with_dictionary(
+                            &object_store,
+                            &manifest_path(object_store.base_path(), version),
+                        )
+                        .await?,
+                    );
+                }
+                _ => {
+                    result?;
+                    let base = object_store.base_path().clone();
+                    return Ok(Self {
+                        object_store,
+                        base,
+                        manifest: Arc::new(manifest),
+                        commit_handler,
+                        deletion_vectors: Default::default(),
+                    });
+                }
+            }
         }
-        let duration_since_epoch = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap();
-        let timestamp_nanos = duration_since_epoch.as_nanos(); // u128
-        manifest.timestamp_nanos = timestamp_nanos;
-        write_manifest_file(&object_store, &mut manifest, None).await?;
-
-        let base = object_store.base_path().clone();
-        Ok(Self {
-            object_store,
-            base,
-            manifest: Arc::new(manifest.clone()),
-        })
     }
 
     /// Create a Scanner to scan the dataset.
@@ -294,15 +723,8 @@ impl Dataset {
         // Open file to read metadata.
         let counts = stream::iter(self.manifest.fragments.as_ref())
             .map(|f| async {
-                let path = self.data_dir().child(f.files[0].path.as_str());
-                let reader = FileReader::try_new_with_fragment(
-                    &self.object_store,
-                    &path,
-                    f.id,
-                    Some(self.manifest.as_ref()),
-                )
-                .await?;
-                Ok::<usize, Error>(reader.len())
+                let num_rows = self.count_physical_rows(f).await?;
+                Ok::<usize, Error>(num_rows - f.num_deleted_rows())
             })
             .buffer_unordered(16)
             .try_collect::<Vec<_>>()
@@ -310,6 +732,69 @@ impl Dataset {
         Ok(counts.iter().sum())
     }
 
+    /// Count the number of rows stored in the data file of a fragment, including deleted rows.
+    async fn count_physical_rows(&self, fragment: &Fragment) -> Result<usize> {
+        let reader = FragmentReader::try_new(self, fragment).await?;
+        Ok(reader.len())
+    }
+
+    /// Load the [DeletionVector] of a fragment. Returns `None` if it does not have deleted rows.
+    ///
+    /// The deletion vectors are cached, so each deletion file is read at most once per version.
+    pub(crate) async fn deletion_vector(
+        &self,
+        fragment: &Fragment,
+    ) -> Result<Option<Arc<DeletionVector>>> {
+        let Some(deletion_file) = fragment.deletion_file.as_ref() else {
+            return Ok(None);
+        };
+        let path = self.deletions_dir().child(deletion_file.path.as_str());
+        let deletion_vector = self
+            .deletion_vectors
+            .get_or_load(
+                fragment.id,
+                read_deletion_file(&self.object_store, &path, deletion_file.num_deleted_rows),
+            )
+            .await?;
+        Ok(Some(deletion_vector))
+    }
+
+    /// Commit `manifest` as the next version of this dataset.
+    ///
+    /// Returns [Error::CommitConflict] if another writer has committed a new version since
+    /// this version was read.
+    async fn commit_manifest(
+        &self,
+        mut manifest: Manifest,
+        indices: Option<Vec<Index>>,
+    ) -> Result<Self> {
+        manifest.version = self.manifest.version + 1;
+        manifest.index_section = None;
+
+        write_manifest_file(
+            &self.object_store,
+            self.commit_handler.as_ref(),
+            &mut manifest,
+            indices,
+            None,
+        )
+        .await?;
+
+        Ok(Self {
+            object_store: self.object_store.clone(),
+            base: self.base.clone(),
+            manifest: Arc::new(manifest),
+            commit_handler: self.commit_handler.clone(),
+            deletion_vectors: Default::default(),
+        })
+    }
+
+    /// Use `commit_handler` to commit the new versions of this dataset.
+    pub fn with_commit_handler(mut self, commit_handler: Arc<dyn CommitHandler>) -> Self {
+        self.commit_handler = commit_handler;
+        self
+    }
+
     /// Create indices on columns.
     ///
     /// Upon finish, a new dataset version is generated.
@@ -398,19 +883,12 @@ impl Dataset {
         let new_idx = Index::new(index_id, &index_name, &[field.id]);
         indices.push(new_idx);
 
-        let latest_manifest = self.latest_manifest().await?;
-        let mut new_manifest = self.manifest.as_ref().clone();
-        new_manifest.version = latest_manifest.version + 1;
-
-        write_manifest_file(&self.object_store, &mut new_manifest, Some(indices)).await?;
-
-        Ok(Self {
-            object_store: self.object_store.clone(),
-            base: self.base.clone(),
-            manifest: Arc::new(new_manifest),
-        })
+        let manifest = self.manifest.as_ref().clone();
+        self.commit_manifest(manifest, Some(indices)).await
     }
 
+    /// Take rows by their positions in the dataset, which are the positions of the rows in a
+    /// full scan. The deleted rows are skipped.
     pub async fn take(&self, row_indices: &[usize], projection: &Schema) -> Result<RecordBatch> {
         let mut sorted_indices: Vec<u32> =
             Vec::from_iter(row_indices.iter().map(|indice| *indice as u32));
@@ -419,43 +897,30 @@ impl Dataset {
         let mut row_count = 0;
         let mut start = 0;
         let schema = Arc::new(ArrowSchema::from(projection));
-        let object_store = &self.object_store;
         let mut batches = Vec::with_capacity(sorted_indices.len());
         for fragment in self.fragments().iter() {
             if start >= sorted_indices.len() {
                 break;
             }
 
-            let path = self.data_dir().child(fragment.files[0].path.as_str());
-            let mut reader = FileReader::try_new_with_fragment(
-                object_store,
-                &path,
-                fragment.id,
-                Some(self.manifest.as_ref()),
-            )
-            .await?;
-
-            let max_row_indices = row_count + reader.len() as u32;
-            if sorted_indices[start] < max_row_indices {
-                let mut end = start;
-                sorted_indices[end] -= row_count;
-                while end + 1 < sorted_indices.len() && sorted_indices[end + 1] < max_row_indices {
-                    end += 1;
-                    sorted_indices[end] -= row_count;
-                }
-                reader.set_projection(projection.clone());
-                batches.push(
-                    reader
-                        .take(&sorted_indices[start..end + 1], projection)
-                        .await?,
-                );
-
-                // restore the row indices
-                for indice in sorted_indices[start..end + 1].iter_mut() {
-                    *indice += row_count;
-                }
+            let reader = FragmentReader::try_new(self, fragment).await?;
+            let deletion_vector = self.deletion_vector(fragment).await?;
+            // The deleted rows do not count in the row indices.
+            let num_rows = reader.len() - deletion_vector.as_ref().map_or(0, |dv| dv.len());
 
-                start = end + 1;
+            let max_row_indices = row_count + num_rows as u32;
+            let end = start + sorted_indices[start..].partition_point(|i| *i < max_row_indices);
+            if end > start {
+                let positions = sorted_indices[start..end]
+                    .iter()
+                    .map(|i| i - row_count)
+                    .collect::<Vec<_>>();
+                let offsets = match deletion_vector {
+                    Some(dv) => dv.physical_offsets(&positions),
+                    None => positions,
+                };
+                batches.push(reader.take(&offsets, projection).await?);
+                start = end;
             }
             row_count = max_row_indices;
         }
@@ -490,22 +955,13 @@ impl Dataset {
                 .or_insert_with(|| vec![offset]);
         });
         let schema = Arc::new(ArrowSchema::from(projection));
-        let object_store = &self.object_store;
         let batches = stream::iter(self.fragments().as_ref())
             .filter(|f| async { row_ids_per_fragment.contains_key(&f.id) })
             .then(|fragment| async {
                 let Some(indices) = row_ids_per_fragment.get(&fragment.id) else {
                     return Ok(RecordBatch::new_empty(schema.clone()));
                 };
-                let path = self.data_dir().child(fragment.files[0].path.as_str());
-                let mut reader = FileReader::try_new_with_fragment(
-                    object_store,
-                    &path,
-                    fragment.id,
-                    Some(self.manifest.as_ref()),
-                )
-                .await?;
-                reader.set_projection(projection.clone());
+                let reader = FragmentReader::try_new(self, fragment).await?;
                 reader.take(indices.as_slice(), projection).await
             })
             .try_collect::<Vec<_>>()
@@ -533,12 +989,14 @@ impl Dataset {
         self.versions_dir().child(format!("{version}.manifest"))
     }
 
-    fn latest_manifest_path(&self) -> Path {
-        latest_manifest_path(&self.base)
-    }
-
     async fn latest_manifest(&self) -> Result<Manifest> {
-        read_manifest(&self.object_store, &self.latest_manifest_path()).await
+        let Some(path) = latest_manifest_path(&self.object_store).await? else {
+            return Err(Error::IO(format!(
+                "Dataset does not exist: {}",
+                self.base.as_ref()
+            )));
+        };
+        read_manifest(&self.object_store, &path).await
     }
 
     pub(crate) fn data_dir(&self) -> Path {
@@ -549,10 +1007,54 @@ impl Dataset {
         self.base.child(INDICES_DIR)
     }
 
+    pub(crate) fn deletions_dir(&self) -> Path {
+        self.base.child(DELETIONS_DIR)
+    }
+
     pub fn version(&self) -> Version {
         Version::from(self.manifest.as_ref())
     }
 
+    /// Restore the dataset to `version`.
+    ///
+    /// It commits a new version on top of the latest version, whose fragments, schema and
+    /// indices are the same as `version`. The versions in between are kept in the history.
+    pub async fn restore(&self, version: u64) -> Result<Self> {
+        let target = Self::checkout_manifest(
+            self.object_store.clone(),
+            self.base.clone(),
+            &self.manifest_file(version),
+        )
+        .await?;
+        let indices = target.load_indices().await?;
+        let latest_manifest = self.latest_manifest().await?;
+
+        let mut manifest = target.manifest.as_ref().clone();
+        manifest.version = latest_manifest.version + 1;
+        manifest.tag = None;
+        manifest.index_section = None;
+        // Do not reuse the IDs of the fragments created after `version`.
+        manifest.max_fragment_id = manifest
+            .max_fragment_id
+            .max(latest_manifest.next_fragment_id().checked_sub(1));
+        write_manifest_file(
+            &self.object_store,
+            self.commit_handler.as_ref(),
+            &mut manifest,
+            Some(indices),
+            None,
+        )
+        .await?;
+
+        Ok(Self {
+            object_store: self.object_store.clone(),
+            base: self.base.clone(),
+            manifest: Arc::new(manifest),
+            commit_handler: self.commit_handler.clone(),
+            deletion_vectors: Default::default(),
+        })
+    }
+
     /// Get all versions.
     pub async fn versions(&self) -> Result<Vec<Version>> {
         let paths: Vec<Path> = self
@@ -573,6 +1075,20 @@ impl Dataset {
         Ok(versions)
     }
 
+    /// Load the key-value metadata stored in the auxiliary data of `version`.
+    ///
+    /// See [WriteParams::commit_metadata]. Returns an empty map if the version does not have it.
+    pub async fn version_aux_metadata(&self, version: u64) -> Result<BTreeMap<String, Vec<u8>>> {
+        let manifest_file = self.manifest_file(version);
+        let manifest = read_manifest(&self.object_store, &manifest_file).await?;
+        let Some(pos) = manifest.version_aux_data else {
+            return Ok(BTreeMap::new());
+        };
+        let reader = self.object_store.open(&manifest_file).await?;
+        let aux_data: pb::VersionAuxData = read_message(reader.as_ref(), pos).await?;
+        Ok(aux_data.metadata.into_iter().collect())
+    }
+
     pub fn schema(&self) -> &Schema {
         &self.manifest.schema
     }
@@ -581,41 +1097,121 @@ impl Dataset {
         &self.manifest.fragments
     }
 
+    /// Get the fragment with the given ID. Returns `None` if it does not exist in this version.
+    pub fn get_fragment(&self, fragment_id: u64) -> Option<FileFragment> {
+        let dataset = Arc::new(self.clone());
+        self.fragments()
+            .iter()
+            .find(|f| f.id == fragment_id)
+            .map(|f| FileFragment::new(dataset, f.clone()))
+    }
+
+    /// Get all the fragments of this version.
+    pub fn get_fragments(&self) -> Vec<FileFragment> {
+        let dataset = Arc::new(self.clone());
+        self.fragments()
+            .iter()
+            .map(|f| FileFragment::new(dataset.clone(), f.clone()))
+            .collect()
+    }
+
     /// Read all indices of this Dataset version.
     pub async fn load_indices(&self) -> Result<Vec<Index>> {
-        if let Some(pos) = self.manifest.index_section.as_ref() {
-            let manifest_file = self.manifest_file(self.version().version);
-
-            let reader = self.object_store.open(&manifest_file).await?;
-            let section: pb::IndexSection = read_message(reader.as_ref(), *pos).await?;
+        let manifest_file = self.manifest_file(self.version().version);
+        read_manifest_indices(&self.object_store, &manifest_file, &self.manifest).await
+    }
+}
 
-            Ok(section
-                .indices
-                .iter()
-                .map(Index::try_from)
-                .collect::<Result<Vec<_>>>()?)
-        } else {
-            Ok(vec![])
-        }
+/// Read the indices of `manifest`, which is stored in `manifest_file`.
+async fn read_manifest_indices(
+    object_store: &ObjectStore,
+    manifest_file: &Path,
+    manifest: &Manifest,
+) -> Result<Vec<Index>> {
+    if let Some(pos) = manifest.index_section.as_ref() {
+        let reader = object_store.open(manifest_file).await?;
+        let section: pb::IndexSection = read_message(reader.as_ref(), *pos).await?;
+
+        Ok(section
+            .indices
+            .iter()
+            .map(Index::try_from)
+            .collect::<Result<Vec<_>>>()?)
+    } else {
+        Ok(vec![])
     }
 }
 
-/// Finish writing the manifest file, and commit the changes by linking the latest manifest file
-/// to this version.
+/// Read the manifest at `path`, with the dictionary values of its schema.
+async fn read_manifest_with_dictionary(
+    object_store: &ObjectStore,
+    path: &Path,
+) -> Result<Manifest> {
+    let mut manifest = read_manifest(object_store, path).await?;
+    let object_reader = object_store.open(path).await?;
+    manifest
+        .schema
+        .load_dictionary(object_reader.as_ref())
+        .await?;
+    Ok(manifest)
+}
+
+/// Finish writing the manifest file, and commit it as a new version.
+///
+/// The manifest is written to a staging path first, and then committed by the `commit_handler`,
+/// which fails with [Error::CommitConflict] if the version already exists.
 async fn write_manifest_file(
     object_store: &ObjectStore,
+    commit_handler: &dyn CommitHandler,
     manifest: &mut Manifest,
     indices: Option<Vec<Index>>,
+    aux_metadata: Option<&BTreeMap<String, Vec<u8>>>,
 ) -> Result<()> {
+    let duration_since_epoch = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    manifest.timestamp_nanos = duration_since_epoch.as_nanos(); // u128
+    manifest.update_max_fragment_id();
+
     let path = manifest_path(object_store.base_path(), manifest.version);
-    let mut object_writer = object_store.create(&path).await?;
+    let staging_path = object_store.base_path().child(VERSIONS_DIR).child(format!(
+        "{}.manifest-{}.tmp",
+        manifest.version,
+        Uuid::new_v4()
+    ));
+    let mut object_writer = object_store.create(&staging_path).await?;
+    // The version aux data is not inherited from the previous version.
+    manifest.version_aux_data = None;
+    if let Some(metadata) = aux_metadata {
+        let aux_data = pb::VersionAuxData {
+            metadata: metadata
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        };
+        manifest.version_aux_data = Some(object_writer.write_protobuf(&aux_data).await?);
+    }
     let pos = write_manifest(&mut object_writer, manifest, indices).await?;
     object_writer.write_magics(pos).await?;
     object_writer.shutdown().await?;
+    if let Err(e) = commit_handler
+        .commit(object_store, &staging_path, &path, manifest.version)
+        .await
+    {
+        // The staging file is left over if the commit failed before moving it.
+        let _ = object_store.inner.delete(&staging_path).await;
+        return Err(e);
+    }
 
-    // Link it to latest manifest, and COMMIT.
-    let latest_manifest = latest_manifest_path(object_store.base_path());
-    object_store.inner.copy(&path, &latest_manifest).await?;
+    // Copy it to `_latest.manifest` for the older readers, unless a newer version has been
+    // committed concurrently. The copy is not atomic with the commit, so it can lag behind;
+    // the latest version is always resolved from `_versions/` instead.
+    let latest_manifest = latest_manifest_link_path(object_store.base_path());
+    if !object_store.exists(&latest_manifest).await?
+        || read_manifest(object_store, &latest_manifest).await?.version < manifest.version
+    {
+        object_store.inner.copy(&path, &latest_manifest).await?;
+    }
 
     Ok(())
 }
@@ -624,18 +1220,25 @@ async fn write_manifest_file(
 mod tests {
     use super::*;
     use crate::{datatypes::Schema, utils::testing::generate_random_array};
+    use std::collections::HashMap;
+    use std::ops::Range;
 
     use crate::dataset::WriteMode::Overwrite;
+    use crate::encodings::{compression::Compression, Encoding, ENCODING_METADATA_KEY};
     use arrow_array::{
-        cast::{as_string_array, as_struct_array},
-        DictionaryArray, FixedSizeListArray, Int32Array, RecordBatch, StringArray, UInt16Array,
+        cast::{as_primitive_array, as_string_array, as_struct_array},
+        ArrayRef, BooleanArray, DictionaryArray, FixedSizeListArray, Int32Array, Int64Array,
+        RecordBatch, StringArray, UInt16Array,
     };
     use arrow_ord::sort::sort_to_indices;
     use arrow_schema::{DataType, Field, Schema as ArrowSchema};
     use arrow_select::take::take;
+    use chrono::Duration;
     use futures::stream::TryStreamExt;
     use tempfile::tempdir;
 
+    use crate::io::FileReader;
+
     #[tokio::test]
     async fn create_dataset() {
         let test_dir = tempdir().unwrap();
@@ -736,7 +1339,7 @@ mod tests {
         write_params.max_rows_per_file = 40;
         write_params.max_rows_per_group = 10;
         let mut batches: Box<dyn RecordBatchReader> = Box::new(batches);
-        Dataset::write(&mut batches, test_uri, Some(write_params))
+        Dataset::write(&mut batches, test_uri, Some(write_params.clone()))
             .await
             .unwrap();
 
@@ -792,17 +1395,21 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn overwrite_dataset() {
+    async fn append_dataset_with_compatible_schema() {
         let test_dir = tempdir().unwrap();
 
-        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
-            "i",
-            DataType::Int32,
-            false,
-        )]));
+        let schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("i", DataType::Int32, false),
+            Field::new("s", DataType::Utf8, true),
+        ]));
         let batches = RecordBatchBuffer::new(vec![RecordBatch::try_new(
             schema.clone(),
-            vec![Arc::new(Int32Array::from_iter_values(0..20))],
+            vec![
+                Arc::new(Int32Array::from_iter_values(0..20)),
+                Arc::new(StringArray::from_iter_values(
+                    (0..20).map(|v| format!("s-{v}")),
+                )),
+            ],
         )
         .unwrap()]);
 
@@ -811,17 +1418,125 @@ mod tests {
         write_params.max_rows_per_file = 40;
         write_params.max_rows_per_group = 10;
         let mut batches: Box<dyn RecordBatchReader> = Box::new(batches);
-        Dataset::write(&mut batches, test_uri, Some(write_params))
+        Dataset::write(&mut batches, test_uri, Some(write_params.clone()))
             .await
             .unwrap();
+        write_params.mode = WriteMode::Append;
 
-        let new_schema = Arc::new(ArrowSchema::new(vec![Field::new(
-            "s",
-            DataType::Utf8,
-            false,
-        )]));
-        let new_batches = RecordBatchBuffer::new(vec![RecordBatch::try_new(
-            new_schema.clone(),
+        // Columns in a different order.
+        let reordered_schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("s", DataType::Utf8, true),
+            Field::new("i", DataType::Int32, false),
+        ]));
+        let batches = RecordBatchBuffer::new(vec![RecordBatch::try_new(
+            reordered_schema,
+            vec![
+                Arc::new(StringArray::from_iter_values(
+                    (20..40).map(|v| format!("s-{v}")),
+                )),
+                Arc::new(Int32Array::from_iter_values(20..40)),
+            ],
+        )
+        .unwrap()]);
+        let mut batches: Box<dyn RecordBatchReader> = Box::new(batches);
+        Dataset::write(&mut batches, test_uri, Some(write_params.clone()))
+            .await
+            .unwrap();
+
+        // Missing a nullable column.
+        let partial_schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            false,
+        )]));
+        let batches = RecordBatchBuffer::new(vec![RecordBatch::try_new(
+            partial_schema,
+            vec![Arc::new(Int32Array::from_iter_values(40..60))],
+        )
+        .unwrap()]);
+        let mut batches: Box<dyn RecordBatchReader> = Box::new(batches);
+        let dataset = Dataset::write(&mut batches, test_uri, Some(write_params.clone()))
+            .await
+            .unwrap();
+        assert_eq!(dataset.version().version, 3);
+        assert_eq!(ArrowSchema::from(dataset.schema()), *schema);
+        // New fragments only store the columns that are appended.
+        assert_eq!(dataset.fragments()[2].files[0].fields, vec![0]);
+
+        let batches = dataset
+            .scan()
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let batch = concat_batches(&schema, &batches).unwrap();
+        let i_arr: &Int32Array = as_primitive_array(batch.column_by_name("i").unwrap());
+        assert_eq!(i_arr.values(), (0..60).collect::<Vec<_>>().as_slice());
+        let s_arr = as_string_array(batch.column_by_name("s").unwrap());
+        assert_eq!(s_arr.value(25), "s-25");
+        assert_eq!(s_arr.null_count(), 20);
+        assert!(s_arr.is_null(45));
+
+        // Type conflict, missing non-nullable columns, and unknown columns are rejected.
+        for (field, arr) in [
+            (
+                Field::new("i", DataType::Int64, false),
+                Arc::new(Int64Array::from_iter_values(0..10)) as ArrayRef,
+            ),
+            (
+                Field::new("s", DataType::Utf8, true),
+                Arc::new(StringArray::from_iter_values(
+                    (0..10).map(|v| v.to_string()),
+                )),
+            ),
+            (
+                Field::new("x", DataType::Int32, false),
+                Arc::new(Int32Array::from_iter_values(0..10)),
+            ),
+        ] {
+            let batch =
+                RecordBatch::try_new(Arc::new(ArrowSchema::new(vec![field])), vec![arr]).unwrap();
+            let mut batches: Box<dyn RecordBatchReader> =
+                Box::new(RecordBatchBuffer::new(vec![batch]));
+            let result = Dataset::write(&mut batches, test_uri, Some(write_params.clone())).await;
+            assert!(matches!(result, Err(Error::Schema(_))));
+        }
+        assert_eq!(Dataset::open(test_uri).await.unwrap().version().version, 3);
+    }
+
+    #[tokio::test]
+    async fn overwrite_dataset() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            false,
+        )]));
+        let batches = RecordBatchBuffer::new(vec![RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from_iter_values(0..20))],
+        )
+        .unwrap()]);
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut write_params = WriteParams::default();
+        write_params.max_rows_per_file = 40;
+        write_params.max_rows_per_group = 10;
+        let mut batches: Box<dyn RecordBatchReader> = Box::new(batches);
+        Dataset::write(&mut batches, test_uri, Some(write_params.clone()))
+            .await
+            .unwrap();
+
+        let new_schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "s",
+            DataType::Utf8,
+            false,
+        )]));
+        let new_batches = RecordBatchBuffer::new(vec![RecordBatch::try_new(
+            new_schema.clone(),
             vec![Arc::new(StringArray::from_iter_values(
                 (20..40).map(|v| v.to_string()),
             ))],
@@ -860,6 +1575,10 @@ mod tests {
         let first_ver = Dataset::checkout(test_uri, 1).await.unwrap();
         assert_eq!(first_ver.version().version, 1);
         assert_eq!(&ArrowSchema::from(first_ver.schema()), schema.as_ref());
+
+        // The fragment IDs are not reused by the overwrite.
+        let max_old_id = first_ver.fragments().iter().map(|f| f.id).max().unwrap();
+        assert!(actual_ds.fragments().iter().all(|f| f.id > max_old_id));
     }
 
     #[tokio::test]
@@ -1081,4 +1800,1074 @@ mod tests {
             .unwrap();
         assert!(dataset.manifest.index_section.is_none());
     }
+
+    #[tokio::test]
+    async fn test_file_fragment() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("i", DataType::Int32, false),
+            Field::new("s", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from_iter_values(0..30)),
+                Arc::new(StringArray::from_iter_values(
+                    (0..30).map(|i| format!("s-{i}")),
+                )),
+            ],
+        )
+        .unwrap();
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut write_params = WriteParams::default();
+        write_params.max_rows_per_file = 10;
+        let mut batches: Box<dyn RecordBatchReader> = Box::new(RecordBatchBuffer::new(vec![batch]));
+        let dataset = Dataset::write(&mut batches, test_uri, Some(write_params))
+            .await
+            .unwrap();
+        let dataset = dataset.delete("i = 12").await.unwrap();
+
+        assert_eq!(
+            dataset
+                .get_fragments()
+                .iter()
+                .map(|f| f.id())
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert!(dataset.get_fragment(3).is_none());
+
+        let fragment = dataset.get_fragment(1).unwrap();
+        assert_eq!(fragment.schema(), dataset.schema());
+        assert_eq!(fragment.count_rows().await.unwrap(), 9);
+
+        let mut scanner = fragment.scan();
+        scanner.project(&["i"]).unwrap().filter("i < 15").unwrap();
+        let batches = scanner
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+        assert_eq!(
+            batch.column(0),
+            &(Arc::new(Int32Array::from(vec![10, 11, 13, 14])) as ArrayRef)
+        );
+
+        let batch = fragment
+            .take(&[5, 0, 3], &dataset.schema().project(&["s"]).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            batch.column(0),
+            &(Arc::new(StringArray::from(vec!["s-15", "s-10", "s-13"])) as ArrayRef)
+        );
+        assert!(fragment.take(&[10], dataset.schema()).await.is_err());
+        // The row of "i = 12" has been deleted.
+        assert!(matches!(
+            fragment.take(&[5, 2], dataset.schema()).await,
+            Err(Error::IO(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_file_fragment_nearest() {
+        let test_dir = tempdir().unwrap();
+
+        let dimension = 16;
+        let schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("i", DataType::Int32, false),
+            Field::new(
+                "vector",
+                DataType::FixedSizeList(
+                    Box::new(Field::new("item", DataType::Float32, true)),
+                    dimension,
+                ),
+                false,
+            ),
+        ]));
+        let vectors = Arc::new(
+            FixedSizeListArray::try_new(generate_random_array(512 * dimension as usize), dimension)
+                .unwrap(),
+        );
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from_iter_values(0..512)),
+                vectors.clone(),
+            ],
+        )
+        .unwrap();
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut write_params = WriteParams::default();
+        write_params.max_rows_per_file = 256;
+        let mut batches: Box<dyn RecordBatchReader> = Box::new(RecordBatchBuffer::new(vec![batch]));
+        let dataset = Dataset::write(&mut batches, test_uri, Some(write_params))
+            .await
+            .unwrap();
+        let mut params = VectorIndexParams::default();
+        params.num_partitions = 2;
+        params.num_sub_vectors = 2;
+        let dataset = dataset
+            .create_index(&["vector"], IndexType::Vector, None, &params, false)
+            .await
+            .unwrap();
+
+        // The nearest rows of the query are in fragment 0, but only fragment 1 is searched.
+        let q = vectors.value(10);
+        let fragment = dataset.get_fragment(1).unwrap();
+        let mut scanner = fragment.scan();
+        scanner.nearest("vector", q.as_ref(), 10).unwrap();
+        let batches = scanner
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+        assert_eq!(batch.num_rows(), 10);
+        let i_arr: &Int32Array = as_primitive_array(batch.column_by_name("i").unwrap());
+        // The row ID of row `i` is `((i / 256) << 32) + i % 256`.
+        assert!(i_arr
+            .values()
+            .iter()
+            .all(|i| (*i as u64 / 256) == fragment.id()));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_commits() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            false,
+        )]));
+        let new_batches = |range: Range<i32>| -> Box<dyn RecordBatchReader> {
+            Box::new(RecordBatchBuffer::new(vec![RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int32Array::from_iter_values(range))],
+            )
+            .unwrap()]))
+        };
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        let dataset = Dataset::write(&mut new_batches(0..20), test_uri, None)
+            .await
+            .unwrap();
+
+        // Concurrent appends are rebased onto each other.
+        let mut write_params = WriteParams::default();
+        write_params.mode = WriteMode::Append;
+        let (mut batches_a, mut batches_b) = (new_batches(20..40), new_batches(40..60));
+        let (result_a, result_b) = tokio::join!(
+            Dataset::write(&mut batches_a, test_uri, Some(write_params.clone())),
+            Dataset::write(&mut batches_b, test_uri, Some(write_params.clone())),
+        );
+        result_a.unwrap();
+        result_b.unwrap();
+        let latest = Dataset::open(test_uri).await.unwrap();
+        assert_eq!(latest.version().version, 3);
+        assert_eq!(latest.count_rows().await.unwrap(), 60);
+        let fragment_ids = latest.fragments().iter().map(|f| f.id).collect::<Vec<_>>();
+        assert_eq!(fragment_ids, vec![0, 1, 2]);
+
+        // A stale `_latest.manifest` does not hide the delete committed as version 4.
+        let deleted = latest.delete("i < 5").await.unwrap();
+        assert_eq!(deleted.version().version, 4);
+        let latest_path = latest_manifest_link_path(dataset.object_store.base_path());
+        dataset
+            .object_store
+            .inner
+            .copy(&latest.manifest_file(3), &latest_path)
+            .await
+            .unwrap();
+        assert_eq!(Dataset::open(test_uri).await.unwrap().version().version, 4);
+        let appended = Dataset::write(&mut new_batches(60..80), test_uri, Some(write_params))
+            .await
+            .unwrap();
+        assert_eq!(appended.version().version, 5);
+        assert_eq!(appended.count_rows().await.unwrap(), 75);
+        assert_eq!(Dataset::open(test_uri).await.unwrap().version().version, 5);
+
+        // Without `_latest.manifest`, the committed manifests are listed.
+        dataset
+            .object_store
+            .inner
+            .delete(&latest_path)
+            .await
+            .unwrap();
+        assert_eq!(Dataset::open(test_uri).await.unwrap().version().version, 5);
+
+        // Other operations on a stale version fail with a conflict.
+        let result = latest.delete("i > 50").await;
+        assert!(matches!(
+            result,
+            Err(Error::CommitConflict { version: 4, .. })
+        ));
+        let result = dataset.add_columns_with_sql(&[("j", "i * 2")]).await;
+        assert!(matches!(
+            result,
+            Err(Error::CommitConflict { version: 2, .. })
+        ));
+        assert_eq!(
+            Dataset::open(test_uri)
+                .await
+                .unwrap()
+                .count_rows()
+                .await
+                .unwrap(),
+            75
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restore() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            false,
+        )]));
+        let new_batches = |range: Range<i32>| -> Box<dyn RecordBatchReader> {
+            Box::new(RecordBatchBuffer::new(vec![RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int32Array::from_iter_values(range))],
+            )
+            .unwrap()]))
+        };
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        let original = Dataset::write(&mut new_batches(0..20), test_uri, None)
+            .await
+            .unwrap();
+        let mut write_params = WriteParams::default();
+        write_params.mode = WriteMode::Append;
+        let dataset = Dataset::write(
+            &mut new_batches(20..40),
+            test_uri,
+            Some(write_params.clone()),
+        )
+        .await
+        .unwrap();
+        let dataset = dataset.delete("i < 10").await.unwrap();
+        let dataset = dataset
+            .add_columns_with_sql(&[("double_i", "i * 2")])
+            .await
+            .unwrap();
+        assert_eq!(dataset.version().version, 4);
+
+        // Restore from a stale version.
+        let restored = original.restore(1).await.unwrap();
+        assert_eq!(restored.version().version, 5);
+        assert_eq!(ArrowSchema::from(restored.schema()), *schema);
+        assert_eq!(restored.count_rows().await.unwrap(), 20);
+        assert_eq!(restored.fragments().len(), 1);
+        assert!(restored.fragments()[0].deletion_file.is_none());
+
+        let latest = Dataset::open(test_uri).await.unwrap();
+        assert_eq!(latest.version().version, 5);
+        assert_eq!(latest.versions().await.unwrap().len(), 5);
+
+        // The fragment IDs used by the versions after the restored version are not reused.
+        let appended = Dataset::write(&mut new_batches(40..60), test_uri, Some(write_params))
+            .await
+            .unwrap();
+        assert_eq!(
+            appended
+                .fragments()
+                .iter()
+                .map(|f| f.id)
+                .collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+        assert_eq!(appended.count_rows().await.unwrap(), 40);
+
+        assert!(latest.restore(100).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_checkout_as_of() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            false,
+        )]));
+        let new_batches = |range: Range<i32>| -> Box<dyn RecordBatchReader> {
+            Box::new(RecordBatchBuffer::new(vec![RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int32Array::from_iter_values(range))],
+            )
+            .unwrap()]))
+        };
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        let before_create = Utc::now() - Duration::seconds(1);
+        let mut timestamps = vec![];
+        for i in 0..3 {
+            let mut write_params = WriteParams::default();
+            if i > 0 {
+                write_params.mode = WriteMode::Append;
+            }
+            Dataset::write(
+                &mut new_batches(i * 10..(i + 1) * 10),
+                test_uri,
+                Some(write_params),
+            )
+            .await
+            .unwrap();
+            timestamps.push(Utc::now());
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        for (i, timestamp) in timestamps.iter().enumerate() {
+            let dataset = Dataset::checkout_as_of(test_uri, *timestamp).await.unwrap();
+            assert_eq!(dataset.version().version, i as u64 + 1);
+            assert_eq!(dataset.count_rows().await.unwrap(), (i + 1) * 10);
+        }
+
+        // The exact commit timestamp of a version resolves to that version.
+        let versions = Dataset::open(test_uri)
+            .await
+            .unwrap()
+            .versions()
+            .await
+            .unwrap();
+        let version_two = versions.iter().find(|v| v.version == 2).unwrap();
+        let dataset = Dataset::checkout_as_of(test_uri, version_two.timestamp)
+            .await
+            .unwrap();
+        assert_eq!(dataset.version().version, 2);
+
+        assert!(Dataset::checkout_as_of(test_uri, before_create)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_version_aux_metadata() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            false,
+        )]));
+        let new_batches = |range: Range<i32>| -> Box<dyn RecordBatchReader> {
+            Box::new(RecordBatchBuffer::new(vec![RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int32Array::from_iter_values(range))],
+            )
+            .unwrap()]))
+        };
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut write_params = WriteParams::default();
+        write_params
+            .commit_metadata
+            .insert("job_id".to_string(), b"job-1".to_vec());
+        write_params
+            .commit_metadata
+            .insert("num_rows".to_string(), b"20".to_vec());
+        Dataset::write(&mut new_batches(0..20), test_uri, Some(write_params))
+            .await
+            .unwrap();
+
+        let mut write_params = WriteParams::default();
+        write_params.mode = WriteMode::Append;
+        let dataset = Dataset::write(&mut new_batches(20..40), test_uri, Some(write_params))
+            .await
+            .unwrap();
+        // The aux data is not inherited by the new versions.
+        let dataset = dataset.delete("i < 10").await.unwrap();
+        assert_eq!(dataset.version().version, 3);
+
+        let metadata = dataset.version_aux_metadata(1).await.unwrap();
+        assert_eq!(
+            metadata,
+            BTreeMap::from([
+                ("job_id".to_string(), b"job-1".to_vec()),
+                ("num_rows".to_string(), b"20".to_vec()),
+            ])
+        );
+        assert!(dataset.version_aux_metadata(2).await.unwrap().is_empty());
+        assert!(dataset.version_aux_metadata(3).await.unwrap().is_empty());
+        assert!(dataset.version_aux_metadata(10).await.is_err());
+
+        // The aux data does not affect reading the version.
+        let dataset = Dataset::checkout(test_uri, 1).await.unwrap();
+        assert_eq!(dataset.count_rows().await.unwrap(), 20);
+    }
+
+    /// The number of rows in each batch of each fragment.
+    async fn fragment_batch_sizes(dataset: &Dataset) -> Vec<Vec<usize>> {
+        let mut sizes = vec![];
+        for fragment in dataset.fragments().iter() {
+            let path = dataset.data_dir().child(fragment.files[0].path.as_str());
+            let reader = FileReader::try_new(dataset.object_store(), &path)
+                .await
+                .unwrap();
+            sizes.push(
+                (0..reader.num_batches())
+                    .map(|i| reader.num_rows_in_batch(i as i32))
+                    .collect(),
+            );
+        }
+        sizes
+    }
+
+    #[tokio::test]
+    async fn test_write_group_and_file_sizes() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            false,
+        )]));
+        let new_batches = || -> Box<dyn RecordBatchReader> {
+            Box::new(RecordBatchBuffer::new(
+                (0..3)
+                    .map(|i| {
+                        RecordBatch::try_new(
+                            schema.clone(),
+                            vec![Arc::new(Int32Array::from_iter_values(i * 25..(i + 1) * 25))],
+                        )
+                        .unwrap()
+                    })
+                    .collect(),
+            ))
+        };
+        let base_uri = test_dir.path().to_str().unwrap();
+
+        // The groups are split exactly at the max rows per group.
+        let mut write_params = WriteParams::default();
+        write_params.max_rows_per_file = 40;
+        write_params.max_rows_per_group = 10;
+        let dataset = Dataset::write(
+            &mut new_batches(),
+            &format!("{base_uri}/rows"),
+            Some(write_params.clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            fragment_batch_sizes(&dataset).await,
+            vec![vec![10, 10, 10, 10], vec![10, 10, 10, 5]]
+        );
+        assert_eq!(dataset.count_rows().await.unwrap(), 75);
+
+        // Each file is closed after its first group.
+        let mut bytes_per_file_params = write_params.clone();
+        bytes_per_file_params.max_bytes_per_file = Some(1);
+        let dataset = Dataset::write(
+            &mut new_batches(),
+            &format!("{base_uri}/bytes_per_file"),
+            Some(bytes_per_file_params),
+        )
+        .await
+        .unwrap();
+        let mut expected = vec![vec![10]; 7];
+        expected.push(vec![5]);
+        assert_eq!(fragment_batch_sizes(&dataset).await, expected);
+
+        // Each group has one row.
+        write_params.max_rows_per_file = 5;
+        write_params.max_bytes_per_group = Some(1);
+        let dataset = Dataset::write(
+            &mut new_batches(),
+            &format!("{base_uri}/bytes_per_group"),
+            Some(write_params),
+        )
+        .await
+        .unwrap();
+        assert_eq!(fragment_batch_sizes(&dataset).await, vec![vec![1; 5]; 15]);
+
+        let batches = dataset
+            .scan()
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let batch = concat_batches(&schema, &batches).unwrap();
+        let mut values = as_primitive_array::<arrow_array::types::Int32Type>(batch.column(0))
+            .values()
+            .to_vec();
+        values.sort();
+        assert_eq!(values, (0..75).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_write_concurrent_files() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            false,
+        )]));
+        let new_batches = || -> Box<dyn RecordBatchReader> {
+            Box::new(RecordBatchBuffer::new(
+                (0..3)
+                    .map(|i| {
+                        RecordBatch::try_new(
+                            schema.clone(),
+                            vec![Arc::new(Int32Array::from_iter_values(i * 25..(i + 1) * 25))],
+                        )
+                        .unwrap()
+                    })
+                    .collect(),
+            ))
+        };
+        let base_uri = test_dir.path().to_str().unwrap();
+
+        let mut write_params = WriteParams::default();
+        write_params.max_rows_per_file = 40;
+        write_params.max_rows_per_group = 10;
+        write_params.max_concurrent_files = 3;
+        let dataset = Dataset::write(
+            &mut new_batches(),
+            &format!("{base_uri}/three_files"),
+            Some(write_params.clone()),
+        )
+        .await
+        .unwrap();
+        // The groups are dealt to the open files in turn.
+        assert_eq!(
+            fragment_batch_sizes(&dataset).await,
+            vec![vec![10, 10, 10], vec![10, 10, 5], vec![10, 10]]
+        );
+
+        write_params.max_rows_per_file = 10;
+        let dataset = Dataset::write(
+            &mut new_batches(),
+            &format!("{base_uri}/eight_files"),
+            Some(write_params),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            dataset.fragments().iter().map(|f| f.id).collect::<Vec<_>>(),
+            (0..8).collect::<Vec<_>>()
+        );
+
+        // The fragment IDs follow the order of the input rows.
+        let mut scanner = dataset.scan();
+        scanner.with_row_id();
+        let batches = scanner
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let mut num_rows = 0;
+        for batch in batches.iter() {
+            let values = as_primitive_array::<arrow_array::types::Int32Type>(batch.column(0));
+            let row_ids: &UInt64Array = as_primitive_array(batch.column_by_name(ROW_ID).unwrap());
+            for (value, row_id) in values.values().iter().zip(row_ids.values().iter()) {
+                assert_eq!((*value / 10) as u64, row_id >> 32);
+                assert_eq!((*value % 10) as u64, row_id & 0xFFFF_FFFF);
+            }
+            num_rows += batch.num_rows();
+        }
+        assert_eq!(num_rows, 75);
+    }
+
+    #[tokio::test]
+    async fn test_write_concurrent_files_max_bytes() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            false,
+        )]));
+        let new_batches = || -> Box<dyn RecordBatchReader> {
+            Box::new(RecordBatchBuffer::new(vec![RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int32Array::from_iter_values(0..1000))],
+            )
+            .unwrap()]))
+        };
+        let file_sizes = |dataset: &Dataset, name: &str| {
+            let data_dir = test_dir.path().join(name).join(DATA_DIR);
+            dataset
+                .fragments()
+                .iter()
+                .map(|f| {
+                    std::fs::metadata(data_dir.join(&f.files[0].path))
+                        .unwrap()
+                        .len() as usize
+                })
+                .collect::<Vec<_>>()
+        };
+        let base_uri = test_dir.path().to_str().unwrap();
+
+        // The limit is checked against the bytes written to each file, as the sequential
+        // writer does. All the groups have the same size, so the full files have as many
+        // groups as those of the sequential writer.
+        let max_bytes_per_file = 400;
+        let mut write_params = WriteParams::default();
+        write_params.max_rows_per_group = 10;
+        write_params.max_bytes_per_file = Some(max_bytes_per_file);
+        let dataset = Dataset::write(
+            &mut new_batches(),
+            &format!("{base_uri}/sequential"),
+            Some(write_params.clone()),
+        )
+        .await
+        .unwrap();
+        assert!(file_sizes(&dataset, "sequential")[0] >= max_bytes_per_file);
+        let groups_per_file = fragment_batch_sizes(&dataset).await[0].len();
+
+        write_params.max_concurrent_files = 3;
+        let dataset = Dataset::write(
+            &mut new_batches(),
+            &format!("{base_uri}/concurrent"),
+            Some(write_params),
+        )
+        .await
+        .unwrap();
+        let sizes = file_sizes(&dataset, "concurrent");
+        assert!(sizes.len() > 3);
+        // Only the files that are open at the end can be smaller.
+        assert!(sizes.iter().filter(|s| **s < max_bytes_per_file).count() <= 3);
+        let batch_sizes = fragment_batch_sizes(&dataset).await;
+        assert!(batch_sizes.iter().all(|b| b.len() <= groups_per_file));
+        assert!(
+            batch_sizes
+                .iter()
+                .filter(|b| b.len() < groups_per_file)
+                .count()
+                <= 3
+        );
+        assert_eq!(dataset.count_rows().await.unwrap(), 1000);
+    }
+
+    #[tokio::test]
+    async fn test_dataset_writer() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            false,
+        )]));
+        let new_batch = |range: Range<i32>| {
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int32Array::from_iter_values(range))],
+            )
+            .unwrap()
+        };
+        let test_uri = test_dir.path().to_str().unwrap();
+
+        let mut write_params = WriteParams::default();
+        write_params.max_rows_per_file = 30;
+        write_params.max_rows_per_group = 10;
+        let mut writer = DatasetWriter::try_new(test_uri, Some(write_params.clone()))
+            .await
+            .unwrap();
+        assert!(matches!(
+            writer.commit().await.unwrap_err(),
+            Error::IO(msg) if msg == "Attempt to write empty record batches"
+        ));
+
+        let mut writer = DatasetWriter::try_new(test_uri, Some(write_params.clone()))
+            .await
+            .unwrap();
+        writer.write(&new_batch(0..15)).await.unwrap();
+        writer.flush().await.unwrap();
+        writer.write(&new_batch(15..35)).await.unwrap();
+        // Nothing is visible before the commit.
+        assert!(Dataset::open(test_uri).await.is_err());
+        let dataset = writer.commit().await.unwrap();
+        assert_eq!(dataset.version().version, 1);
+        assert_eq!(
+            fragment_batch_sizes(&dataset).await,
+            vec![vec![10, 5, 10, 5], vec![5]]
+        );
+
+        write_params.mode = WriteMode::Append;
+        let mut writer = DatasetWriter::try_new(test_uri, Some(write_params))
+            .await
+            .unwrap();
+        writer.write(&new_batch(35..40)).await.unwrap();
+        let dataset = writer.commit().await.unwrap();
+        assert_eq!(dataset.version().version, 2);
+        assert_eq!(
+            dataset.fragments().iter().map(|f| f.id).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+
+        let batches = dataset
+            .scan()
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let batch = concat_batches(&schema, &batches).unwrap();
+        assert_eq!(batch, new_batch(0..40));
+    }
+
+    #[tokio::test]
+    async fn test_write_compressed_dataset() {
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("i", DataType::Int32, false),
+            Field::new("s", DataType::Utf8, true),
+        ]));
+        let new_batch = |range: Range<i32>| {
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int32Array::from_iter_values(range.clone())),
+                    Arc::new(StringArray::from_iter_values(
+                        range.map(|v| format!("str-{}", v % 10)),
+                    )),
+                ],
+            )
+            .unwrap()
+        };
+
+        let mut write_params = WriteParams::default();
+        write_params.max_rows_per_group = 10;
+        write_params.compression = Some(Compression::Zstd);
+        write_params.column_compression = HashMap::from([("s".to_string(), None)]);
+        let mut writer = DatasetWriter::try_new(test_uri, Some(write_params.clone()))
+            .await
+            .unwrap();
+        writer.write(&new_batch(0..35)).await.unwrap();
+        writer.commit().await.unwrap();
+
+        // The columns keep their compression when appending.
+        write_params.mode = WriteMode::Append;
+        write_params.compression = Some(Compression::Lz4);
+        write_params.column_compression.clear();
+        let mut writer = DatasetWriter::try_new(test_uri, Some(write_params))
+            .await
+            .unwrap();
+        writer.write(&new_batch(35..50)).await.unwrap();
+        writer.commit().await.unwrap();
+
+        let dataset = Dataset::open(test_uri).await.unwrap();
+        assert_eq!(
+            dataset.schema().field("i").unwrap().compression,
+            Some(Compression::Zstd)
+        );
+        assert_eq!(dataset.schema().field("s").unwrap().compression, None);
+
+        let batches = dataset
+            .scan()
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(concat_batches(&schema, &batches).unwrap(), new_batch(0..50));
+
+        let batch = dataset
+            .take(&[3, 17, 36, 49], dataset.schema())
+            .await
+            .unwrap();
+        let expected = [3, 17, 36, 49]
+            .iter()
+            .map(|v| new_batch(*v..*v + 1))
+            .collect::<Vec<_>>();
+        assert_eq!(batch, concat_batches(&schema, &expected).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_write_dataset_with_selected_encodings() {
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("label", DataType::Int32, true),
+            Field::new("hash", DataType::Int32, false),
+        ]));
+        let new_batch = |range: Range<i32>| {
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int64Array::from_iter_values(
+                        range.clone().map(|v| v as i64 + 1_000_000),
+                    )),
+                    Arc::new(Int32Array::from_iter(
+                        range.clone().map(|v| (v % 7 != 0).then_some(v % 4)),
+                    )),
+                    Arc::new(Int32Array::from_iter_values(
+                        range.map(|v| v.wrapping_mul(v).wrapping_mul(-1_640_531_527)),
+                    )),
+                ],
+            )
+            .unwrap()
+        };
+
+        let mut write_params = WriteParams::default();
+        write_params.max_rows_per_group = 1000;
+        write_params.select_encodings = true;
+        write_params.column_compression =
+            HashMap::from([("id".to_string(), Some(Compression::Zstd))]);
+        let mut writer = DatasetWriter::try_new(test_uri, Some(write_params))
+            .await
+            .unwrap();
+        writer.write(&new_batch(0..2500)).await.unwrap();
+        writer.commit().await.unwrap();
+
+        let dataset = Dataset::open(test_uri).await.unwrap();
+        let encoding = |name: &str| dataset.schema().field(name).unwrap().encoding.clone();
+        assert_eq!(encoding("id"), Some(Encoding::Delta));
+        assert_eq!(encoding("label"), Some(Encoding::BitPacked));
+        assert_eq!(encoding("hash"), Some(Encoding::Plain));
+
+        let batches = dataset
+            .scan()
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(
+            concat_batches(&schema, &batches).unwrap(),
+            new_batch(0..2500)
+        );
+
+        let batch = dataset
+            .take(&[0, 999, 1000, 1777, 2499], dataset.schema())
+            .await
+            .unwrap();
+        let expected = [0, 999, 1000, 1777, 2499]
+            .iter()
+            .map(|v| new_batch(*v..*v + 1))
+            .collect::<Vec<_>>();
+        assert_eq!(batch, concat_batches(&schema, &expected).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_write_rle_encoded_dataset() {
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("i", DataType::Int32, false),
+            Field::new("region", DataType::Utf8, true),
+            Field::new("flag", DataType::Boolean, false).with_metadata(HashMap::from([(
+                ENCODING_METADATA_KEY.to_string(),
+                "rle".to_string(),
+            )])),
+        ]));
+        let new_batch = |range: Range<i32>| {
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int32Array::from_iter_values(range.clone())),
+                    Arc::new(StringArray::from_iter(
+                        range
+                            .clone()
+                            .map(|v| (v % 40 >= 5).then(|| format!("region-{}", v / 40))),
+                    )),
+                    Arc::new(BooleanArray::from_iter(range.map(|v| Some(v % 64 < 16)))),
+                ],
+            )
+            .unwrap()
+        };
+
+        let mut write_params = WriteParams::default();
+        write_params.max_rows_per_group = 100;
+        write_params.column_encodings = HashMap::from([("region".to_string(), Encoding::RLE)]);
+        let mut writer = DatasetWriter::try_new(test_uri, Some(write_params))
+            .await
+            .unwrap();
+        writer.write(&new_batch(0..250)).await.unwrap();
+        writer.commit().await.unwrap();
+
+        let dataset = Dataset::open(test_uri).await.unwrap();
+        let encoding = |name: &str| dataset.schema().field(name).unwrap().encoding.clone();
+        assert_eq!(encoding("i"), Some(Encoding::Plain));
+        assert_eq!(encoding("region"), Some(Encoding::RLE));
+        assert_eq!(encoding("flag"), Some(Encoding::RLE));
+
+        let batches = dataset
+            .scan()
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(
+            concat_batches(&schema, &batches).unwrap(),
+            new_batch(0..250)
+        );
+
+        let mut scanner = dataset.scan();
+        scanner.limit(30, Some(85)).unwrap();
+        let batches = scanner
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(
+            concat_batches(&schema, &batches).unwrap(),
+            new_batch(85..115)
+        );
+
+        let batch = dataset
+            .take(&[0, 4, 5, 99, 100, 201, 249], dataset.schema())
+            .await
+            .unwrap();
+        let expected = [0, 4, 5, 99, 100, 201, 249]
+            .iter()
+            .map(|v| new_batch(*v..*v + 1))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            batch.columns(),
+            concat_batches(&schema, &expected).unwrap().columns()
+        );
+
+        // Delta encoding only stores integers.
+        let mut write_params = WriteParams::default();
+        write_params.mode = WriteMode::Overwrite;
+        write_params.column_encodings = HashMap::from([("flag".to_string(), Encoding::Delta)]);
+        let mut writer = DatasetWriter::try_new(test_uri, Some(write_params))
+            .await
+            .unwrap();
+        assert!(matches!(
+            writer.write(&new_batch(0..10)).await,
+            Err(Error::Schema(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_create_fragments_and_commit() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("i", DataType::Int32, false),
+            Field::new("s", DataType::Utf8, true),
+        ]));
+        let new_batches = |range: Range<i32>| -> Box<dyn RecordBatchReader> {
+            Box::new(RecordBatchBuffer::new(vec![RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int32Array::from_iter_values(range.clone())),
+                    Arc::new(StringArray::from_iter_values(
+                        range.map(|v| format!("s-{v}")),
+                    )),
+                ],
+            )
+            .unwrap()]))
+        };
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        assert!(Dataset::commit(test_uri, Operation::Append(vec![]), None)
+            .await
+            .is_err());
+
+        // Write the fragments on the "workers", and send them back as JSON.
+        let mut fragments = vec![];
+        for range in [0..10, 10..30] {
+            let fragment = Fragment::create(test_uri, &mut new_batches(range), None)
+                .await
+                .unwrap();
+            let json = fragment.to_json().unwrap();
+            assert_eq!(FragmentMetadata::from_json(&json).unwrap(), fragment);
+            fragments.push(fragment);
+        }
+        assert_eq!(
+            fragments.iter().map(|f| f.num_rows).collect::<Vec<_>>(),
+            vec![10, 20]
+        );
+        // Nothing is committed yet.
+        assert!(Dataset::open(test_uri).await.is_err());
+
+        let dataset = Dataset::commit(
+            test_uri,
+            Operation::Overwrite {
+                fragments,
+                schema: schema.as_ref().clone(),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(dataset.version().version, 1);
+        assert_eq!(ArrowSchema::from(dataset.schema()), *schema);
+        assert_eq!(
+            dataset.fragments().iter().map(|f| f.id).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert_eq!(dataset.count_rows().await.unwrap(), 30);
+
+        let mut write_params = WriteParams::default();
+        write_params.mode = WriteMode::Append;
+        let appended = Fragment::create(test_uri, &mut new_batches(30..35), Some(write_params))
+            .await
+            .unwrap();
+        let dataset = Dataset::commit(test_uri, Operation::Append(vec![appended.clone()]), None)
+            .await
+            .unwrap();
+        assert_eq!(dataset.version().version, 2);
+        assert_eq!(
+            dataset.fragments().iter().map(|f| f.id).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(dataset.count_rows().await.unwrap(), 35);
+
+        // The fragments with unknown fields are rejected.
+        let dataset = dataset.drop_columns(&["s"]).await.unwrap();
+        assert!(matches!(
+            Dataset::commit(test_uri, Operation::Append(vec![appended]), None).await,
+            Err(Error::Schema(_))
+        ));
+        assert_eq!(dataset.version().version, 3);
+
+        // Overwrite does not reuse the fragment IDs of the previous versions.
+        let mut fragments = vec![];
+        for range in [0..10, 10..20] {
+            fragments.push(
+                Fragment::create(test_uri, &mut new_batches(range), None)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let dataset = Dataset::commit(
+            test_uri,
+            Operation::Overwrite {
+                fragments,
+                schema: schema.as_ref().clone(),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(dataset.version().version, 4);
+        assert_eq!(
+            dataset.fragments().iter().map(|f| f.id).collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+        assert_eq!(dataset.count_rows().await.unwrap(), 20);
+    }
 }
@@ -0,0 +1,259 @@
+// Copyright 2023 Lance Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Clean up the old versions of a [Dataset].
+
+use std::collections::HashSet;
+
+use chrono::{Duration, Utc};
+use futures::stream::TryStreamExt;
+
+use super::{read_manifest_indices, tag_path, Dataset, TAGS_DIR};
+use crate::io::read_manifest;
+use crate::Result;
+
+/// Statistics of [`Dataset::cleanup_old_versions`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RemovalStats {
+    /// Number of the versions removed.
+    pub old_versions: u64,
+
+    /// Number of the tags removed along with the versions they refer to.
+    pub tags_removed: u64,
+
+    /// Total bytes of the manifests, tags, staging and data files removed.
+    pub bytes_removed: u64,
+}
+
+impl Dataset {
+    /// Remove the versions older than `older_than`, and the files that are only used by them.
+    ///
+    /// The latest version is always kept. If `keep_tagged` is true, the tagged versions are
+    /// kept too, otherwise the tags of the removed versions are removed. The data, deletion
+    /// and index files that are not referenced by any of the remaining versions are removed,
+    /// if they were not modified within `older_than`. So are the staging files of the failed
+    /// commits. In this way, the files written by an ongoing transaction are not removed.
+    ///
+    /// If `dry_run` is true, nothing is removed, and the returned [RemovalStats] reports what
+    /// would have been removed.
+    pub async fn cleanup_old_versions(
+        &self,
+        older_than: Duration,
+        keep_tagged: bool,
+        dry_run: bool,
+    ) -> Result<RemovalStats> {
+        let cutoff = Utc::now() - older_than;
+        let latest_version = self.latest_manifest().await?.version;
+        let tags = self.list_tags().await?;
+
+        let mut stats = RemovalStats::default();
+        let mut removed_paths = vec![];
+        let mut data_files = HashSet::new();
+        let mut deletion_files = HashSet::new();
+        let mut index_dirs = vec![];
+        let mut manifests = self
+            .object_store
+            .inner
+            .list(Some(&self.versions_dir()))
+            .await?;
+        while let Some(meta) = manifests.try_next().await? {
+            if !meta.location.as_ref().ends_with(".manifest") {
+                continue;
+            }
+            let manifest = read_manifest(&self.object_store, &meta.location).await?;
+            let version_tags = tags
+                .iter()
+                .filter(|(_, v)| **v == manifest.version)
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>();
+            let tagged = manifest.tag.is_some() || !version_tags.is_empty();
+            if manifest.version != latest_version
+                && manifest.timestamp() < cutoff
+                && !(keep_tagged && tagged)
+            {
+                stats.old_versions += 1;
+                stats.bytes_removed += meta.size as u64;
+                removed_paths.push(meta.location);
+                // The tags of the removed version would be dangling.
+                for name in version_tags {
+                    let path = tag_path(&self.base, name);
+                    stats.tags_removed += 1;
+                    stats.bytes_removed += self.object_store.inner.head(&path).await?.size as u64;
+                    removed_paths.push(path);
+                }
+                continue;
+            }
+
+            for fragment in manifest.fragments.iter() {
+                for data_file in fragment.files.iter() {
+                    data_files.insert(self.data_dir().child(data_file.path.as_str()));
+                }
+                if let Some(deletion_file) = fragment.deletion_file.as_ref() {
+                    deletion_files.insert(self.deletions_dir().child(deletion_file.path.as_str()));
+                }
+            }
+            for index in
+                read_manifest_indices(&self.object_store, &meta.location, &manifest).await?
+            {
+                index_dirs.push(self.indices_dir().child(index.uuid.to_string()));
+            }
+        }
+
+        for dir in [self.data_dir(), self.deletions_dir(), self.indices_dir()] {
+            let mut files = self.object_store.inner.list(Some(&dir)).await?;
+            while let Some(meta) = files.try_next().await? {
+                let referenced = data_files.contains(&meta.location)
+                    || deletion_files.contains(&meta.location)
+                    || index_dirs
+                        .iter()
+                        .any(|index_dir| meta.location.prefix_matches(index_dir));
+                if !referenced && meta.last_modified < cutoff {
+                    stats.bytes_removed += meta.size as u64;
+                    removed_paths.push(meta.location);
+                }
+            }
+        }
+
+        // The staging files left over by the failed commits of versions and tags.
+        for dir in [self.versions_dir(), self.base.child(TAGS_DIR)] {
+            let mut files = self.object_store.inner.list(Some(&dir)).await?;
+            while let Some(meta) = files.try_next().await? {
+                if meta.location.as_ref().ends_with(".tmp") && meta.last_modified < cutoff {
+                    stats.bytes_removed += meta.size as u64;
+                    removed_paths.push(meta.location);
+                }
+            }
+        }
+
+        if !dry_run {
+            for path in removed_paths.iter() {
+                self.object_store.inner.delete(path).await?;
+            }
+        }
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::ops::Range;
+    use std::sync::Arc;
+
+    use arrow_array::{Int32Array, RecordBatch, RecordBatchReader};
+    use arrow_schema::{DataType, Field, Schema as ArrowSchema};
+    use tempfile::tempdir;
+
+    use crate::arrow::RecordBatchBuffer;
+    use crate::dataset::{WriteMode, WriteParams};
+
+    #[tokio::test]
+    async fn test_cleanup_old_versions() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            false,
+        )]));
+        let new_batches = |range: Range<i32>| -> Box<dyn RecordBatchReader> {
+            Box::new(RecordBatchBuffer::new(vec![RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int32Array::from_iter_values(range))],
+            )
+            .unwrap()]))
+        };
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut write_params = WriteParams::default();
+        Dataset::write(
+            &mut new_batches(0..20),
+            test_uri,
+            Some(write_params.clone()),
+        )
+        .await
+        .unwrap();
+        write_params.mode = WriteMode::Append;
+        Dataset::write(
+            &mut new_batches(20..40),
+            test_uri,
+            Some(write_params.clone()),
+        )
+        .await
+        .unwrap();
+        write_params.mode = WriteMode::Overwrite;
+        Dataset::write(
+            &mut new_batches(40..60),
+            test_uri,
+            Some(write_params.clone()),
+        )
+        .await
+        .unwrap();
+        write_params.mode = WriteMode::Append;
+        let dataset = Dataset::write(&mut new_batches(60..80), test_uri, Some(write_params))
+            .await
+            .unwrap();
+        let dataset = dataset.delete("i < 45").await.unwrap();
+        assert_eq!(dataset.version().version, 5);
+
+        // The staging files of the failed commits are ignored, and removed when they are old.
+        std::fs::write(test_dir.path().join("_versions/6.manifest-0.tmp"), b"6").unwrap();
+        std::fs::create_dir_all(test_dir.path().join("_tags")).unwrap();
+        std::fs::write(test_dir.path().join("_tags/t.txt-0.tmp"), b"5").unwrap();
+        assert_eq!(Dataset::open(test_uri).await.unwrap().version().version, 5);
+        assert!(dataset.list_tags().await.unwrap().is_empty());
+
+        let count_files = |dir: &str| {
+            std::fs::read_dir(test_dir.path().join(dir))
+                .unwrap()
+                .count()
+        };
+        assert_eq!(count_files("data"), 4);
+        assert_eq!(count_files("_versions"), 6);
+        assert_eq!(count_files("_tags"), 1);
+
+        // Nothing is old enough.
+        let stats = dataset
+            .cleanup_old_versions(Duration::days(1), false, false)
+            .await
+            .unwrap();
+        assert_eq!(stats, RemovalStats::default());
+
+        let dry_run_stats = dataset
+            .cleanup_old_versions(Duration::zero(), false, true)
+            .await
+            .unwrap();
+        assert_eq!(dry_run_stats.old_versions, 4);
+        assert!(dry_run_stats.bytes_removed > 0);
+        assert_eq!(count_files("data"), 4);
+        assert_eq!(count_files("_versions"), 6);
+        assert_eq!(count_files("_tags"), 1);
+        assert!(Dataset::checkout(test_uri, 1).await.is_ok());
+
+        let stats = dataset
+            .cleanup_old_versions(Duration::zero(), false, false)
+            .await
+            .unwrap();
+        assert_eq!(stats, dry_run_stats);
+        assert_eq!(count_files("data"), 2);
+        assert_eq!(count_files("_versions"), 1);
+        assert_eq!(count_files("_tags"), 0);
+        assert!(Dataset::checkout(test_uri, 1).await.is_err());
+
+        let dataset = Dataset::open(test_uri).await.unwrap();
+        assert_eq!(dataset.version().version, 5);
+        assert_eq!(dataset.count_rows().await.unwrap(), 35);
+    }
+}
@@ -0,0 +1,344 @@
+// Copyright 2023 Lance Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compact the small fragments of a [Dataset].
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use super::fragment::FragmentReader;
+use super::{CompactionOptions, Dataset, FragmentWriter, WriteParams};
+use crate::format::{Fragment, Index};
+use crate::index::vector::ivf::remap_index_file;
+use crate::Result;
+
+/// Maps the row IDs of the compacted fragments to the new fragments.
+///
+/// Compaction keeps the order of the rows, so the new row ID of a row is computed from its
+/// position among the rows written, instead of being stored for each row.
+#[derive(Debug, Default)]
+pub(crate) struct RowIdRemap {
+    /// The compacted fragments by ID, with the position of their first row written, and the
+    /// sorted offsets of their deleted rows.
+    old_fragments: HashMap<u64, (u64, Vec<u32>)>,
+
+    /// The new fragments, as the position of their first row and their ID, in ascending order.
+    new_fragments: Vec<(u64, u64)>,
+}
+
+impl RowIdRemap {
+    /// Get the new row ID of `row_id`.
+    ///
+    /// Returns `None` if the row was not compacted, and `Some(None)` if it was deleted.
+    pub(crate) fn get(&self, row_id: u64) -> Option<Option<u64>> {
+        let (start, deleted) = self.old_fragments.get(&(row_id >> 32))?;
+        let offset = row_id as u32;
+        let num_deleted = deleted.partition_point(|d| *d < offset);
+        if deleted.get(num_deleted) == Some(&offset) {
+            return Some(None);
+        }
+        let pos = start + (offset - num_deleted as u32) as u64;
+        let idx = self.new_fragments.partition_point(|(s, _)| *s <= pos) - 1;
+        let (fragment_start, fragment_id) = self.new_fragments[idx];
+        Some(Some((fragment_id << 32) + pos - fragment_start))
+    }
+}
+
+/// The sorted IDs of the fields stored in the data files of a fragment.
+fn fragment_field_ids(fragment: &Fragment) -> Vec<i32> {
+    let mut field_ids = fragment
+        .files
+        .iter()
+        .flat_map(|f| f.fields.iter().copied())
+        .collect::<Vec<_>>();
+    field_ids.sort();
+    field_ids.dedup();
+    field_ids
+}
+
+impl Dataset {
+    /// Compact the small fragments into larger ones.
+    ///
+    /// The runs of consecutive fragments that have fewer rows than
+    /// `options.target_rows_per_fragment`, and store the same columns, are rewritten into new
+    /// fragments, with the deleted rows removed. The indices are rewritten with the row IDs
+    /// remapped to the new fragments.
+    ///
+    /// Upon finish, a new dataset version is generated. If there is nothing to compact,
+    /// returns the dataset as is.
+    pub async fn compact_files(&self, options: &CompactionOptions) -> Result<Self> {
+        let runs = self.plan_compaction(options).await?;
+        if runs.is_empty() {
+            return Ok(self.clone());
+        }
+
+        let params = WriteParams {
+            max_rows_per_file: options.target_rows_per_fragment,
+            max_rows_per_group: options.max_rows_per_group,
+            ..Default::default()
+        };
+        let mut next_fragment_id = self.manifest.next_fragment_id();
+        let mut row_id_remap = RowIdRemap::default();
+        // The position of the next row written, over all the runs.
+        let mut num_rows = 0_u64;
+        let mut fragments = vec![];
+        let mut pos = 0;
+        for run in runs {
+            fragments.extend_from_slice(&self.fragments()[pos..run.start]);
+            pos = run.end;
+
+            let old_fragments = &self.fragments()[run];
+            let schema = self
+                .schema()
+                .project_by_ids(&fragment_field_ids(&old_fragments[0]))?;
+            // The batches are written as they are read, so only one is held in memory.
+            let mut writer =
+                FragmentWriter::new(&self.object_store, &schema, next_fragment_id, &params);
+            let mut new_fragment_start = num_rows;
+            for fragment in old_fragments {
+                let reader = FragmentReader::try_new(self, fragment).await?;
+                let deletion_vector = self.deletion_vector(fragment).await?.unwrap_or_default();
+                row_id_remap
+                    .old_fragments
+                    .insert(fragment.id, (num_rows, deletion_vector.iter().collect()));
+                let mut start_offset = 0_u32;
+                for batch_id in 0..reader.num_batches() as i32 {
+                    let batch = reader.read_batch(batch_id, .., &schema).await?;
+                    let kept = deletion_vector.filter_batch(&batch, start_offset)?;
+                    start_offset += batch.num_rows() as u32;
+                    if kept.num_rows() > 0 {
+                        num_rows += kept.num_rows() as u64;
+                        writer.write(&kept).await?;
+                    }
+                }
+            }
+
+            let new_fragments = writer.finish().await?;
+            for fragment in new_fragments.iter() {
+                row_id_remap
+                    .new_fragments
+                    .push((new_fragment_start, fragment.id));
+                new_fragment_start += self.count_physical_rows(fragment).await? as u64;
+                next_fragment_id = fragment.id + 1;
+            }
+            fragments.extend(new_fragments);
+        }
+        fragments.extend_from_slice(&self.fragments()[pos..]);
+
+        let mut indices = vec![];
+        for index in self.load_indices().await? {
+            let index_id = Uuid::new_v4();
+            remap_index_file(
+                self,
+                &index.uuid.to_string(),
+                &index_id.to_string(),
+                &row_id_remap,
+            )
+            .await?;
+            indices.push(Index::new(index_id, &index.name, &index.fields));
+        }
+
+        let mut manifest = self.manifest.as_ref().clone();
+        manifest.fragments = Arc::new(fragments);
+        self.commit_manifest(manifest, Some(indices)).await
+    }
+
+    /// Find the runs of consecutive fragments to compact, as ranges over [Self::fragments].
+    async fn plan_compaction(&self, options: &CompactionOptions) -> Result<Vec<Range<usize>>> {
+        let mut runs = vec![];
+        // The start of the current run, the columns it stores, and its number of rows.
+        let mut current: Option<(usize, Vec<i32>, usize)> = None;
+        for (idx, fragment) in self.fragments().iter().enumerate() {
+            let num_rows = self.count_physical_rows(fragment).await? - fragment.num_deleted_rows();
+            let field_ids = self
+                .schema()
+                .project_by_ids(&fragment_field_ids(fragment))?
+                .field_ids();
+            if let Some((start, run_field_ids, run_rows)) = current.as_mut() {
+                if num_rows < options.target_rows_per_fragment
+                    && *run_field_ids == field_ids
+                    && *run_rows + num_rows <= options.target_rows_per_fragment
+                {
+                    *run_rows += num_rows;
+                    continue;
+                }
+                runs.push(*start..idx);
+            }
+            current = if num_rows < options.target_rows_per_fragment {
+                Some((idx, field_ids, num_rows))
+            } else {
+                None
+            };
+        }
+        if let Some((start, _, _)) = current {
+            runs.push(start..self.fragments().len());
+        }
+
+        // Only rewrite a single fragment if it reclaims deleted rows or merges data files.
+        Ok(runs
+            .into_iter()
+            .filter(|run| {
+                let fragment = &self.fragments()[run.start];
+                run.len() > 1 || fragment.deletion_file.is_some() || fragment.files.len() > 1
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow_array::{
+        cast::{as_fixed_size_list_array, as_primitive_array},
+        FixedSizeListArray, Float32Array, Int32Array, RecordBatch, RecordBatchReader,
+    };
+    use arrow_schema::{DataType, Field, Schema as ArrowSchema};
+    use arrow_select::concat::concat_batches;
+    use futures::stream::TryStreamExt;
+    use tempfile::tempdir;
+
+    use crate::arrow::RecordBatchBuffer;
+    use crate::index::{vector::VectorIndexParams, IndexType};
+    use crate::utils::testing::generate_random_array;
+
+    #[tokio::test]
+    async fn test_compact_files() {
+        let test_dir = tempdir().unwrap();
+
+        let dimension = 16;
+        let schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("i", DataType::Int32, false),
+            Field::new(
+                "embeddings",
+                DataType::FixedSizeList(
+                    Box::new(Field::new("item", DataType::Float32, true)),
+                    dimension,
+                ),
+                false,
+            ),
+        ]));
+        let float_arr = generate_random_array(512 * dimension as usize);
+        let vectors = Arc::new(FixedSizeListArray::try_new(float_arr, dimension).unwrap());
+        let batches = RecordBatchBuffer::new(
+            (0..16)
+                .map(|i| {
+                    RecordBatch::try_new(
+                        schema.clone(),
+                        vec![
+                            Arc::new(Int32Array::from_iter_values(i * 32..(i + 1) * 32)),
+                            vectors.slice(i as usize * 32, 32),
+                        ],
+                    )
+                    .unwrap()
+                })
+                .collect(),
+        );
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut write_params = WriteParams::default();
+        write_params.max_rows_per_file = 64;
+        write_params.max_rows_per_group = 32;
+        let mut batches: Box<dyn RecordBatchReader> = Box::new(batches);
+        let dataset = Dataset::write(&mut batches, test_uri, Some(write_params))
+            .await
+            .unwrap();
+        assert_eq!(dataset.fragments().len(), 8);
+
+        let mut params = VectorIndexParams::default();
+        params.num_partitions = 2;
+        params.num_sub_vectors = 2;
+        let dataset = dataset
+            .create_index(&["embeddings"], IndexType::Vector, None, &params, false)
+            .await
+            .unwrap();
+        let dataset = dataset.delete("i < 10").await.unwrap();
+
+        let options = CompactionOptions {
+            target_rows_per_fragment: 256,
+            max_rows_per_group: 64,
+        };
+        let dataset = dataset.compact_files(&options).await.unwrap();
+        assert_eq!(dataset.version().version, 4);
+        assert_eq!(
+            dataset.fragments().iter().map(|f| f.id).collect::<Vec<_>>(),
+            vec![8, 9]
+        );
+        assert!(dataset
+            .fragments()
+            .iter()
+            .all(|f| f.deletion_file.is_none()));
+        assert_eq!(dataset.count_rows().await.unwrap(), 502);
+        assert_eq!(ArrowSchema::from(dataset.schema()), *schema);
+
+        let batches = dataset
+            .scan()
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let batch = concat_batches(&schema, &batches).unwrap();
+        let i_arr: &Int32Array = as_primitive_array(batch.column_by_name("i").unwrap());
+        assert_eq!(i_arr.values(), (10..512).collect::<Vec<_>>().as_slice());
+
+        // The index refers to the rows in the new fragments.
+        let indices = dataset.load_indices().await.unwrap();
+        assert_eq!(indices.len(), 1);
+        let q: &Float32Array = as_primitive_array(vectors.value(100).as_ref());
+        let batches = dataset
+            .scan()
+            .nearest("embeddings", q, 10)
+            .unwrap()
+            .nprobs(2)
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let results = concat_batches(&batches[0].schema(), &batches).unwrap();
+        assert_eq!(results.num_rows(), 10);
+        let i_arr: &Int32Array = as_primitive_array(results.column_by_name("i").unwrap());
+        let embeddings = as_fixed_size_list_array(results.column_by_name("embeddings").unwrap());
+        for (row, i) in i_arr.values().iter().enumerate() {
+            assert!(*i >= 10);
+            assert_eq!(embeddings.value(row), vectors.value(*i as usize));
+        }
+
+        // Nothing left to compact.
+        let dataset = dataset.compact_files(&options).await.unwrap();
+        assert_eq!(dataset.version().version, 4);
+    }
+
+    #[test]
+    fn test_row_id_remap() {
+        // Fragment 1 has 4 rows, with the row at offset 1 deleted, and fragment 2 has 3 rows.
+        // They are compacted into fragment 5 of 4 rows and fragment 6 of 2 rows.
+        let remap = RowIdRemap {
+            old_fragments: HashMap::from([(1, (0, vec![1])), (2, (3, vec![]))]),
+            new_fragments: vec![(0, 5), (4, 6)],
+        };
+        assert_eq!(remap.get(3), None);
+        assert_eq!(remap.get(1 << 32), Some(Some(5 << 32)));
+        assert_eq!(remap.get((1 << 32) + 1), Some(None));
+        assert_eq!(remap.get((1 << 32) + 3), Some(Some((5 << 32) + 2)));
+        assert_eq!(remap.get(2 << 32), Some(Some((5 << 32) + 3)));
+        assert_eq!(remap.get((2 << 32) + 2), Some(Some((6 << 32) + 1)));
+    }
+}
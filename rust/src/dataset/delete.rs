@@ -0,0 +1,447 @@
+// Copyright 2023 Lance Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Delete and update the rows of a [Dataset].
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use arrow_array::{cast::as_primitive_array, RecordBatch, UInt64Array};
+use arrow_cast::cast::{cast_with_options, CastOptions};
+use arrow_schema::{DataType, Schema as ArrowSchema};
+use futures::stream::TryStreamExt;
+use uuid::Uuid;
+
+use super::{write_fragments, Dataset, WriteParams, ROW_ID};
+use crate::datafusion::physical_expr::column_names_in_expr;
+use crate::format::{DeletionFile, Fragment};
+use crate::io::{deletion::write_deletion_file, exec::Planner};
+use crate::{Error, Result};
+
+/// Group the row offsets in the [ROW_ID] column of `batches` by fragment ID.
+pub(super) fn group_row_ids_by_fragment(
+    batches: &[RecordBatch],
+) -> Result<BTreeMap<u64, Vec<u32>>> {
+    let mut row_ids_by_fragment: BTreeMap<u64, Vec<u32>> = BTreeMap::new();
+    for batch in batches {
+        let row_id_arr = batch
+            .column_by_name(ROW_ID)
+            .ok_or_else(|| Error::IO(format!("{ROW_ID} column does not exist in scan")))?;
+        let row_ids: &UInt64Array = as_primitive_array(row_id_arr);
+        row_ids.values().iter().for_each(|row_id| {
+            let fragment_id = row_id >> 32;
+            let offset = (row_id - (fragment_id << 32)) as u32;
+            row_ids_by_fragment
+                .entry(fragment_id)
+                .or_default()
+                .push(offset);
+        });
+    }
+    Ok(row_ids_by_fragment)
+}
+
+impl Dataset {
+    /// Delete the rows that match the `predicate`.
+    ///
+    /// The predicate is a SQL filter, as in the WHERE clause, i.e., `"id IN (1, 2, 3)"`.
+    ///
+    /// The rows are not removed from the data files. Instead, the offsets of the deleted rows
+    /// are recorded in a deletion file of each affected fragment, which the readers skip.
+    /// Fragments that have all their rows deleted are removed from the dataset.
+    ///
+    /// Upon finish, a new dataset version is generated. If no row matches the predicate,
+    /// the dataset is returned as is.
+    pub async fn delete(&self, predicate: &str) -> Result<Self> {
+        let planner = Planner::new(Arc::new(self.schema().into()));
+        let filter_expr = planner.create_physical_expr(&planner.parse_filter(predicate)?)?;
+        let columns = column_names_in_expr(filter_expr.as_ref());
+
+        let mut scanner = self.scan();
+        scanner
+            .project(&columns.iter().map(|c| c.as_str()).collect::<Vec<_>>())?
+            .filter(predicate)?
+            .with_row_id();
+        let batches = scanner
+            .try_into_stream()
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        let deleted_rows = group_row_ids_by_fragment(&batches)?;
+        if deleted_rows.is_empty() {
+            return Ok(self.clone());
+        }
+
+        let mut manifest = self.manifest.as_ref().clone();
+        manifest.fragments = Arc::new(self.delete_rows(&deleted_rows).await?);
+        let indices = self.load_indices().await?;
+        self.commit_manifest(manifest, Some(indices)).await
+    }
+
+    /// Update the rows that match the `predicate`.
+    ///
+    /// `assignments` are pairs of the top-level column name and the SQL expression of its new
+    /// value, i.e., `[("label", "'cat'"), ("score", "score * 2")]`. The expressions are
+    /// evaluated over the original rows.
+    ///
+    /// The updated rows are written to new fragments, and the original rows are marked as deleted.
+    /// The indices on the updated columns are dropped.
+    ///
+    /// Upon finish, a new dataset version is generated. If no row matches the predicate,
+    /// the dataset is returned as is.
+    pub async fn update(&self, predicate: &str, assignments: &[(&str, &str)]) -> Result<Self> {
+        if assignments.is_empty() {
+            return Err(Error::IO("Update: no column to update".to_string()));
+        }
+        let arrow_schema = Arc::new(ArrowSchema::from(self.schema()));
+        let planner = Planner::new(arrow_schema.clone());
+        let mut updates = HashMap::new();
+        for (column, value) in assignments {
+            let Some((idx, field)) = arrow_schema.column_with_name(column) else {
+                return Err(Error::Schema(format!(
+                    "Update: column '{column}' does not exist"
+                )));
+            };
+            if matches!(field.data_type(), DataType::Dictionary(_, _)) {
+                return Err(Error::Schema(format!(
+                    "Update: dictionary column '{column}' is not supported"
+                )));
+            }
+            let expr = planner.create_physical_expr(&planner.parse_expr(value)?)?;
+            if updates.insert(idx, expr).is_some() {
+                return Err(Error::IO(format!(
+                    "Update: column '{column}' is assigned more than once"
+                )));
+            }
+        }
+
+        let mut scanner = self.scan();
+        scanner.filter(predicate)?.with_row_id();
+        let batches = scanner
+            .try_into_stream()
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        let deleted_rows = group_row_ids_by_fragment(&batches)?;
+        if deleted_rows.is_empty() {
+            return Ok(self.clone());
+        }
+
+        let updated_batches = batches
+            .iter()
+            .filter(|batch| batch.num_rows() > 0)
+            .map(|batch| {
+                let columns = arrow_schema
+                    .fields()
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, field)| {
+                        let Some(expr) = updates.get(&idx) else {
+                            return batch.column_by_name(field.name()).cloned().ok_or_else(|| {
+                                Error::IO(format!(
+                                    "Update: column '{}' does not exist in scan",
+                                    field.name()
+                                ))
+                            });
+                        };
+                        let arr = expr.evaluate(batch)?.into_array(batch.num_rows());
+                        if arr.data_type() == field.data_type() {
+                            Ok(arr)
+                        } else {
+                            // Unlike the safe cast, an invalid value is an error instead of null.
+                            cast_with_options(&arr, field.data_type(), &CastOptions { safe: false })
+                                .map_err(|e| {
+                                    Error::Schema(format!(
+                                        "Update: can not cast the value of column '{}' to {}: {e}",
+                                        field.name(),
+                                        field.data_type()
+                                    ))
+                                })
+                        }
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(RecordBatch::try_new(arrow_schema.clone(), columns)?)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut fragments = self.delete_rows(&deleted_rows).await?;
+        fragments.extend(
+            write_fragments(
+                &self.object_store,
+                self.schema(),
+                updated_batches.into_iter().map(Ok),
+                self.manifest.next_fragment_id(),
+                &WriteParams::default(),
+            )
+            .await?,
+        );
+
+        let mut manifest = self.manifest.as_ref().clone();
+        manifest.fragments = Arc::new(fragments);
+        let updated_field_ids = updates
+            .keys()
+            .map(|idx| self.schema().fields[*idx].id)
+            .collect::<Vec<_>>();
+        let indices = self
+            .load_indices()
+            .await?
+            .into_iter()
+            .filter(|idx| !idx.fields.iter().any(|f| updated_field_ids.contains(f)))
+            .collect();
+        self.commit_manifest(manifest, Some(indices)).await
+    }
+
+    /// Mark the rows as deleted, and returns the new fragments.
+    ///
+    /// `deleted_rows` maps the fragment ID to the offsets of the rows to delete in the fragment.
+    pub(super) async fn delete_rows(
+        &self,
+        deleted_rows: &BTreeMap<u64, Vec<u32>>,
+    ) -> Result<Vec<Fragment>> {
+        let mut fragments = vec![];
+        for fragment in self.fragments().iter() {
+            let Some(offsets) = deleted_rows.get(&fragment.id) else {
+                fragments.push(fragment.clone());
+                continue;
+            };
+            let mut deletion_vector = self
+                .deletion_vector(fragment)
+                .await?
+                .map(|dv| dv.as_ref().clone())
+                .unwrap_or_default();
+            deletion_vector.extend(offsets.iter().copied());
+            if deletion_vector.len() >= self.count_physical_rows(fragment).await? {
+                // All rows are deleted, drop the fragment.
+                continue;
+            }
+
+            let deletion_file = DeletionFile {
+                path: format!("{}-{}.del", fragment.id, Uuid::new_v4()),
+                num_deleted_rows: deletion_vector.len(),
+            };
+            let path = self.deletions_dir().child(deletion_file.path.as_str());
+            write_deletion_file(&self.object_store, &path, &deletion_vector).await?;
+
+            let mut fragment = fragment.clone();
+            fragment.deletion_file = Some(deletion_file);
+            fragments.push(fragment);
+        }
+        Ok(fragments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow_array::{cast::as_string_array, Int32Array, RecordBatchReader, StringArray};
+    use arrow_schema::Field;
+    use arrow_select::concat::concat_batches;
+    use tempfile::tempdir;
+
+    use crate::arrow::RecordBatchBuffer;
+
+    #[tokio::test]
+    async fn test_delete() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            false,
+        )]));
+        let batches = RecordBatchBuffer::new(
+            (0..20)
+                .map(|i| {
+                    RecordBatch::try_new(
+                        schema.clone(),
+                        vec![Arc::new(Int32Array::from_iter_values(i * 20..(i + 1) * 20))],
+                    )
+                    .unwrap()
+                })
+                .collect(),
+        );
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut write_params = WriteParams::default();
+        write_params.max_rows_per_file = 40;
+        write_params.max_rows_per_group = 10;
+        let mut batches: Box<dyn RecordBatchReader> = Box::new(batches);
+        let dataset = Dataset::write(&mut batches, test_uri, Some(write_params))
+            .await
+            .unwrap();
+
+        // Delete a few rows from some fragments, and all the rows of the first fragment.
+        let dataset = dataset
+            .delete("i < 40 OR i = 45 OR i = 101 OR i = 102 OR i = 399")
+            .await
+            .unwrap();
+        assert_eq!(dataset.version().version, 2);
+        assert_eq!(dataset.fragments().len(), 9);
+        assert_eq!(dataset.count_rows().await.unwrap(), 400 - 44);
+        assert_eq!(
+            dataset
+                .fragments()
+                .iter()
+                .map(|f| f.num_deleted_rows())
+                .sum::<usize>(),
+            4
+        );
+
+        let read_values = |dataset: Dataset| async move {
+            let batches = dataset
+                .scan()
+                .try_into_stream()
+                .await
+                .unwrap()
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap();
+            let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+            let arr: &Int32Array = as_primitive_array(batch.column_by_name("i").unwrap());
+            let mut values = arr.values().to_vec();
+            values.sort();
+            values
+        };
+        let expected = (40..400)
+            .filter(|v| ![45, 101, 102, 399].contains(v))
+            .collect::<Vec<_>>();
+        assert_eq!(read_values(dataset.clone()).await, expected);
+
+        // Positional take skips the deleted rows, the same as a scan.
+        let positions = [0, 5, 6, 61, 62, 355];
+        let batch = dataset.take(&positions, dataset.schema()).await.unwrap();
+        let arr: &Int32Array = as_primitive_array(batch.column_by_name("i").unwrap());
+        assert_eq!(
+            arr.values(),
+            positions
+                .iter()
+                .map(|p| expected[*p])
+                .collect::<Vec<_>>()
+                .as_slice()
+        );
+
+        // Deletion vectors are merged with the existing deleted rows.
+        let dataset = dataset.delete("i >= 390").await.unwrap();
+        assert_eq!(dataset.count_rows().await.unwrap(), 400 - 53);
+
+        // Nothing matches, no new version is created.
+        let dataset = dataset.delete("i > 1000").await.unwrap();
+        assert_eq!(dataset.version().version, 3);
+
+        // The previous version still has all the rows.
+        let dataset = Dataset::checkout(test_uri, 1).await.unwrap();
+        assert_eq!(dataset.count_rows().await.unwrap(), 400);
+        assert_eq!(read_values(dataset).await, (0..400).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_update() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("i", DataType::Int32, false),
+            Field::new("s", DataType::Utf8, false),
+        ]));
+        let batches = RecordBatchBuffer::new(
+            (0..10)
+                .map(|i| {
+                    RecordBatch::try_new(
+                        schema.clone(),
+                        vec![
+                            Arc::new(Int32Array::from_iter_values(i * 20..(i + 1) * 20)),
+                            Arc::new(StringArray::from_iter_values(
+                                (i * 20..(i + 1) * 20).map(|v| format!("s-{v}")),
+                            )),
+                        ],
+                    )
+                    .unwrap()
+                })
+                .collect(),
+        );
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut write_params = WriteParams::default();
+        write_params.max_rows_per_file = 40;
+        write_params.max_rows_per_group = 10;
+        let mut batches: Box<dyn RecordBatchReader> = Box::new(batches);
+        let dataset = Dataset::write(&mut batches, test_uri, Some(write_params))
+            .await
+            .unwrap();
+
+        let dataset = dataset
+            .update(
+                "i >= 30 AND i < 50",
+                &[("s", "'updated'"), ("i", "i + 1000")],
+            )
+            .await
+            .unwrap();
+        assert_eq!(dataset.version().version, 2);
+        assert_eq!(dataset.count_rows().await.unwrap(), 200);
+        // The updated rows are written to a new fragment.
+        assert_eq!(dataset.fragments().len(), 6);
+        assert_eq!(dataset.fragments().last().unwrap().id, 5);
+
+        let read_rows = |dataset: Dataset| async move {
+            let batches = dataset
+                .scan()
+                .try_into_stream()
+                .await
+                .unwrap()
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap();
+            let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+            let i_arr: &Int32Array = as_primitive_array(batch.column_by_name("i").unwrap());
+            let s_arr = as_string_array(batch.column_by_name("s").unwrap());
+            let mut rows = i_arr
+                .values()
+                .iter()
+                .zip(s_arr.iter())
+                .map(|(i, s)| (*i, s.unwrap().to_string()))
+                .collect::<Vec<_>>();
+            rows.sort();
+            rows
+        };
+        let mut expected = (0..200)
+            .map(|v| {
+                if (30..50).contains(&v) {
+                    (v + 1000, "updated".to_string())
+                } else {
+                    (v, format!("s-{v}"))
+                }
+            })
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(read_rows(dataset.clone()).await, expected);
+
+        // Nothing matches, no new version is created.
+        let dataset = dataset.update("i < 0", &[("s", "'none'")]).await.unwrap();
+        assert_eq!(dataset.version().version, 2);
+        assert!(dataset.update("i < 0", &[("x", "1")]).await.is_err());
+        // The value can not be cast to the column type.
+        assert!(matches!(
+            dataset.update("i = 1", &[("i", "'abc'")]).await,
+            Err(Error::Schema(_))
+        ));
+
+        // The previous version is not changed.
+        let dataset = Dataset::checkout(test_uri, 1).await.unwrap();
+        assert_eq!(
+            read_rows(dataset).await,
+            (0..200).map(|v| (v, format!("s-{v}"))).collect::<Vec<_>>()
+        );
+    }
+}
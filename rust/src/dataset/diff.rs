@@ -0,0 +1,387 @@
+// Copyright 2023 Lance Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compare two versions of a [Dataset].
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use super::scanner::Scanner;
+use super::{flatten_fields, Dataset};
+use crate::datatypes::Field;
+use crate::format::{Fragment, Index};
+use crate::Result;
+
+/// Changes between two versions of a [Dataset], returned by [`Dataset::diff`].
+#[derive(Debug, Clone)]
+pub struct DatasetDiff {
+    pub from_version: u64,
+    pub to_version: u64,
+
+    /// Fragments of `to_version` that do not exist in `from_version`.
+    pub added_fragments: Vec<Fragment>,
+
+    /// Fragments of `from_version` that do not exist in `to_version`.
+    pub removed_fragments: Vec<Fragment>,
+
+    /// Fragments of `to_version` whose data or deletion files have changed since
+    /// `from_version`, i.e., by deleting rows or adding columns.
+    pub updated_fragments: Vec<Fragment>,
+
+    /// Fields of `to_version` that do not exist in `from_version`, including the nested fields.
+    pub added_fields: Vec<Field>,
+
+    /// Fields of `from_version` that do not exist in `to_version`, including the nested fields.
+    pub removed_fields: Vec<Field>,
+
+    /// Fields of `to_version` that have been renamed or changed nullability.
+    pub altered_fields: Vec<Field>,
+
+    /// Indices of `to_version` that do not exist in `from_version`.
+    pub added_indices: Vec<Index>,
+
+    /// Indices of `from_version` that do not exist in `to_version`.
+    pub removed_indices: Vec<Index>,
+
+    /// The dataset checked out at `to_version`.
+    dataset: Arc<Dataset>,
+}
+
+impl DatasetDiff {
+    /// Scan the rows of the added fragments, as of `to_version`.
+    ///
+    /// The rows that were rewritten, i.e., by [`Dataset::compact_files`], are included too.
+    pub fn scan_added_rows(&self) -> Scanner {
+        let mut scanner = Scanner::new(self.dataset.clone());
+        scanner.with_fragments(self.added_fragments.clone());
+        scanner
+    }
+}
+
+impl Dataset {
+    /// Compare the manifests of `from_version` and `to_version`.
+    ///
+    /// Fragments, fields and indices are matched by their IDs, so the renamed columns are
+    /// reported as altered rather than removed and added. The fragments replaced by an
+    /// overwrite are reported as removed, even if their IDs have been reused.
+    pub async fn diff(&self, from_version: u64, to_version: u64) -> Result<DatasetDiff> {
+        let from = Self::checkout_manifest(
+            self.object_store.clone(),
+            self.base.clone(),
+            &self.manifest_file(from_version),
+        )
+        .await?;
+        let to = Self::checkout_manifest(
+            self.object_store.clone(),
+            self.base.clone(),
+            &self.manifest_file(to_version),
+        )
+        .await?;
+
+        // Before the overwrites kept the fragment IDs unique, they restarted the IDs from 0.
+        // The first data file of a fragment never changes, so it tells the fragments apart.
+        let fragment_key = |f: &Fragment| (f.id, f.files.first().map(|file| file.path.clone()));
+        let from_fragments: HashMap<_, &Fragment> = from
+            .fragments()
+            .iter()
+            .map(|f| (fragment_key(f), f))
+            .collect();
+        let to_fragment_keys: HashSet<_> = to.fragments().iter().map(fragment_key).collect();
+        let mut added_fragments = vec![];
+        let mut updated_fragments = vec![];
+        for fragment in to.fragments().iter() {
+            match from_fragments.get(&fragment_key(fragment)) {
+                None => added_fragments.push(fragment.clone()),
+                Some(old) => {
+                    if old.files != fragment.files || old.deletion_file != fragment.deletion_file {
+                        updated_fragments.push(fragment.clone());
+                    }
+                }
+            }
+        }
+        let removed_fragments = from
+            .fragments()
+            .iter()
+            .filter(|f| !to_fragment_keys.contains(&fragment_key(f)))
+            .cloned()
+            .collect();
+
+        let from_fields: HashMap<i32, &Field> = flatten_fields(&from.schema().fields)
+            .into_iter()
+            .map(|f| (f.id, f))
+            .collect();
+        let to_fields = flatten_fields(&to.schema().fields);
+        let to_field_ids: HashSet<i32> = to_fields.iter().map(|f| f.id).collect();
+        let mut added_fields = vec![];
+        let mut altered_fields = vec![];
+        for field in to_fields {
+            match from_fields.get(&field.id) {
+                None => added_fields.push(field.clone()),
+                Some(old) => {
+                    if old.name != field.name || old.nullable != field.nullable {
+                        altered_fields.push(field.clone());
+                    }
+                }
+            }
+        }
+        let removed_fields = flatten_fields(&from.schema().fields)
+            .into_iter()
+            .filter(|f| !to_field_ids.contains(&f.id))
+            .cloned()
+            .collect();
+
+        let from_indices = from.load_indices().await?;
+        let to_indices = to.load_indices().await?;
+        let added_indices = to_indices
+            .iter()
+            .filter(|i| !from_indices.iter().any(|old| old.uuid == i.uuid))
+            .cloned()
+            .collect();
+        let removed_indices = from_indices
+            .iter()
+            .filter(|i| !to_indices.iter().any(|new| new.uuid == i.uuid))
+            .cloned()
+            .collect();
+
+        Ok(DatasetDiff {
+            from_version,
+            to_version,
+            added_fragments,
+            removed_fragments,
+            updated_fragments,
+            added_fields,
+            removed_fields,
+            altered_fields,
+            added_indices,
+            removed_indices,
+            dataset: Arc::new(to),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::ops::Range;
+
+    use arrow_array::{
+        cast::as_primitive_array, FixedSizeListArray, Int32Array, RecordBatch, RecordBatchReader,
+    };
+    use arrow_schema::{DataType, Field, Schema as ArrowSchema};
+    use arrow_select::concat::concat_batches;
+    use futures::stream::TryStreamExt;
+    use tempfile::tempdir;
+
+    use crate::arrow::RecordBatchBuffer;
+    use crate::dataset::{write_manifest_file, ColumnAlteration, WriteMode, WriteParams};
+    use crate::index::{vector::VectorIndexParams, IndexType};
+    use crate::utils::testing::generate_random_array;
+
+    #[tokio::test]
+    async fn test_diff() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            false,
+        )]));
+        let new_batches = |range: Range<i32>| -> Box<dyn RecordBatchReader> {
+            Box::new(RecordBatchBuffer::new(vec![RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int32Array::from_iter_values(range))],
+            )
+            .unwrap()]))
+        };
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        Dataset::write(&mut new_batches(0..20), test_uri, None)
+            .await
+            .unwrap();
+        let mut write_params = WriteParams::default();
+        write_params.mode = WriteMode::Append;
+        let dataset = Dataset::write(&mut new_batches(20..40), test_uri, Some(write_params))
+            .await
+            .unwrap();
+        let dataset = dataset.delete("i < 10").await.unwrap();
+        let dataset = dataset
+            .add_columns_with_sql(&[("double_i", "i * 2")])
+            .await
+            .unwrap();
+        let dataset = dataset
+            .alter_columns(&[ColumnAlteration::new("i").rename("id")])
+            .await
+            .unwrap();
+        assert_eq!(dataset.version().version, 5);
+
+        let fragment_ids =
+            |fragments: &[Fragment]| fragments.iter().map(|f| f.id).collect::<Vec<_>>();
+        let field_names = |fields: &[crate::datatypes::Field]| {
+            fields.iter().map(|f| f.name.clone()).collect::<Vec<_>>()
+        };
+
+        let diff = dataset.diff(1, 2).await.unwrap();
+        assert_eq!(fragment_ids(&diff.added_fragments), vec![1]);
+        assert!(diff.removed_fragments.is_empty());
+        assert!(diff.updated_fragments.is_empty());
+        assert!(diff.added_fields.is_empty());
+        assert!(diff.removed_fields.is_empty());
+        assert!(diff.altered_fields.is_empty());
+        assert!(diff.added_indices.is_empty());
+        assert!(diff.removed_indices.is_empty());
+        let batches = diff
+            .scan_added_rows()
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let added = concat_batches(&schema, &batches).unwrap();
+        assert_eq!(
+            added.column(0).as_ref(),
+            &Int32Array::from_iter_values(20..40)
+        );
+
+        let diff = dataset.diff(2, 4).await.unwrap();
+        assert!(diff.added_fragments.is_empty());
+        assert_eq!(fragment_ids(&diff.updated_fragments), vec![0, 1]);
+        assert_eq!(field_names(&diff.added_fields), vec!["double_i"]);
+
+        let diff = dataset.diff(4, 5).await.unwrap();
+        assert!(diff.updated_fragments.is_empty());
+        assert!(diff.added_fields.is_empty());
+        assert_eq!(field_names(&diff.altered_fields), vec!["id"]);
+
+        // Diff backwards.
+        let diff = dataset.diff(5, 1).await.unwrap();
+        assert_eq!(fragment_ids(&diff.removed_fragments), vec![1]);
+        assert_eq!(fragment_ids(&diff.updated_fragments), vec![0]);
+        assert_eq!(field_names(&diff.removed_fields), vec!["double_i"]);
+        assert_eq!(field_names(&diff.altered_fields), vec!["i"]);
+
+        // An overwrite replaces all the fragments.
+        let mut write_params = WriteParams::default();
+        write_params.mode = WriteMode::Overwrite;
+        let dataset = Dataset::write(&mut new_batches(100..110), test_uri, Some(write_params))
+            .await
+            .unwrap();
+        assert_eq!(dataset.version().version, 6);
+        let diff = dataset.diff(5, 6).await.unwrap();
+        assert_eq!(fragment_ids(&diff.added_fragments), vec![2]);
+        assert_eq!(fragment_ids(&diff.removed_fragments), vec![0, 1]);
+        assert!(diff.updated_fragments.is_empty());
+        let batches = diff
+            .scan_added_rows()
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let added = concat_batches(&schema, &batches).unwrap();
+        assert_eq!(
+            added.column(0).as_ref(),
+            &Int32Array::from_iter_values(100..110)
+        );
+
+        // So does an overwrite that restarted the fragment IDs, as the older writers did.
+        let mut manifest = dataset.manifest.as_ref().clone();
+        manifest.version = 7;
+        let mut fragments = manifest.fragments.as_ref().clone();
+        fragments[0].id = 0;
+        manifest.fragments = Arc::new(fragments);
+        write_manifest_file(
+            &dataset.object_store,
+            dataset.commit_handler.as_ref(),
+            &mut manifest,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let diff = dataset.diff(5, 7).await.unwrap();
+        assert_eq!(fragment_ids(&diff.added_fragments), vec![0]);
+        assert_eq!(fragment_ids(&diff.removed_fragments), vec![0, 1]);
+        assert!(diff.updated_fragments.is_empty());
+
+        assert!(dataset.diff(1, 100).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_diff_nearest_added_rows() {
+        let test_dir = tempdir().unwrap();
+
+        let dimension = 16;
+        let schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("i", DataType::Int32, false),
+            Field::new(
+                "vector",
+                DataType::FixedSizeList(
+                    Box::new(Field::new("item", DataType::Float32, true)),
+                    dimension,
+                ),
+                false,
+            ),
+        ]));
+        let vectors = Arc::new(
+            FixedSizeListArray::try_new(generate_random_array(512 * dimension as usize), dimension)
+                .unwrap(),
+        );
+        let new_batches = |range: Range<i32>| -> Box<dyn RecordBatchReader> {
+            let vectors = vectors.slice(range.start as usize, range.len());
+            Box::new(RecordBatchBuffer::new(vec![RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int32Array::from_iter_values(range)), vectors],
+            )
+            .unwrap()]))
+        };
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        let dataset = Dataset::write(&mut new_batches(0..256), test_uri, None)
+            .await
+            .unwrap();
+        let mut params = VectorIndexParams::default();
+        params.num_partitions = 2;
+        params.num_sub_vectors = 2;
+        let dataset = dataset
+            .create_index(&["vector"], IndexType::Vector, None, &params, false)
+            .await
+            .unwrap();
+        let mut write_params = WriteParams::default();
+        write_params.mode = WriteMode::Append;
+        let dataset = Dataset::write(&mut new_batches(256..512), test_uri, Some(write_params))
+            .await
+            .unwrap();
+        assert_eq!(dataset.version().version, 3);
+
+        // The nearest rows of the query are unchanged, only the added rows are searched.
+        let diff = dataset.diff(2, 3).await.unwrap();
+        let q = vectors.value(10);
+        let mut scanner = diff.scan_added_rows();
+        scanner.nearest("vector", q.as_ref(), 10).unwrap();
+        let batches = scanner
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+        assert_eq!(batch.num_rows(), 10);
+        let i_arr: &Int32Array = as_primitive_array(batch.column_by_name("i").unwrap());
+        assert!(i_arr.values().iter().all(|i| *i >= 256));
+    }
+}
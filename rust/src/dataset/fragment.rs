@@ -0,0 +1,335 @@
+// Copyright 2023 Lance Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Read the data of one [Fragment], which might be stored in multiple data files, and write
+//! new fragments without committing them.
+
+use std::sync::Arc;
+
+use arrow_array::{
+    new_null_array, Array, ArrayRef, RecordBatch, RecordBatchOptions, RecordBatchReader,
+    UInt64Array,
+};
+use arrow_schema::{DataType, Field as ArrowField, Schema as ArrowSchema};
+use arrow_select::take::take;
+use serde::{Deserialize, Serialize};
+
+use super::scanner::Scanner;
+use super::{
+    latest_manifest_path, peek_schema, project_schema_for_append, read_manifest_with_dictionary,
+    write_fragments, Dataset, WriteMode, WriteParams, ROW_ID,
+};
+use crate::datatypes::Schema;
+use crate::format::{DataFile, Fragment};
+use crate::io::{FileReader, ObjectStore, ReadBatchParams};
+use crate::{Error, Result};
+
+/// Metadata of a fragment written by [Fragment::create], to be committed by
+/// [Dataset::commit].
+///
+/// It can be serialized to JSON, to send it from the workers to the driver of a distributed
+/// write.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FragmentMetadata {
+    /// The data files of the fragment.
+    pub files: Vec<DataFile>,
+
+    /// Number of rows in the fragment.
+    pub num_rows: usize,
+}
+
+impl FragmentMetadata {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+impl Fragment {
+    /// Write `batches` into a new data file of the dataset at `uri`, without committing it.
+    ///
+    /// In [WriteMode::Append], the columns are matched to the latest version of the dataset
+    /// by name, as in [Dataset::write]. Otherwise, the field IDs are assigned from the schema
+    /// of the batches, so the fragments written with the same schema can be committed together.
+    ///
+    /// All the rows are written to one data file, so the file size limits of `params` do not
+    /// apply. The fragment ID is assigned by [Dataset::commit].
+    pub async fn create(
+        uri: &str,
+        batches: &mut Box<dyn RecordBatchReader>,
+        params: Option<WriteParams>,
+    ) -> Result<FragmentMetadata> {
+        let object_store = ObjectStore::new(uri).await?;
+        let mut params = params.unwrap_or_default();
+        params.max_rows_per_file = usize::MAX;
+        params.max_bytes_per_file = None;
+        params.max_concurrent_files = 1;
+
+        let mut peekable = batches.peekable();
+        let mut schema = peek_schema(&mut peekable)?;
+        schema.set_compression(params.compression, &params.column_compression)?;
+        if matches!(params.mode, WriteMode::Append) {
+            let Some(latest_manifest_path) = latest_manifest_path(&object_store).await? else {
+                return Err(Error::IO(format!("Dataset does not exist: {uri}")));
+            };
+            let latest_manifest =
+                read_manifest_with_dictionary(&object_store, &latest_manifest_path).await?;
+            schema = project_schema_for_append(&latest_manifest.schema, &schema, "Append")?;
+        }
+
+        let mut num_rows = 0;
+        let batches = peekable.inspect(|batch| {
+            if let Ok(batch) = batch {
+                num_rows += batch.num_rows();
+            }
+        });
+        let fragment = write_fragments(&object_store, &schema, batches, 0, &params)
+            .await?
+            .pop()
+            .ok_or_else(|| Error::IO("Attempt to write empty record batches".to_string()))?;
+        Ok(FragmentMetadata {
+            files: fragment.files,
+            num_rows,
+        })
+    }
+}
+
+/// A fragment of a [Dataset], which can be read on its own.
+///
+/// Obtained from [Dataset::get_fragment] or [Dataset::get_fragments], i.e., to assign the
+/// fragments of a dataset to the tasks of a distributed engine.
+#[derive(Debug, Clone)]
+pub struct FileFragment {
+    dataset: Arc<Dataset>,
+    metadata: Fragment,
+}
+
+impl FileFragment {
+    pub(crate) fn new(dataset: Arc<Dataset>, metadata: Fragment) -> Self {
+        Self { dataset, metadata }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.metadata.id
+    }
+
+    /// The metadata of the fragment, with its data files and deletion file.
+    pub fn metadata(&self) -> &Fragment {
+        &self.metadata
+    }
+
+    /// The schema of the fragment, which is the schema of the dataset. The columns that are
+    /// not stored in the data files of the fragment are read as nulls.
+    pub fn schema(&self) -> &Schema {
+        self.dataset.schema()
+    }
+
+    /// Count the number of rows in the fragment, excluding the deleted rows.
+    pub async fn count_rows(&self) -> Result<usize> {
+        let reader = FragmentReader::try_new(&self.dataset, &self.metadata).await?;
+        Ok(reader.len() - self.metadata.num_deleted_rows())
+    }
+
+    /// Create a [Scanner] over the rows of this fragment only.
+    pub fn scan(&self) -> Scanner {
+        let mut scanner = Scanner::new(self.dataset.clone());
+        scanner.with_fragments(vec![self.metadata.clone()]);
+        scanner
+    }
+
+    /// Take rows by their offsets within the fragment, which are the lower 32 bits of the
+    /// row IDs. The rows are returned in the order of `offsets`.
+    ///
+    /// Returns [Error::IO] if any of the rows has been deleted.
+    pub async fn take(&self, offsets: &[u32], projection: &Schema) -> Result<RecordBatch> {
+        let reader = FragmentReader::try_new(&self.dataset, &self.metadata).await?;
+        if let Some(offset) = offsets.iter().find(|o| **o as usize >= reader.len()) {
+            return Err(Error::IO(format!(
+                "Offset {offset} is out of range, fragment {} has {} rows",
+                self.metadata.id,
+                reader.len()
+            )));
+        }
+        if let Some(deletion_vector) = self.dataset.deletion_vector(&self.metadata).await? {
+            if let Some(offset) = offsets.iter().find(|o| deletion_vector.contains(**o)) {
+                return Err(Error::IO(format!(
+                    "Row {offset} of fragment {} has been deleted",
+                    self.metadata.id
+                )));
+            }
+        }
+        let mut sorted_offsets = Vec::from(offsets);
+        sorted_offsets.sort();
+        let batch = reader.take(&sorted_offsets, projection).await?;
+
+        let remapping_index: UInt64Array = offsets
+            .iter()
+            .map(|o| sorted_offsets.binary_search(o).unwrap() as u64)
+            .collect();
+        let columns = batch
+            .columns()
+            .iter()
+            .map(|c| take(c.as_ref(), &remapping_index, None))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(RecordBatch::try_new(batch.schema(), columns)?)
+    }
+}
+
+/// Reader of one [Fragment].
+///
+/// The columns of a fragment can be stored in several data files, i.e., after adding columns
+/// to the dataset. All the data files of a fragment have the same batch layout.
+/// The columns that exist in the schema but not in any data file are read as nulls.
+pub(crate) struct FragmentReader<'a> {
+    /// Readers and the schema of the fields stored in each data file.
+    readers: Vec<(FileReader<'a>, Schema)>,
+
+    /// If set true, returns the row ID alongside with the actual data.
+    with_row_id: bool,
+}
+
+impl<'a> FragmentReader<'a> {
+    /// Open all the data files of a [Fragment] in the dataset.
+    pub(crate) async fn try_new(dataset: &'a Dataset, fragment: &Fragment) -> Result<Self> {
+        if fragment.files.is_empty() {
+            return Err(Error::IO(format!(
+                "Fragment {} does not have any data file",
+                fragment.id
+            )));
+        }
+        let mut readers = Vec::with_capacity(fragment.files.len());
+        for data_file in fragment.files.iter() {
+            let path = dataset.data_dir().child(data_file.path.as_str());
+            let schema = dataset.schema().project_by_ids(&data_file.fields)?;
+            let reader = FileReader::try_new_with_fragment(
+                dataset.object_store(),
+                &path,
+                fragment.id,
+                Some(&schema),
+            )
+            .await?;
+            readers.push((reader, schema));
+        }
+        Ok(Self {
+            readers,
+            with_row_id: false,
+        })
+    }
+
+    /// Instruct the reader to return meta row id column.
+    pub(crate) fn with_row_id(&mut self, v: bool) -> &mut Self {
+        self.with_row_id = v;
+        self
+    }
+
+    pub(crate) fn num_batches(&self) -> usize {
+        self.readers[0].0.num_batches()
+    }
+
+    /// Get the number of rows in this batch
+    pub(crate) fn num_rows_in_batch(&self, batch_id: i32) -> usize {
+        self.readers[0].0.num_rows_in_batch(batch_id)
+    }
+
+    /// Count the number of rows in this fragment, including the deleted rows.
+    pub(crate) fn len(&self) -> usize {
+        self.readers[0].0.len()
+    }
+
+    /// Read a batch of data from the fragment.
+    pub(crate) async fn read_batch(
+        &self,
+        batch_id: i32,
+        params: impl Into<ReadBatchParams>,
+        projection: &Schema,
+    ) -> Result<RecordBatch> {
+        let params = params.into();
+        let mut batches = vec![];
+        for (reader, schema) in self.readers.iter() {
+            let file_projection = projection.project_by_ids(&schema.field_ids())?;
+            if file_projection.fields.is_empty() {
+                continue;
+            }
+            batches.push(
+                reader
+                    .read_batch(batch_id, &params, &file_projection)
+                    .await?,
+            );
+        }
+
+        let num_rows = match &params {
+            ReadBatchParams::Indices(indices) => indices.len(),
+            ReadBatchParams::Range(r) => r.len(),
+            ReadBatchParams::RangeFull => self.num_rows_in_batch(batch_id),
+            ReadBatchParams::RangeTo(r) => r.end,
+            ReadBatchParams::RangeFrom(r) => self.num_rows_in_batch(batch_id) - r.start,
+        };
+        let row_ids = if self.with_row_id {
+            Some(Arc::new(self.readers[0].0.row_ids(batch_id, &params)?) as ArrayRef)
+        } else {
+            None
+        };
+        assemble_batch(&batches, projection, num_rows, row_ids)
+    }
+
+    /// Take rows by the offsets within the fragment.
+    ///
+    /// The indices must be sorted.
+    pub(crate) async fn take(&self, indices: &[u32], projection: &Schema) -> Result<RecordBatch> {
+        let mut batches = vec![];
+        for (reader, schema) in self.readers.iter() {
+            let file_projection = projection.project_by_ids(&schema.field_ids())?;
+            if file_projection.fields.is_empty() {
+                continue;
+            }
+            batches.push(reader.take(indices, &file_projection).await?);
+        }
+        assemble_batch(&batches, projection, indices.len(), None)
+    }
+}
+
+/// Assemble the columns read from different data files into one [RecordBatch] of `projection`.
+///
+/// The columns that do not exist in any of the `batches` are filled with nulls.
+fn assemble_batch(
+    batches: &[RecordBatch],
+    projection: &Schema,
+    num_rows: usize,
+    row_ids: Option<ArrayRef>,
+) -> Result<RecordBatch> {
+    let arrow_schema = ArrowSchema::from(projection);
+    let mut fields = arrow_schema.fields().clone();
+    let mut columns = fields
+        .iter()
+        .map(|field| {
+            batches
+                .iter()
+                .find_map(|b| b.column_by_name(field.name()))
+                .cloned()
+                .unwrap_or_else(|| new_null_array(field.data_type(), num_rows))
+        })
+        .collect::<Vec<_>>();
+    if let Some(row_ids) = row_ids {
+        fields.push(ArrowField::new(ROW_ID, DataType::UInt64, false));
+        columns.push(row_ids);
+    }
+    Ok(RecordBatch::try_new_with_options(
+        Arc::new(ArrowSchema::new(fields)),
+        columns,
+        &RecordBatchOptions::new().with_row_count(Some(num_rows)),
+    )?)
+}
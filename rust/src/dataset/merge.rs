@@ -0,0 +1,312 @@
+// Copyright 2023 Lance Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Merge rows into a [Dataset] by key.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use arrow::row::{RowConverter, SortField};
+use arrow_array::{RecordBatch, RecordBatchReader};
+use arrow_schema::{ArrowError, Schema as ArrowSchema};
+use datafusion::physical_plan::expressions::Column;
+use datafusion::physical_plan::joins::{HashJoinExec, PartitionMode};
+use datafusion::physical_plan::memory::MemoryExec;
+use datafusion::physical_plan::ExecutionPlan;
+use datafusion::prelude::{JoinType, SessionContext};
+use futures::stream::TryStreamExt;
+
+use super::delete::group_row_ids_by_fragment;
+use super::scanner::{DEFAULT_BATCH_SIZE, PREFETCH_SIZE};
+use super::{peek_schema, project_schema_for_append, Dataset, FragmentWriter, WriteParams};
+use crate::io::exec::LanceScanExec;
+use crate::{Error, Result};
+
+impl Dataset {
+    /// Merge the rows of `source` into the dataset, matching them on the key columns `on`.
+    ///
+    /// The rows of the dataset that match a source row on all the key columns are replaced by
+    /// the source row (when matched, update), and the source rows that do not match any row are
+    /// inserted (when not matched, insert). The source must contain all the columns of the
+    /// dataset, matched by name, and the keys must be unique in the source.
+    ///
+    /// The source rows are written to new fragments as they are read, and the matches are
+    /// found by a hash join, built on the key columns of the source and probed with a scan of
+    /// the key columns of the dataset. The replaced rows are marked as deleted, in one new
+    /// version. If any row is replaced, the indices on
+    /// the non-key columns are dropped.
+    pub async fn merge_insert(
+        &self,
+        source: &mut Box<dyn RecordBatchReader>,
+        on: &[&str],
+    ) -> Result<Self> {
+        if on.is_empty() {
+            return Err(Error::IO("Merge insert: no key column".to_string()));
+        }
+        let mut batches = source
+            .filter(|batch| !matches!(batch, Ok(b) if b.num_rows() == 0))
+            .peekable();
+        if batches.peek().is_none() {
+            return Ok(self.clone());
+        }
+        let source_schema = peek_schema(&mut batches)?;
+        let schema = project_schema_for_append(self.schema(), &source_schema, "Merge insert")?;
+        if schema.fields.len() != self.schema().fields.len() {
+            return Err(Error::Schema(
+                "Merge insert: the source must contain all the columns of the dataset".to_string(),
+            ));
+        }
+        for column in on {
+            if self.schema().fields.iter().all(|f| f.name != *column) {
+                return Err(Error::Schema(format!(
+                    "Merge insert: key column '{column}' does not exist"
+                )));
+            }
+        }
+
+        // Write the source rows to new fragments as they are read, and only keep their key
+        // columns for the join.
+        let source_arrow_schema = ArrowSchema::from(&source_schema);
+        let key_indices = on
+            .iter()
+            .map(|column| source_arrow_schema.index_of(column))
+            .collect::<std::result::Result<Vec<_>, ArrowError>>()?;
+        let key_schema = Arc::new(source_arrow_schema.project(&key_indices)?);
+        let mut converter = RowConverter::new(
+            key_schema
+                .fields()
+                .iter()
+                .map(|f| SortField::new(f.data_type().clone()))
+                .collect(),
+        )?;
+        let mut keys = HashSet::new();
+        let mut key_batches = vec![];
+        let mut writer = FragmentWriter::new(
+            &self.object_store,
+            &schema,
+            self.manifest.next_fragment_id(),
+            &WriteParams::default(),
+        );
+        for batch in batches {
+            let batch = batch?;
+            let key_batch = batch.project(&key_indices)?;
+            let rows = converter.convert_columns(key_batch.columns())?;
+            if !rows.iter().all(|row| keys.insert(row.owned())) {
+                return Err(Error::IO(format!(
+                    "Merge insert: the source has duplicate keys on {on:?}"
+                )));
+            }
+            writer.write(&batch).await?;
+            key_batches.push(RecordBatch::try_new(
+                key_schema.clone(),
+                key_batch.columns().to_vec(),
+            )?);
+        }
+        let new_fragments = writer.finish().await?;
+
+        let source_node = Arc::new(MemoryExec::try_new(
+            &[key_batches],
+            key_schema.clone(),
+            None,
+        )?);
+        let scan_node = Arc::new(LanceScanExec::new(
+            Arc::new(self.clone()),
+            self.fragments().clone(),
+            Arc::new(self.schema().project(on)?),
+            DEFAULT_BATCH_SIZE,
+            PREFETCH_SIZE,
+            true,
+        ));
+        let scan_schema = scan_node.schema();
+        let join_on = on
+            .iter()
+            .map(|column| {
+                Ok((
+                    Column::new(column, key_schema.index_of(column)?),
+                    Column::new(column, scan_schema.index_of(column)?),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let join_node = HashJoinExec::try_new(
+            source_node,
+            scan_node,
+            join_on,
+            None,
+            &JoinType::Inner,
+            PartitionMode::CollectLeft,
+            &false,
+        )?;
+        let matched = join_node
+            .execute(0, SessionContext::new().task_ctx())?
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        let deleted_rows = group_row_ids_by_fragment(&matched)?;
+        let mut fragments = if deleted_rows.is_empty() {
+            self.fragments().as_ref().clone()
+        } else {
+            self.delete_rows(&deleted_rows).await?
+        };
+        fragments.extend(new_fragments);
+
+        let mut manifest = self.manifest.as_ref().clone();
+        manifest.fragments = Arc::new(fragments);
+        let key_field_ids = on
+            .iter()
+            .map(|column| self.schema().field_id(column))
+            .collect::<Result<Vec<_>>>()?;
+        let indices = self
+            .load_indices()
+            .await?
+            .into_iter()
+            .filter(|idx| {
+                deleted_rows.is_empty() || idx.fields.iter().all(|f| key_field_ids.contains(f))
+            })
+            .collect();
+        self.commit_manifest(manifest, Some(indices)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow_array::{
+        cast::{as_primitive_array, as_string_array},
+        Int32Array, StringArray,
+    };
+    use arrow_schema::{DataType, Field};
+    use arrow_select::concat::concat_batches;
+    use tempfile::tempdir;
+
+    use crate::arrow::RecordBatchBuffer;
+
+    #[tokio::test]
+    async fn test_merge_insert() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("value", DataType::Utf8, true),
+        ]));
+        let new_batches = |ids: Vec<i32>, value: &str| -> Box<dyn RecordBatchReader> {
+            let values = ids.iter().map(|_| value).collect::<Vec<_>>();
+            Box::new(RecordBatchBuffer::new(vec![RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int32Array::from(ids)),
+                    Arc::new(StringArray::from(values)),
+                ],
+            )
+            .unwrap()]))
+        };
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        let dataset = Dataset::write(&mut new_batches((0..10).collect(), "old"), test_uri, None)
+            .await
+            .unwrap();
+
+        let dataset = dataset
+            .merge_insert(&mut new_batches(vec![5, 6, 10, 11], "new"), &["id"])
+            .await
+            .unwrap();
+        assert_eq!(dataset.version().version, 2);
+        assert_eq!(dataset.count_rows().await.unwrap(), 12);
+        assert_eq!(
+            dataset.fragments().iter().map(|f| f.id).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+
+        let batches = dataset
+            .scan()
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let batch = concat_batches(&schema, &batches).unwrap();
+        let ids: &Int32Array = as_primitive_array(batch.column(0));
+        let values = as_string_array(batch.column(1));
+        let mut rows = ids
+            .values()
+            .iter()
+            .zip(values.iter())
+            .map(|(id, value)| (*id, value.unwrap().to_string()))
+            .collect::<Vec<_>>();
+        rows.sort();
+        let expected = (0..12)
+            .map(|id| {
+                let value = if id < 5 || id > 6 && id < 10 {
+                    "old"
+                } else {
+                    "new"
+                };
+                (id, value.to_string())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(rows, expected);
+
+        // The source must have all the columns.
+        let id_only = Arc::new(ArrowSchema::new(vec![Field::new(
+            "id",
+            DataType::Int32,
+            false,
+        )]));
+        let mut source: Box<dyn RecordBatchReader> =
+            Box::new(RecordBatchBuffer::new(vec![RecordBatch::try_new(
+                id_only,
+                vec![Arc::new(Int32Array::from(vec![1]))],
+            )
+            .unwrap()]));
+        assert!(matches!(
+            dataset.merge_insert(&mut source, &["id"]).await,
+            Err(Error::Schema(_))
+        ));
+        let extra_column = Arc::new(ArrowSchema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("value", DataType::Utf8, true),
+            Field::new("extra", DataType::Int32, true),
+        ]));
+        let mut source: Box<dyn RecordBatchReader> =
+            Box::new(RecordBatchBuffer::new(vec![RecordBatch::try_new(
+                extra_column,
+                vec![
+                    Arc::new(Int32Array::from(vec![1])),
+                    Arc::new(StringArray::from(vec!["new"])),
+                    Arc::new(Int32Array::from(vec![1])),
+                ],
+            )
+            .unwrap()]));
+        assert!(matches!(
+            dataset.merge_insert(&mut source, &["id"]).await,
+            Err(Error::Schema(message)) if message.starts_with("Merge insert with different schema")
+        ));
+        assert!(dataset
+            .merge_insert(&mut new_batches(vec![1], "new"), &["key"])
+            .await
+            .is_err());
+
+        // The keys must be unique in the source.
+        assert!(matches!(
+            dataset
+                .merge_insert(&mut new_batches(vec![3, 20, 3], "dup"), &["id"])
+                .await,
+            Err(Error::IO(message)) if message.contains("duplicate keys")
+        ));
+        let dataset = Dataset::open(test_uri).await.unwrap();
+        assert_eq!(dataset.version().version, 2);
+        assert_eq!(dataset.count_rows().await.unwrap(), 12);
+    }
+}
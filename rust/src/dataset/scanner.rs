@@ -16,8 +16,11 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
-use arrow_array::{Float32Array, RecordBatch};
-use arrow_schema::DataType::Float32;
+use arrow_array::{
+    types::{Float16Type, Float32Type, Float64Type},
+    Array, RecordBatch,
+};
+use arrow_schema::DataType::{self, Float32};
 use arrow_schema::{Field as ArrowField, Schema as ArrowSchema, SchemaRef};
 use datafusion::execution::{
     context::SessionState,
@@ -32,9 +35,10 @@ use futures::stream::{Stream, StreamExt};
 use sqlparser::{dialect::GenericDialect, parser::Parser};
 
 use super::Dataset;
+use crate::arrow::{cast_float_array, BFloat16Type, FloatArray, FloatType};
 use crate::datafusion::physical_expr::column_names_in_expr;
 use crate::datatypes::Schema;
-use crate::format::Index;
+use crate::format::{Fragment, Index};
 use crate::index::vector::{MetricType, Query};
 use crate::io::exec::{GlobalTakeExec, KNNFlatExec, KNNIndexExec, LanceScanExec, LocalTakeExec};
 use crate::{Error, Result};
@@ -43,7 +47,7 @@ use crate::{Error, Result};
 pub const ROW_ID: &str = "_rowid";
 pub const DEFAULT_BATCH_SIZE: usize = 8192;
 
-const PREFETCH_SIZE: usize = 8;
+pub(crate) const PREFETCH_SIZE: usize = 8;
 
 /// Dataset Scanner
 ///
@@ -76,6 +80,9 @@ pub struct Scanner {
 
     /// Scan the dataset with a meta column: "_rowid"
     with_row_id: bool,
+
+    /// Scan only these fragments, instead of all the fragments of the dataset.
+    fragments: Option<Arc<Vec<Fragment>>>,
 }
 
 impl Scanner {
@@ -90,6 +97,7 @@ impl Scanner {
             offset: None,
             nearest: None,
             with_row_id: false,
+            fragments: None,
         }
     }
 
@@ -150,7 +158,11 @@ impl Scanner {
     }
 
     /// Find k-nearest neighbour within the vector column.
-    pub fn nearest(&mut self, column: &str, q: &Float32Array, k: usize) -> Result<&mut Self> {
+    ///
+    /// The vector column can be of `bfloat16`, `f16`, `f32` or `f64` values, and the
+    /// distances are computed in its float type. The query vector `q` is converted to
+    /// the float type of the column.
+    pub fn nearest(&mut self, column: &str, q: &dyn Array, k: usize) -> Result<&mut Self> {
         if k == 0 {
             return Err(Error::IO("k must be positive".to_string()));
         }
@@ -160,10 +172,26 @@ impl Scanner {
             ));
         }
         // make sure the field exists
-        self.dataset.schema().project(&[column])?;
+        let schema = self.dataset.schema().project(&[column])?;
+        let float_type = match schema.fields[0].data_type() {
+            DataType::FixedSizeList(elem, _) => FloatType::try_from(elem.data_type()).ok(),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            Error::IO(format!(
+                "Column {column} is not a vector column of floats: {}",
+                schema.fields[0].data_type()
+            ))
+        })?;
+        let key = match float_type {
+            FloatType::BFloat16 => cast_float_array::<BFloat16Type>(q)?.into_array_ref(),
+            FloatType::Float16 => cast_float_array::<Float16Type>(q)?.into_array_ref(),
+            FloatType::Float32 => cast_float_array::<Float32Type>(q)?.into_array_ref(),
+            FloatType::Float64 => cast_float_array::<Float64Type>(q)?.into_array_ref(),
+        };
         self.nearest = Some(Query {
             column: column.to_string(),
-            key: Arc::new(q.clone()),
+            key,
             k,
             nprobs: 1,
             refine_factor: None,
@@ -234,6 +262,15 @@ impl Scanner {
         }
     }
 
+    /// Only scan the given fragments of the dataset.
+    ///
+    /// The vector indices cover the whole dataset, so [Self::nearest] does a flat search over
+    /// these fragments instead of using an index.
+    pub fn with_fragments(&mut self, fragments: Vec<Fragment>) -> &mut Self {
+        self.fragments = Some(Arc::new(fragments));
+        self
+    }
+
     /// Create a stream of this Scanner.
     ///
     /// TODO: implement as IntoStream/IntoIterator.
@@ -251,7 +288,9 @@ impl Scanner {
 
         let mut plan: Arc<dyn ExecutionPlan> = if let Some(q) = self.nearest.as_ref() {
             let column_id = self.dataset.schema().field_id(q.column.as_str())?;
-            let use_index = self.nearest.as_ref().map(|q| q.use_index).unwrap_or(false);
+            // The index covers all the fragments, so a scan of some fragments is searched
+            // exhaustively instead.
+            let use_index = q.use_index && self.fragments.is_none();
             let indices = if use_index {
                 self.dataset.load_indices().await?
             } else {
@@ -313,7 +352,7 @@ impl Scanner {
                 )?,
             );
             let scan = self.scan(true, filter_schema);
-            self.filter_node(filter, scan, true)?
+            self.filter_node(filter, scan, !with_row_id)?
         } else {
             self.scan(with_row_id, Arc::new(self.projections.clone()))
         };
@@ -335,7 +374,9 @@ impl Scanner {
     fn scan(&self, with_row_id: bool, projection: Arc<Schema>) -> Arc<dyn ExecutionPlan> {
         Arc::new(LanceScanExec::new(
             self.dataset.clone(),
-            self.dataset.fragments().clone(),
+            self.fragments
+                .clone()
+                .unwrap_or_else(|| self.dataset.fragments().clone()),
             projection,
             self.batch_size,
             PREFETCH_SIZE,
@@ -0,0 +1,754 @@
+// Copyright 2023 Lance Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Add, drop and alter the columns of a [Dataset].
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow_array::{
+    cast::as_primitive_array, RecordBatch, RecordBatchReader, UInt32Array, UInt64Array,
+};
+use arrow_schema::{ArrowError, DataType, Field as ArrowField, Schema as ArrowSchema};
+use arrow_select::{concat::concat_batches, take::take};
+use uuid::Uuid;
+
+use super::fragment::FragmentReader;
+use super::{new_file_writer, Dataset, ROW_ID};
+use crate::arrow::*;
+use crate::datafusion::physical_expr::column_names_in_expr;
+use crate::datatypes::Schema;
+use crate::format::{DataFile, Fragment};
+use crate::io::exec::Planner;
+use crate::{Error, Result};
+
+/// Alteration of one column, used by [`Dataset::alter_columns`].
+#[derive(Debug, Clone)]
+pub struct ColumnAlteration {
+    /// Qualified name of the column, i.e., `"s.x"` for a nested field.
+    pub path: String,
+
+    /// New name of the column.
+    pub rename: Option<String>,
+
+    /// Whether the column is nullable. Only relaxing a non-nullable column is supported.
+    pub nullable: Option<bool>,
+}
+
+impl ColumnAlteration {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            rename: None,
+            nullable: None,
+        }
+    }
+
+    /// Rename the column.
+    pub fn rename(mut self, name: &str) -> Self {
+        self.rename = Some(name.to_string());
+        self
+    }
+
+    /// Set the nullability of the column.
+    pub fn set_nullable(mut self, nullable: bool) -> Self {
+        self.nullable = Some(nullable);
+        self
+    }
+}
+
+impl Dataset {
+    /// Add new columns to the dataset, with the values from a stream of [RecordBatch]s.
+    ///
+    /// Every batch must have the [ROW_ID] column, i.e., from a scan with row ID, to align the
+    /// new values with the existing rows. All the other columns in the stream are added to the
+    /// dataset as nullable columns. The rows that are not in the stream are nulls.
+    ///
+    /// The stream is processed one fragment at a time, so the rows of each fragment must be
+    /// contiguous in the stream, as in a scan. Each row ID must exist in the dataset and appear
+    /// at most once.
+    ///
+    /// Only the new columns are written, to a new data file in each fragment.
+    /// Upon finish, a new dataset version is generated.
+    pub async fn add_columns(&self, batches: &mut Box<dyn RecordBatchReader>) -> Result<Self> {
+        let input_schema = batches.schema();
+        match input_schema.column_with_name(ROW_ID) {
+            Some((_, field)) if field.data_type() == &DataType::UInt64 => {}
+            Some((_, field)) => {
+                return Err(Error::Schema(format!(
+                    "AddColumns: {ROW_ID} column must be UInt64, got {}",
+                    field.data_type()
+                )));
+            }
+            None => {
+                return Err(Error::Schema(format!(
+                    "AddColumns: {ROW_ID} column does not exist in the input: {input_schema}"
+                )));
+            }
+        }
+        let new_fields = input_schema
+            .fields()
+            .iter()
+            .filter(|f| f.name() != ROW_ID)
+            .map(|f| ArrowField::new(f.name(), f.data_type().clone(), true))
+            .collect::<Vec<_>>();
+        let new_arrow_schema = Arc::new(ArrowSchema::new(new_fields.clone()));
+
+        let fragments_by_id: HashMap<u64, &Fragment> =
+            self.fragments().iter().map(|f| (f.id, f)).collect();
+        let mut schemas = None;
+        let mut added: HashMap<u64, Fragment> = HashMap::new();
+        // The fragment being read from the stream, with its values and the offsets of them.
+        let mut current: Option<(&Fragment, Vec<RecordBatch>, HashMap<u32, u32>)> = None;
+        for batch in batches {
+            let batch = batch?;
+            let values = RecordBatch::try_new(
+                new_arrow_schema.clone(),
+                batch.drop_column(ROW_ID)?.columns().to_vec(),
+            )?;
+            if schemas.is_none() {
+                schemas = Some(self.extend_schema(&new_fields, &values)?);
+            }
+            let (_, new_columns_schema) = schemas.as_ref().unwrap();
+            let row_ids: &UInt64Array = as_primitive_array(batch.column_by_name(ROW_ID).unwrap());
+
+            let mut start = 0;
+            while start < row_ids.len() {
+                let fragment_id = row_ids.value(start) >> 32;
+                let end = (start..row_ids.len())
+                    .find(|idx| row_ids.value(*idx) >> 32 != fragment_id)
+                    .unwrap_or(row_ids.len());
+                if !matches!(&current, Some((f, _, _)) if f.id == fragment_id) {
+                    if let Some((fragment, fragment_values, positions)) = current.take() {
+                        let fragment_values = concat_batches(&new_arrow_schema, &fragment_values)?;
+                        let fragment = self
+                            .add_fragment_columns(
+                                fragment,
+                                new_columns_schema,
+                                &fragment_values,
+                                &positions,
+                            )
+                            .await?;
+                        added.insert(fragment.id, fragment);
+                    }
+                    if added.contains_key(&fragment_id) {
+                        return Err(Error::IO(format!(
+                            "AddColumns: the rows of fragment {fragment_id} are not contiguous"
+                        )));
+                    }
+                    let fragment = fragments_by_id.get(&fragment_id).copied().ok_or_else(|| {
+                        Error::IO(format!(
+                            "AddColumns: row id {} does not exist in the dataset",
+                            row_ids.value(start)
+                        ))
+                    })?;
+                    current = Some((fragment, vec![], HashMap::new()));
+                }
+
+                let (_, fragment_values, positions) = current.as_mut().unwrap();
+                for idx in start..end {
+                    let row_id = row_ids.value(idx);
+                    let position = positions.len() as u32;
+                    if positions.insert(row_id as u32, position).is_some() {
+                        return Err(Error::IO(format!(
+                            "AddColumns: duplicate row id {row_id} in the input"
+                        )));
+                    }
+                }
+                fragment_values.push(values.slice(start, end - start));
+                start = end;
+            }
+        }
+
+        let (schema, new_columns_schema) = match schemas {
+            Some(schemas) => schemas,
+            None => self.extend_schema(
+                &new_fields,
+                &RecordBatch::new_empty(new_arrow_schema.clone()),
+            )?,
+        };
+        if let Some((fragment, fragment_values, positions)) = current.take() {
+            let fragment_values = concat_batches(&new_arrow_schema, &fragment_values)?;
+            let fragment = self
+                .add_fragment_columns(fragment, &new_columns_schema, &fragment_values, &positions)
+                .await?;
+            added.insert(fragment.id, fragment);
+        }
+        let mut fragments = vec![];
+        for fragment in self.fragments().iter() {
+            fragments.push(match added.remove(&fragment.id) {
+                Some(fragment) => fragment,
+                None => {
+                    self.add_fragment_columns(
+                        fragment,
+                        &new_columns_schema,
+                        &RecordBatch::new_empty(new_arrow_schema.clone()),
+                        &HashMap::new(),
+                    )
+                    .await?
+                }
+            });
+        }
+
+        let mut manifest = self.manifest.as_ref().clone();
+        manifest.schema = schema;
+        manifest.fragments = Arc::new(fragments);
+        let indices = self.load_indices().await?;
+        self.commit_manifest(manifest, Some(indices)).await
+    }
+
+    /// Write the new columns of the `fragment` to a new data file, and returns the updated
+    /// fragment.
+    ///
+    /// `positions` maps the offsets of the rows in the fragment to the rows in `values`.
+    /// The rows that are not in `positions` are nulls.
+    async fn add_fragment_columns(
+        &self,
+        fragment: &Fragment,
+        schema: &Schema,
+        values: &RecordBatch,
+        positions: &HashMap<u32, u32>,
+    ) -> Result<Fragment> {
+        let reader = FragmentReader::try_new(self, fragment).await?;
+        if let Some(offset) = positions
+            .keys()
+            .find(|offset| **offset as usize >= reader.len())
+        {
+            return Err(Error::IO(format!(
+                "AddColumns: row id {} does not exist in the dataset",
+                (fragment.id << 32) + *offset as u64
+            )));
+        }
+        let indices = UInt32Array::from_iter(
+            (0..reader.len() as u32).map(|offset| positions.get(&offset).copied()),
+        );
+        let columns = values
+            .columns()
+            .iter()
+            .map(|arr| take(arr.as_ref(), &indices, None))
+            .collect::<std::result::Result<Vec<_>, ArrowError>>()?;
+        let fragment_values = RecordBatch::try_new(values.schema(), columns)?;
+
+        // Keep the same batch layout as the existing data files.
+        let mut offset = 0;
+        let mut batches = vec![];
+        for batch_id in 0..reader.num_batches() as i32 {
+            let num_rows = reader.num_rows_in_batch(batch_id);
+            batches.push(fragment_values.slice(offset, num_rows));
+            offset += num_rows;
+        }
+        self.add_data_file(fragment, schema, &batches).await
+    }
+
+    /// Add new columns to the dataset, computed by SQL expressions over the existing columns.
+    ///
+    /// `columns` are pairs of the new column name and its SQL expression, i.e.,
+    /// `[("double_i", "i * 2")]`.
+    ///
+    /// Only the new columns are written, to a new data file in each fragment.
+    /// Upon finish, a new dataset version is generated.
+    pub async fn add_columns_with_sql(&self, columns: &[(&str, &str)]) -> Result<Self> {
+        let arrow_schema = ArrowSchema::from(self.schema());
+        let planner = Planner::new(Arc::new(arrow_schema.clone()));
+        let mut exprs = vec![];
+        let mut new_fields = vec![];
+        let mut input_columns: Vec<String> = vec![];
+        for (name, value) in columns {
+            let expr = planner.create_physical_expr(&planner.parse_expr(value)?)?;
+            new_fields.push(ArrowField::new(
+                *name,
+                expr.data_type(&arrow_schema)?,
+                expr.nullable(&arrow_schema)?,
+            ));
+            for column in column_names_in_expr(expr.as_ref()) {
+                if !input_columns.contains(&column) {
+                    input_columns.push(column);
+                }
+            }
+            exprs.push(expr);
+        }
+        let new_arrow_schema = Arc::new(ArrowSchema::new(new_fields.clone()));
+        let projection = self
+            .schema()
+            .project(&input_columns.iter().map(|c| c.as_str()).collect::<Vec<_>>())?;
+
+        let mut fragment_batches = vec![];
+        for fragment in self.fragments().iter() {
+            let reader = FragmentReader::try_new(self, fragment).await?;
+            let mut batches = vec![];
+            for batch_id in 0..reader.num_batches() as i32 {
+                let batch = reader.read_batch(batch_id, .., &projection).await?;
+                let columns = exprs
+                    .iter()
+                    .map(|expr| Ok(expr.evaluate(&batch)?.into_array(batch.num_rows())))
+                    .collect::<Result<Vec<_>>>()?;
+                batches.push(RecordBatch::try_new(new_arrow_schema.clone(), columns)?);
+            }
+            fragment_batches.push(batches);
+        }
+
+        let dictionary_batch = fragment_batches
+            .iter()
+            .flatten()
+            .next()
+            .cloned()
+            .unwrap_or_else(|| RecordBatch::new_empty(new_arrow_schema.clone()));
+        let (schema, new_columns_schema) = self.extend_schema(&new_fields, &dictionary_batch)?;
+        let mut fragments = vec![];
+        for (fragment, batches) in self.fragments().iter().zip(fragment_batches.iter()) {
+            fragments.push(
+                self.add_data_file(fragment, &new_columns_schema, batches)
+                    .await?,
+            );
+        }
+
+        let mut manifest = self.manifest.as_ref().clone();
+        manifest.schema = schema;
+        manifest.fragments = Arc::new(fragments);
+        let indices = self.load_indices().await?;
+        self.commit_manifest(manifest, Some(indices)).await
+    }
+
+    /// Extend the dataset schema with new top-level fields.
+    ///
+    /// Returns the extended schema, and the schema of the new fields only. The dictionaries
+    /// of the new fields are set from `batch`.
+    fn extend_schema(
+        &self,
+        fields: &[ArrowField],
+        batch: &RecordBatch,
+    ) -> Result<(Schema, Schema)> {
+        let mut schema = self.schema().clone();
+        schema.extend(fields, self.manifest.max_field_id() + 1)?;
+        let mut new_columns_schema =
+            schema.project(&fields.iter().map(|f| f.name().as_str()).collect::<Vec<_>>())?;
+        new_columns_schema.set_dictionary(batch)?;
+        Ok((self.schema().merge(&new_columns_schema), new_columns_schema))
+    }
+
+    /// Write `batches` to a new data file of the `fragment`, and returns the updated fragment.
+    ///
+    /// `batches` must have the same batch layout as the existing data files of the fragment.
+    async fn add_data_file(
+        &self,
+        fragment: &Fragment,
+        schema: &Schema,
+        batches: &[RecordBatch],
+    ) -> Result<Fragment> {
+        let file_path = format!("{}.lance", Uuid::new_v4());
+        let mut writer = new_file_writer(&self.object_store, &file_path, schema).await?;
+        for batch in batches {
+            writer.write(batch).await?;
+        }
+        writer.finish().await?;
+
+        let mut fragment = fragment.clone();
+        fragment.files.push(DataFile::new(&file_path, schema));
+        Ok(fragment)
+    }
+
+    /// Drop columns from the dataset.
+    ///
+    /// Nested fields can be dropped by the qualified name, i.e., `"s.x"`.
+    ///
+    /// Only the schema in the manifest is changed, the data files are not rewritten.
+    /// The indices on the dropped columns are removed.
+    /// Upon finish, a new dataset version is generated.
+    pub async fn drop_columns(&self, columns: &[&str]) -> Result<Self> {
+        let dropped = self.schema().project(columns)?;
+        let schema = self.schema().exclude(&dropped)?;
+        if schema.fields.is_empty() {
+            return Err(Error::Schema(
+                "DropColumns: can not drop all the columns".to_string(),
+            ));
+        }
+
+        let dropped_field_ids = dropped.field_ids();
+        let indices = self
+            .load_indices()
+            .await?
+            .into_iter()
+            .filter(|idx| !idx.fields.iter().any(|f| dropped_field_ids.contains(f)))
+            .collect();
+
+        let mut manifest = self.manifest.as_ref().clone();
+        manifest.schema = schema;
+        self.commit_manifest(manifest, Some(indices)).await
+    }
+
+    /// Rename columns, or relax non-nullable columns to be nullable.
+    ///
+    /// Only the schema in the manifest is changed, the data files are not rewritten.
+    /// Upon finish, a new dataset version is generated.
+    pub async fn alter_columns(&self, alterations: &[ColumnAlteration]) -> Result<Self> {
+        let mut schema = self.schema().clone();
+        for alteration in alterations {
+            let field_id = schema
+                .field(&alteration.path)
+                .map(|f| f.id)
+                .ok_or_else(|| {
+                    Error::Schema(format!(
+                        "AlterColumns: column '{}' does not exist",
+                        alteration.path
+                    ))
+                })?;
+            if let Some(new_name) = alteration.rename.as_ref() {
+                let new_path = match alteration.path.rsplit_once('.') {
+                    Some((parent, _)) => format!("{parent}.{new_name}"),
+                    None => new_name.clone(),
+                };
+                if new_name.contains('.') || schema.field(&new_path).is_some() {
+                    return Err(Error::Schema(format!(
+                        "AlterColumns: can not rename column '{}' to '{new_name}'",
+                        alteration.path
+                    )));
+                }
+            }
+            let field = schema.mut_field_by_id(field_id).unwrap();
+            if let Some(nullable) = alteration.nullable {
+                if field.nullable && !nullable {
+                    return Err(Error::Schema(format!(
+                        "AlterColumns: can not make nullable column '{}' non-nullable",
+                        alteration.path
+                    )));
+                }
+                field.nullable = nullable;
+            }
+            if let Some(new_name) = alteration.rename.as_ref() {
+                field.name = new_name.clone();
+            }
+        }
+
+        let mut manifest = self.manifest.as_ref().clone();
+        manifest.schema = schema;
+        let indices = self.load_indices().await?;
+        self.commit_manifest(manifest, Some(indices)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow_array::{
+        cast::as_string_array, ArrayRef, Int32Array, Int64Array, StringArray, StructArray,
+    };
+    use arrow_schema::Field;
+    use futures::stream::TryStreamExt;
+    use tempfile::tempdir;
+
+    use crate::dataset::WriteParams;
+
+    #[tokio::test]
+    async fn test_add_columns() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            false,
+        )]));
+        let batches = RecordBatchBuffer::new(
+            (0..10)
+                .map(|i| {
+                    RecordBatch::try_new(
+                        schema.clone(),
+                        vec![Arc::new(Int32Array::from_iter_values(i * 20..(i + 1) * 20))],
+                    )
+                    .unwrap()
+                })
+                .collect(),
+        );
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut write_params = WriteParams::default();
+        write_params.max_rows_per_file = 40;
+        write_params.max_rows_per_group = 10;
+        let mut batches: Box<dyn RecordBatchReader> = Box::new(batches);
+        let dataset = Dataset::write(&mut batches, test_uri, Some(write_params))
+            .await
+            .unwrap();
+
+        // Add a column computed from the existing column.
+        let dataset = dataset
+            .add_columns_with_sql(&[("double_i", "i * 2")])
+            .await
+            .unwrap();
+        assert_eq!(dataset.version().version, 2);
+        assert!(dataset.fragments().iter().all(|f| f.files.len() == 2));
+
+        // Add a column from a stream aligned by the row ID.
+        let mut scanner = dataset.scan();
+        scanner.project(&["i"]).unwrap().with_row_id();
+        let s_batches = scanner
+            .try_into_stream()
+            .await
+            .unwrap()
+            .map_ok(|batch| {
+                let i_arr: &Int32Array = as_primitive_array(batch.column_by_name("i").unwrap());
+                let s_arr =
+                    StringArray::from_iter_values(i_arr.values().iter().map(|v| format!("s-{v}")));
+                RecordBatch::try_new(
+                    Arc::new(ArrowSchema::new(vec![
+                        Field::new("s", DataType::Utf8, false),
+                        Field::new(ROW_ID, DataType::UInt64, false),
+                    ])),
+                    vec![
+                        Arc::new(s_arr),
+                        batch.column_by_name(ROW_ID).unwrap().clone(),
+                    ],
+                )
+                .unwrap()
+            })
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let mut reader: Box<dyn RecordBatchReader> = Box::new(RecordBatchBuffer::new(s_batches));
+        let dataset = dataset.add_columns(&mut reader).await.unwrap();
+        assert_eq!(dataset.version().version, 3);
+        assert_eq!(
+            ArrowSchema::from(dataset.schema()),
+            ArrowSchema::new(vec![
+                Field::new("i", DataType::Int32, false),
+                Field::new("double_i", DataType::Int32, false),
+                Field::new("s", DataType::Utf8, true),
+            ])
+        );
+        assert_eq!(dataset.count_rows().await.unwrap(), 200);
+
+        let batches = dataset
+            .scan()
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+        let i_arr: &Int32Array = as_primitive_array(batch.column_by_name("i").unwrap());
+        let double_i_arr: &Int32Array =
+            as_primitive_array(batch.column_by_name("double_i").unwrap());
+        let s_arr = as_string_array(batch.column_by_name("s").unwrap());
+        for idx in 0..batch.num_rows() {
+            let i = i_arr.value(idx);
+            assert_eq!(double_i_arr.value(idx), i * 2);
+            assert_eq!(s_arr.value(idx), format!("s-{i}"));
+        }
+
+        // Take rows from both the old and the new data files.
+        let taken = dataset
+            .take(&[5, 199], &dataset.schema().project(&["s", "i"]).unwrap())
+            .await
+            .unwrap();
+        let s_arr = as_string_array(taken.column_by_name("s").unwrap());
+        assert_eq!(s_arr.value(0), "s-5");
+        assert_eq!(s_arr.value(1), "s-199");
+
+        // Adding a column that already exists fails.
+        assert!(dataset
+            .add_columns_with_sql(&[("i", "i + 1")])
+            .await
+            .is_err());
+
+        // The row IDs must be UInt64, exist in the dataset and be unique.
+        let new_values = |row_ids: ArrayRef| -> Box<dyn RecordBatchReader> {
+            let schema = Arc::new(ArrowSchema::new(vec![
+                Field::new("t", DataType::Int32, false),
+                Field::new(ROW_ID, row_ids.data_type().clone(), false),
+            ]));
+            let values = Int32Array::from_iter_values(0..row_ids.len() as i32);
+            Box::new(RecordBatchBuffer::new(vec![RecordBatch::try_new(
+                schema,
+                vec![Arc::new(values), row_ids],
+            )
+            .unwrap()]))
+        };
+        assert!(matches!(
+            dataset
+                .add_columns(&mut new_values(Arc::new(Int64Array::from(vec![0, 1]))))
+                .await,
+            Err(Error::Schema(_))
+        ));
+        assert!(matches!(
+            dataset
+                .add_columns(&mut new_values(Arc::new(UInt64Array::from(vec![0, 40]))))
+                .await,
+            Err(Error::IO(message)) if message.contains("row id 40 does not exist")
+        ));
+        assert!(matches!(
+            dataset
+                .add_columns(&mut new_values(Arc::new(UInt64Array::from(vec![
+                    0,
+                    10 << 32
+                ]))))
+                .await,
+            Err(Error::IO(message)) if message.contains("does not exist")
+        ));
+        assert!(matches!(
+            dataset
+                .add_columns(&mut new_values(Arc::new(UInt64Array::from(vec![1, 2, 1]))))
+                .await,
+            Err(Error::IO(message)) if message.contains("duplicate row id 1")
+        ));
+        assert!(matches!(
+            dataset
+                .add_columns(&mut new_values(Arc::new(UInt64Array::from(vec![
+                    0,
+                    1 << 32,
+                    1
+                ]))))
+                .await,
+            Err(Error::IO(message)) if message.contains("not contiguous")
+        ));
+        assert_eq!(Dataset::open(test_uri).await.unwrap().version().version, 3);
+
+        // The previous version keeps the old schema.
+        let dataset = Dataset::checkout(test_uri, 1).await.unwrap();
+        assert_eq!(ArrowSchema::from(dataset.schema()), *schema);
+    }
+
+    #[tokio::test]
+    async fn test_drop_and_alter_columns() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("i", DataType::Int32, false),
+            Field::new("s", DataType::Utf8, false),
+            Field::new(
+                "st",
+                DataType::Struct(vec![
+                    Field::new("x", DataType::Int32, false),
+                    Field::new("y", DataType::Int32, false),
+                ]),
+                false,
+            ),
+        ]));
+        let batches = RecordBatchBuffer::new(
+            (0..5)
+                .map(|i| {
+                    RecordBatch::try_new(
+                        schema.clone(),
+                        vec![
+                            Arc::new(Int32Array::from_iter_values(i * 20..(i + 1) * 20)),
+                            Arc::new(StringArray::from_iter_values(
+                                (i * 20..(i + 1) * 20).map(|v| format!("s-{v}")),
+                            )),
+                            Arc::new(StructArray::from(vec![
+                                (
+                                    Field::new("x", DataType::Int32, false),
+                                    Arc::new(Int32Array::from_iter_values(i * 20..(i + 1) * 20))
+                                        as ArrayRef,
+                                ),
+                                (
+                                    Field::new("y", DataType::Int32, false),
+                                    Arc::new(Int32Array::from_iter_values(
+                                        (i * 20..(i + 1) * 20).map(|v| v * 10),
+                                    )) as ArrayRef,
+                                ),
+                            ])),
+                        ],
+                    )
+                    .unwrap()
+                })
+                .collect(),
+        );
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut write_params = WriteParams::default();
+        write_params.max_rows_per_file = 40;
+        write_params.max_rows_per_group = 10;
+        let mut batches: Box<dyn RecordBatchReader> = Box::new(batches);
+        let dataset = Dataset::write(&mut batches, test_uri, Some(write_params))
+            .await
+            .unwrap();
+
+        let dataset = dataset.drop_columns(&["s", "st.x"]).await.unwrap();
+        assert_eq!(dataset.version().version, 2);
+        let dataset = dataset
+            .alter_columns(&[
+                ColumnAlteration::new("i").rename("id").set_nullable(true),
+                ColumnAlteration::new("st.y").rename("z"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(dataset.version().version, 3);
+
+        let expected_schema = ArrowSchema::new(vec![
+            Field::new("id", DataType::Int32, true),
+            Field::new(
+                "st",
+                DataType::Struct(vec![Field::new("z", DataType::Int32, false)]),
+                false,
+            ),
+        ]);
+        assert_eq!(ArrowSchema::from(dataset.schema()), expected_schema);
+
+        let batches = dataset
+            .scan()
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let batch = concat_batches(&Arc::new(expected_schema), &batches).unwrap();
+        let id_arr: &Int32Array = as_primitive_array(batch.column_by_name("id").unwrap());
+        let z_arr: &Int32Array =
+            as_primitive_array(batch.column_by_qualified_name("st.z").unwrap());
+        assert_eq!(id_arr.values(), (0..100).collect::<Vec<_>>().as_slice());
+        assert_eq!(
+            z_arr.values(),
+            (0..100).map(|v| v * 10).collect::<Vec<_>>().as_slice()
+        );
+
+        let dataset = dataset
+            .alter_columns(&[ColumnAlteration::new("st.z").set_nullable(true)])
+            .await
+            .unwrap();
+        assert!(dataset.schema().field("st.z").unwrap().nullable);
+
+        // Invalid alterations.
+        assert!(dataset
+            .alter_columns(&[ColumnAlteration::new("id").set_nullable(false)])
+            .await
+            .is_err());
+        assert!(dataset
+            .alter_columns(&[ColumnAlteration::new("id").rename("st")])
+            .await
+            .is_err());
+        assert!(dataset.drop_columns(&["id", "st"]).await.is_err());
+
+        // The IDs of the dropped fields are not reused.
+        let dataset = dataset
+            .add_columns_with_sql(&[("s", "id * 2")])
+            .await
+            .unwrap();
+        assert!(dataset.schema().field("s").unwrap().id > 4);
+
+        // The previous version still has the old schema and data.
+        let dataset = Dataset::checkout(test_uri, 1).await.unwrap();
+        assert_eq!(ArrowSchema::from(dataset.schema()), *schema);
+        let batches = dataset
+            .scan()
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let batch = concat_batches(&schema, &batches).unwrap();
+        let s_arr = as_string_array(batch.column_by_name("s").unwrap());
+        assert_eq!(s_arr.value(42), "s-42");
+    }
+}
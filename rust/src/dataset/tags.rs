@@ -0,0 +1,238 @@
+// Copyright 2023 Lance Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tag the versions of a [Dataset].
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use futures::stream::TryStreamExt;
+use object_store::path::Path;
+use uuid::Uuid;
+
+use super::{manifest_path, tag_path, Dataset, TAGS_DIR};
+use crate::io::ObjectStore;
+use crate::{Error, Result};
+
+/// Read the version that the tag file at `path` refers to.
+async fn read_tag(object_store: &ObjectStore, path: &Path, name: &str) -> Result<u64> {
+    let bytes = match object_store.inner.get(path).await {
+        Ok(result) => result.bytes().await?,
+        Err(object_store::Error::NotFound { .. }) => {
+            return Err(Error::IO(format!("Tag '{name}' does not exist")));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    String::from_utf8_lossy(&bytes)
+        .trim()
+        .parse()
+        .map_err(|e| Error::IO(format!("Invalid tag file of '{name}': {e}")))
+}
+
+impl Dataset {
+    /// Check out the version of the dataset tagged with `name`.
+    pub async fn checkout_tag(uri: &str, name: &str) -> Result<Self> {
+        let object_store = Arc::new(ObjectStore::new(uri).await?);
+
+        let base_path = object_store.base_path().clone();
+        let version = read_tag(&object_store, &tag_path(&base_path, name), name).await?;
+        let manifest_file = manifest_path(&base_path, version);
+        Self::checkout_manifest(object_store, base_path, &manifest_file).await
+    }
+
+    /// Tag the `version` of the dataset with `name`.
+    ///
+    /// The tag name must be unique within the dataset, and can only contain ASCII letters,
+    /// digits, `-`, `_` and `.`.
+    pub async fn tag(&self, version: u64, name: &str) -> Result<()> {
+        if name.is_empty()
+            || name.starts_with('.')
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err(Error::IO(format!("Invalid tag name: '{name}'")));
+        }
+        if !self
+            .object_store
+            .exists(&self.manifest_file(version))
+            .await?
+        {
+            return Err(Error::IO(format!(
+                "Can not tag '{name}': version {version} does not exist"
+            )));
+        }
+
+        let path = tag_path(&self.base, name);
+        let staging_path = self
+            .base
+            .child(TAGS_DIR)
+            .child(format!("{name}.txt-{}.tmp", Uuid::new_v4()));
+        self.object_store
+            .inner
+            .put(&staging_path, version.to_string().into())
+            .await?;
+        match self
+            .commit_handler
+            .commit(&self.object_store, &staging_path, &path, version)
+            .await
+        {
+            Err(Error::CommitConflict { .. }) => {
+                Err(Error::IO(format!("Tag '{name}' already exists")))
+            }
+            Err(e) => {
+                let _ = self.object_store.inner.delete(&staging_path).await;
+                Err(e)
+            }
+            Ok(()) => Ok(()),
+        }
+    }
+
+    /// Delete the tag `name`. The tagged version is not affected.
+    pub async fn delete_tag(&self, name: &str) -> Result<()> {
+        let path = tag_path(&self.base, name);
+        if !self.object_store.exists(&path).await? {
+            return Err(Error::IO(format!("Tag '{name}' does not exist")));
+        }
+        self.object_store.inner.delete(&path).await?;
+        Ok(())
+    }
+
+    /// List all the tags, as a map from the tag name to the version.
+    pub async fn list_tags(&self) -> Result<BTreeMap<String, u64>> {
+        let mut tags = BTreeMap::new();
+        let mut files = self
+            .object_store
+            .inner
+            .list(Some(&self.base.child(TAGS_DIR)))
+            .await?;
+        while let Some(meta) = files.try_next().await? {
+            let Some(name) = meta
+                .location
+                .filename()
+                .and_then(|f| f.strip_suffix(".txt"))
+                .map(|n| n.to_string())
+            else {
+                continue;
+            };
+            let version = read_tag(&self.object_store, &meta.location, &name).await?;
+            tags.insert(name, version);
+        }
+        Ok(tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::ops::Range;
+
+    use arrow_array::{Int32Array, RecordBatch, RecordBatchReader};
+    use arrow_schema::{DataType, Field, Schema as ArrowSchema};
+    use chrono::Duration;
+    use tempfile::tempdir;
+
+    use crate::arrow::RecordBatchBuffer;
+    use crate::dataset::{WriteMode, WriteParams};
+
+    #[tokio::test]
+    async fn test_tags() {
+        let test_dir = tempdir().unwrap();
+
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            false,
+        )]));
+        let new_batches = |range: Range<i32>| -> Box<dyn RecordBatchReader> {
+            Box::new(RecordBatchBuffer::new(vec![RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int32Array::from_iter_values(range))],
+            )
+            .unwrap()]))
+        };
+
+        let test_uri = test_dir.path().to_str().unwrap();
+        Dataset::write(&mut new_batches(0..20), test_uri, None)
+            .await
+            .unwrap();
+        let mut write_params = WriteParams::default();
+        write_params.mode = WriteMode::Append;
+        for range in [20..40, 40..60] {
+            Dataset::write(
+                &mut new_batches(range),
+                test_uri,
+                Some(write_params.clone()),
+            )
+            .await
+            .unwrap();
+        }
+        let dataset = Dataset::open(test_uri).await.unwrap();
+        assert_eq!(dataset.version().version, 3);
+
+        dataset.tag(1, "train-2026-10").await.unwrap();
+        dataset.tag(2, "eval").await.unwrap();
+        // Tag names are unique.
+        assert!(dataset.tag(2, "train-2026-10").await.is_err());
+        assert!(dataset.tag(10, "future").await.is_err());
+        assert!(dataset.tag(1, "a/b").await.is_err());
+        assert!(dataset.tag(1, "").await.is_err());
+        assert_eq!(
+            dataset.list_tags().await.unwrap(),
+            BTreeMap::from([("eval".to_string(), 2), ("train-2026-10".to_string(), 1)])
+        );
+
+        let tagged = Dataset::checkout_tag(test_uri, "train-2026-10")
+            .await
+            .unwrap();
+        assert_eq!(tagged.version().version, 1);
+        assert_eq!(tagged.count_rows().await.unwrap(), 20);
+
+        dataset.delete_tag("eval").await.unwrap();
+        assert!(dataset.delete_tag("eval").await.is_err());
+        assert!(Dataset::checkout_tag(test_uri, "eval").await.is_err());
+        assert_eq!(dataset.list_tags().await.unwrap().len(), 1);
+
+        // Tagged versions can be kept during cleanup.
+        let stats = dataset
+            .cleanup_old_versions(Duration::zero(), true, false)
+            .await
+            .unwrap();
+        assert_eq!(stats.old_versions, 1);
+        let tagged = Dataset::checkout_tag(test_uri, "train-2026-10")
+            .await
+            .unwrap();
+        assert_eq!(tagged.count_rows().await.unwrap(), 20);
+
+        // A dry run reports the tags that would be removed.
+        let dry_run_stats = dataset
+            .cleanup_old_versions(Duration::zero(), false, true)
+            .await
+            .unwrap();
+        assert_eq!(dry_run_stats.old_versions, 1);
+        assert_eq!(dry_run_stats.tags_removed, 1);
+        assert_eq!(dataset.list_tags().await.unwrap().len(), 1);
+
+        let stats = dataset
+            .cleanup_old_versions(Duration::zero(), false, false)
+            .await
+            .unwrap();
+        assert_eq!(stats, dry_run_stats);
+        assert!(dataset.list_tags().await.unwrap().is_empty());
+        assert!(Dataset::checkout_tag(test_uri, "train-2026-10")
+            .await
+            .is_err());
+    }
+}
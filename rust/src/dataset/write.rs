@@ -15,6 +15,15 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use arrow_schema::Schema as ArrowSchema;
+
+use super::fragment::FragmentMetadata;
+use crate::encodings::{compression::Compression, Encoding};
+use crate::io::CommitHandler;
+
 /// The mode to write dataset.
 #[derive(Debug, Clone, Copy)]
 pub enum WriteMode {
@@ -26,8 +35,24 @@ pub enum WriteMode {
     Overwrite,
 }
 
+/// Operation to commit as a new version with [Dataset::commit](super::Dataset::commit).
+#[derive(Debug, Clone)]
+pub enum Operation {
+    /// Append the fragments to the latest version of the dataset.
+    Append(Vec<FragmentMetadata>),
+
+    /// Overwrite the dataset with the fragments, or create the dataset if it does not exist.
+    ///
+    /// The fragments must be written with the field IDs of `schema`, i.e., by
+    /// [Fragment::create](crate::format::Fragment::create) with the same schema.
+    Overwrite {
+        fragments: Vec<FragmentMetadata>,
+        schema: ArrowSchema,
+    },
+}
+
 /// Dataset Write Parameters
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct WriteParams {
     /// Max number of records per file.
     pub max_rows_per_file: usize,
@@ -35,8 +60,62 @@ pub struct WriteParams {
     /// Max number of rows per row group.
     pub max_rows_per_group: usize,
 
+    /// Target size of each data file in bytes.
+    ///
+    /// A file is closed once it reaches this size, so it can be larger by up to one group.
+    pub max_bytes_per_file: Option<usize>,
+
+    /// Max size of each row group in bytes, estimated from the Arrow buffers of the rows.
+    ///
+    /// A group has at least one row, even if the row is larger than this.
+    pub max_bytes_per_group: Option<usize>,
+
+    /// Max number of data files to write concurrently.
+    ///
+    /// With more than one, the row groups are dealt to the open files in turn, so the rows of
+    /// a fragment are not contiguous in the input. The fragment IDs are still deterministic.
+    pub max_concurrent_files: usize,
+
     /// Write mode
     pub mode: WriteMode,
+
+    /// Compression of the data pages of the columns, unless set in [Self::column_compression].
+    ///
+    /// Each page is compressed on its own, so reading a few rows only decompresses the pages
+    /// they are in. In [WriteMode::Append], the columns keep the compression of the dataset.
+    pub compression: Option<Compression>,
+
+    /// Compression of the data pages of the named columns, i.e., `"a.b"` for a nested field.
+    /// `None` keeps the column uncompressed.
+    pub column_compression: HashMap<String, Option<Compression>>,
+
+    /// Choose the encoding of each integer column, between plain, bit-packed and delta, from
+    /// the values of the first batch written.
+    ///
+    /// In [WriteMode::Append], the columns keep the encodings of the dataset.
+    pub select_encodings: bool,
+
+    /// Encoding of the named columns, i.e., `"a.b"` for a nested field, over the encodings
+    /// selected by [Self::select_encodings].
+    ///
+    /// Use [Encoding::RLE] for the sorted or low-cardinality columns. The encoding can also be
+    /// set in the Arrow field metadata, by
+    /// [ENCODING_METADATA_KEY](crate::encodings::ENCODING_METADATA_KEY). Only the metadata is
+    /// applied by [Fragment::create](crate::format::Fragment::create), so that the fragments
+    /// can be committed with the same Arrow schema.
+    pub column_encodings: HashMap<String, Encoding>,
+
+    /// Handler to commit the new version. If not set, the default of the object store is used.
+    ///
+    /// S3 has no default, since it can not commit atomically: set
+    /// [UnsafeCommitHandler](crate::io::commit::UnsafeCommitHandler) for a single writer.
+    pub commit_handler: Option<Arc<dyn CommitHandler>>,
+
+    /// Key-value metadata of the new version, i.e., the ID of the writer job.
+    ///
+    /// It is stored in the version auxiliary data, which is only loaded by
+    /// [Dataset::version_aux_metadata](super::Dataset::version_aux_metadata).
+    pub commit_metadata: BTreeMap<String, Vec<u8>>,
 }
 
 impl Default for WriteParams {
@@ -44,7 +123,36 @@ impl Default for WriteParams {
         Self {
             max_rows_per_file: 1024 * 1024, // 1 million
             max_rows_per_group: 1024,
+            max_bytes_per_file: None,
+            max_bytes_per_group: None,
+            max_concurrent_files: 1,
             mode: WriteMode::Create,
+            compression: None,
+            column_compression: HashMap::new(),
+            select_encodings: false,
+            column_encodings: HashMap::new(),
+            commit_handler: None,
+            commit_metadata: BTreeMap::new(),
+        }
+    }
+}
+
+/// Parameters of [Dataset::compact_files](super::Dataset::compact_files).
+#[derive(Debug, Clone, Copy)]
+pub struct CompactionOptions {
+    /// The fragments with fewer rows than this are compacted.
+    /// It is also the max number of rows in each of the new fragments.
+    pub target_rows_per_fragment: usize,
+
+    /// Max number of rows per row group in the new data files.
+    pub max_rows_per_group: usize,
+}
+
+impl Default for CompactionOptions {
+    fn default() -> Self {
+        Self {
+            target_rows_per_fragment: 1024 * 1024, // 1 million
+            max_rows_per_group: 1024,
         }
     }
 }
@@ -0,0 +1,254 @@
+// Copyright 2023 Lance Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Write a new version of a [Dataset] incrementally, one batch at a time.
+
+use std::sync::Arc;
+
+use arrow_array::RecordBatch;
+
+use super::{
+    latest_manifest_path, manifest_path, project_schema_for_append, read_manifest_with_dictionary,
+    rebase_append, write_manifest_file, Dataset, FragmentWriter, WriteMode, WriteParams,
+    MAX_COMMIT_RETRIES,
+};
+use crate::datatypes::Schema;
+use crate::format::Manifest;
+use crate::io::{CommitHandler, ObjectStore};
+use crate::{Error, Result};
+
+/// The schemas of the data being written, set from the first batch.
+struct WriteState {
+    /// Schema of the written batches.
+    batch_schema: Schema,
+
+    /// Schema of the new data files. In [WriteMode::Append], it is the projection of
+    /// the dataset schema over the columns of the batches.
+    write_schema: Schema,
+
+    /// Schema of the new version of the dataset.
+    dataset_schema: Schema,
+
+    fragment_writer: FragmentWriter,
+}
+
+/// Write a new version of a [Dataset], with the batches passed in over time.
+///
+/// The data file is kept open across calls to [DatasetWriter::write], and it is rolled over
+/// by the number of rows and the size set in [WriteParams]. Nothing is visible to the readers
+/// until [DatasetWriter::commit].
+///
+/// ```ignore
+/// let mut writer = DatasetWriter::try_new(uri, None).await?;
+/// writer.write(&batch).await?;
+/// let dataset = writer.commit().await?;
+/// ```
+pub struct DatasetWriter {
+    object_store: Arc<ObjectStore>,
+    params: WriteParams,
+    commit_handler: Arc<dyn CommitHandler>,
+
+    /// The latest version when the writer was created, or `None` in [WriteMode::Create].
+    latest_manifest: Option<Manifest>,
+
+    state: Option<WriteState>,
+}
+
+impl DatasetWriter {
+    /// Create a writer to the dataset at `uri`.
+    ///
+    /// Returns [Error] in [WriteMode::Create] if the dataset already exists.
+    pub async fn try_new(uri: &str, params: Option<WriteParams>) -> Result<Self> {
+        let object_store = Arc::new(ObjectStore::new(uri).await?);
+        let params = params.unwrap_or_default();
+        let commit_handler = params
+            .commit_handler
+            .clone()
+            .unwrap_or_else(|| object_store.commit_handler());
+
+        let latest_manifest_path = latest_manifest_path(&object_store).await?;
+        let latest_manifest = match (&params.mode, latest_manifest_path) {
+            (WriteMode::Create, Some(_)) => {
+                return Err(Error::IO(format!("Dataset already exists: {uri}")));
+            }
+            (WriteMode::Create, None) => None,
+            (_, Some(path)) => Some(read_manifest_with_dictionary(&object_store, &path).await?),
+            (_, None) => return Err(Error::IO(format!("Dataset does not exist: {uri}"))),
+        };
+
+        Ok(Self {
+            object_store,
+            params,
+            commit_handler,
+            latest_manifest,
+            state: None,
+        })
+    }
+
+    /// Write a batch. The rows are written to the data files as the groups fill up.
+    ///
+    /// The schema and the dictionary values are taken from the first batch.
+    pub async fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        if self.state.is_none() {
+            self.state = Some(self.new_state(batch)?);
+        }
+        self.state
+            .as_mut()
+            .unwrap()
+            .fragment_writer
+            .write(batch)
+            .await
+    }
+
+    fn new_state(&self, batch: &RecordBatch) -> Result<WriteState> {
+        let mut batch_schema = Schema::try_from(batch.schema().as_ref())?;
+        batch_schema.set_dictionary(batch)?;
+        batch_schema.set_compression(self.params.compression, &self.params.column_compression)?;
+        if self.params.select_encodings {
+            batch_schema.choose_encodings(batch)?;
+        }
+        batch_schema.set_encodings(&self.params.column_encodings)?;
+
+        let mut write_schema = batch_schema.clone();
+        let mut dataset_schema = batch_schema.clone();
+        // Overwrite continues the fragment IDs from the previous versions, so that the row IDs
+        // in the old indices are never reused.
+        let mut fragment_id = self
+            .latest_manifest
+            .as_ref()
+            .map_or(0, |m| m.next_fragment_id());
+        if matches!(self.params.mode, WriteMode::Append) {
+            if let Some(m) = self.latest_manifest.as_ref() {
+                // The file writer looks up the columns by name, so the batches do not
+                // need to be reordered.
+                write_schema = project_schema_for_append(&m.schema, &batch_schema, "Append")?;
+                dataset_schema = m.schema.clone();
+                fragment_id = m.next_fragment_id();
+            }
+        }
+
+        let fragment_writer =
+            FragmentWriter::new(&self.object_store, &write_schema, fragment_id, &self.params);
+        Ok(WriteState {
+            batch_schema,
+            write_schema,
+            dataset_schema,
+            fragment_writer,
+        })
+    }
+
+    /// Write the buffered rows as a group, even if it is not full.
+    ///
+    /// With [WriteParams::max_concurrent_files] above one, the group is kept in memory until
+    /// every open file has a group to write.
+    pub async fn flush(&mut self) -> Result<()> {
+        match self.state.as_mut() {
+            Some(state) => state.fragment_writer.flush().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Close the data files and commit them as a new version of the dataset.
+    ///
+    /// If another writer has committed the same version, the write is rebased onto it and
+    /// retried. Only appends and overwrites can be rebased.
+    pub async fn commit(self) -> Result<Dataset> {
+        let Some(state) = self.state else {
+            return Err(Error::IO(
+                "Attempt to write empty record batches".to_string(),
+            ));
+        };
+        let params = self.params;
+        let object_store = self.object_store;
+        let latest_manifest = self.latest_manifest;
+
+        let new_fragments = state.fragment_writer.finish().await?;
+        let mut fragments = if matches!(params.mode, WriteMode::Append) {
+            latest_manifest
+                .as_ref()
+                .map_or(vec![], |m| m.fragments.as_ref().clone())
+        } else {
+            // Create or Overwrite create new fragments.
+            vec![]
+        };
+        fragments.extend(new_fragments.iter().cloned());
+
+        let mut manifest = Manifest::new(&state.dataset_schema, Arc::new(fragments));
+        manifest.version = latest_manifest.as_ref().map_or(1, |m| m.version + 1);
+        manifest.max_fragment_id = latest_manifest.and_then(|m| m.max_fragment_id);
+        if matches!(params.mode, WriteMode::Overwrite) {
+            // If overwrite, invalidate index
+            manifest.index_section = None;
+        }
+
+        let mut num_retries = 0;
+        loop {
+            let result = write_manifest_file(
+                &object_store,
+                self.commit_handler.as_ref(),
+                &mut manifest,
+                None,
+                Some(&params.commit_metadata).filter(|m| !m.is_empty()),
+            )
+            .await;
+            let version = match result {
+                Err(Error::CommitConflict { version, .. })
+                    if !matches!(params.mode, WriteMode::Create)
+                        && num_retries < MAX_COMMIT_RETRIES =>
+                {
+                    version
+                }
+                _ => break result?,
+            };
+            num_retries += 1;
+            let base_manifest = read_manifest_with_dictionary(
+                &object_store,
+                &manifest_path(object_store.base_path(), version),
+            )
+            .await?;
+            if matches!(params.mode, WriteMode::Append) {
+                manifest = rebase_append(
+                    &base_manifest,
+                    &state.batch_schema,
+                    &state.write_schema,
+                    &new_fragments,
+                )?;
+            } else {
+                // Overwrite replaces all the fragments, renumber them after the new base.
+                let mut fragment_id = base_manifest.next_fragment_id();
+                let fragments = new_fragments
+                    .iter()
+                    .map(|f| {
+                        let mut fragment = f.clone();
+                        fragment.id = fragment_id;
+                        fragment_id += 1;
+                        fragment
+                    })
+                    .collect::<Vec<_>>();
+                manifest.fragments = Arc::new(fragments);
+                manifest.max_fragment_id = base_manifest.max_fragment_id;
+                manifest.version = base_manifest.version + 1;
+            }
+        }
+
+        let base = object_store.base_path().clone();
+        Ok(Dataset {
+            object_store,
+            base,
+            manifest: Arc::new(manifest),
+            commit_handler: self.commit_handler,
+            deletion_vectors: Default::default(),
+        })
+    }
+}
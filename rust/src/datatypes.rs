@@ -4,16 +4,23 @@ use std::cmp::max;
 use std::collections::HashMap;
 use std::fmt::Formatter;
 use std::fmt::{self};
+use std::str::FromStr;
 
+use arrow_array::cast::{as_dictionary_array, as_struct_array};
 use arrow_array::types::{
     Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
 };
-use arrow_array::{cast::as_dictionary_array, ArrayRef, RecordBatch};
-use arrow_schema::{DataType, Field as ArrowField, Schema as ArrowSchema, TimeUnit};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{
+    DataType, Field as ArrowField, IntervalUnit, Schema as ArrowSchema, TimeUnit, UnionMode,
+};
 use async_recursion::async_recursion;
 
 use crate::arrow::DataTypeExt;
-use crate::encodings::Encoding;
+use crate::encodings::{
+    choose_integer_encoding, compression::Compression, rle::supports_rle, Encoding,
+    ENCODING_METADATA_KEY,
+};
 use crate::format::pb;
 use crate::io::object_reader::{read_binary_array, read_fixed_stride_array, ObjectReader};
 use crate::{Error, Result};
@@ -41,6 +48,37 @@ impl LogicalType {
     fn is_struct(&self) -> bool {
         self.0 == "struct"
     }
+
+    fn is_map(&self) -> bool {
+        self.0 == "map" || self.0 == "map:sorted"
+    }
+
+    fn is_union(&self) -> bool {
+        self.0.starts_with("union:")
+    }
+
+    /// The type IDs and the mode of a union type, i.e., `union:dense:0,1`.
+    fn union_params(&self) -> Result<(Vec<i8>, UnionMode)> {
+        let splits = self.0.split(':').collect::<Vec<_>>();
+        if splits.len() != 3 || splits[0] != "union" {
+            return Err(Error::Schema(format!("Unsupported union type: {}", self)));
+        }
+        let mode = match splits[1] {
+            "sparse" => UnionMode::Sparse,
+            "dense" => UnionMode::Dense,
+            _ => return Err(Error::Schema(format!("Unsupported union type: {}", self))),
+        };
+        let type_ids = if splits[2].is_empty() {
+            vec![]
+        } else {
+            splits[2]
+                .split(',')
+                .map(|id| id.parse::<i8>())
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|e| Error::Schema(e.to_string()))?
+        };
+        Ok((type_ids, mode))
+    }
 }
 
 impl From<&str> for LogicalType {
@@ -58,6 +96,24 @@ fn timeunit_to_str(unit: &TimeUnit) -> &'static str {
     }
 }
 
+fn parse_timeunit(unit: &str) -> Result<TimeUnit> {
+    match unit {
+        "s" => Ok(TimeUnit::Second),
+        "ms" => Ok(TimeUnit::Millisecond),
+        "us" => Ok(TimeUnit::Microsecond),
+        "ns" => Ok(TimeUnit::Nanosecond),
+        _ => Err(Error::Schema(format!("Unsupported time unit: {unit}"))),
+    }
+}
+
+fn interval_unit_to_str(unit: &IntervalUnit) -> &'static str {
+    match unit {
+        IntervalUnit::YearMonth => "year_month",
+        IntervalUnit::DayTime => "day_time",
+        IntervalUnit::MonthDayNano => "month_day_nano",
+    }
+}
+
 impl TryFrom<&DataType> for LogicalType {
     type Error = Error;
 
@@ -86,7 +142,11 @@ impl TryFrom<&DataType> for LogicalType {
             DataType::Date64 => "date64:ms".to_string(),
             DataType::Time32(tu) => format!("time32:{}", timeunit_to_str(tu)),
             DataType::Time64(tu) => format!("time64:{}", timeunit_to_str(tu)),
-            DataType::Timestamp(tu, _) => format!("timestamp:{}", timeunit_to_str(tu)),
+            DataType::Timestamp(tu, None) => format!("timestamp:{}", timeunit_to_str(tu)),
+            DataType::Timestamp(tu, Some(tz)) => {
+                format!("timestamp:{}:{tz}", timeunit_to_str(tu))
+            }
+            DataType::Interval(unit) => format!("interval:{}", interval_unit_to_str(unit)),
             DataType::Duration(tu) => format!("duration:{}", timeunit_to_str(tu)),
             DataType::Struct(_) => "struct".to_string(),
             DataType::Dictionary(key_type, value_type) => {
@@ -112,6 +172,25 @@ impl TryFrom<&DataType> for LogicalType {
                 *len
             ),
             DataType::FixedSizeBinary(len) => format!("fixed_size_binary:{}", *len),
+            DataType::Map(_, keys_sorted) => {
+                if *keys_sorted {
+                    "map:sorted".to_string()
+                } else {
+                    "map".to_string()
+                }
+            }
+            DataType::Union(_, type_ids, mode) => format!(
+                "union:{}:{}",
+                match mode {
+                    UnionMode::Sparse => "sparse",
+                    UnionMode::Dense => "dense",
+                },
+                type_ids
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
             _ => return Err(Error::Schema(format!("Unsupport data type: {:?}", dt))),
         };
 
@@ -156,24 +235,43 @@ impl TryFrom<&LogicalType> for DataType {
             "duration:ms" => Some(Duration(TimeUnit::Millisecond)),
             "duration:us" => Some(Duration(TimeUnit::Microsecond)),
             "duration:ns" => Some(Duration(TimeUnit::Nanosecond)),
+            "interval:year_month" => Some(Interval(IntervalUnit::YearMonth)),
+            "interval:day_time" => Some(Interval(IntervalUnit::DayTime)),
+            "interval:month_day_nano" => Some(Interval(IntervalUnit::MonthDayNano)),
             _ => None,
         } {
             Ok(t)
         } else {
             let splits = lt.0.split(':').collect::<Vec<_>>();
             match splits[0] {
-                "fixed_size_list" => {
+                "timestamp" => {
+                    // The timezone, i.e., "+08:00", can contain ':'.
+                    let splits = lt.0.splitn(3, ':').collect::<Vec<_>>();
                     if splits.len() != 3 {
-                        Err(Error::Schema(format!("Unsupported logical type: {}", lt)))
+                        Err(Error::Schema(format!("Unsupported timestamp type: {}", lt)))
                     } else {
-                        let elem_type = (&LogicalType(splits[1].to_string())).try_into()?;
-                        let size: i32 = splits[2]
+                        Ok(Timestamp(
+                            parse_timeunit(splits[1])?,
+                            Some(splits[2].to_string()),
+                        ))
+                    }
+                }
+                "fixed_size_list" => {
+                    // The item type, i.e., "fixed_size_binary:2", can contain ':'.
+                    let splits =
+                        lt.0.split_once(':')
+                            .and_then(|(_, elem_and_size)| elem_and_size.rsplit_once(':'));
+                    if let Some((elem, size)) = splits {
+                        let elem_type = (&LogicalType(elem.to_string())).try_into()?;
+                        let size: i32 = size
                             .parse::<i32>()
                             .map_err(|e: _| Error::Schema(e.to_string()))?;
                         Ok(FixedSizeList(
                             Box::new(ArrowField::new("item", elem_type, true)),
                             size,
                         ))
+                    } else {
+                        Err(Error::Schema(format!("Unsupported logical type: {}", lt)))
                     }
                 }
                 "fixed_size_binary" => {
@@ -264,6 +362,8 @@ pub struct Field {
     logical_type: LogicalType,
     extension_name: String,
     pub(crate) encoding: Option<Encoding>,
+    /// Compression of the data pages, or `None` if they are not compressed.
+    pub(crate) compression: Option<Compression>,
     pub nullable: bool,
 
     pub children: Vec<Field>,
@@ -283,6 +383,19 @@ impl Field {
             lt if lt.is_struct() => {
                 DataType::Struct(self.children.iter().map(ArrowField::from).collect())
             }
+            lt if lt.is_map() => DataType::Map(
+                Box::new(ArrowField::from(&self.children[0])),
+                lt.0 == "map:sorted",
+            ),
+            lt if lt.is_union() => {
+                // Validated when the field is created.
+                let (type_ids, mode) = lt.union_params().unwrap();
+                DataType::Union(
+                    self.children.iter().map(ArrowField::from).collect(),
+                    type_ids,
+                    mode,
+                )
+            }
             lt => DataType::try_from(lt).unwrap(),
         }
     }
@@ -344,6 +457,60 @@ impl Field {
         }
     }
 
+    /// Choose the encoding of a plain-encoded integer field, and of the integer fields of a
+    /// struct, from the values in `arr`. The other fields keep their encoding.
+    fn choose_encoding(&mut self, arr: &ArrayRef) {
+        let data_type = self.data_type();
+        if data_type.is_integer_like() && self.encoding == Some(Encoding::Plain) {
+            self.encoding = Some(choose_integer_encoding(arr.as_ref()));
+        } else if let DataType::Struct(_) = data_type {
+            let struct_array = as_struct_array(arr);
+            for child in self.children.iter_mut() {
+                if let Some(column) = struct_array.column_by_name(&child.name) {
+                    child.choose_encoding(column);
+                }
+            }
+        }
+    }
+
+    /// Set the encoding of this field, if it can store the values of the field.
+    fn set_encoding(&mut self, encoding: Encoding) -> Result<()> {
+        let data_type = self.data_type();
+        let supported = match encoding {
+            Encoding::RLE => supports_rle(&data_type),
+            Encoding::BitPacked | Encoding::Delta => data_type.is_integer_like(),
+            _ => default_encoding(&data_type) == Some(encoding.clone()),
+        };
+        if !supported {
+            return Err(Error::Schema(format!(
+                "Field '{}' of type {} does not support {:?} encoding",
+                self.name, data_type, encoding
+            )));
+        }
+        self.encoding = Some(encoding);
+        Ok(())
+    }
+
+    /// Set the compression of this field and all its children.
+    fn set_compression(&mut self, compression: Option<Compression>) {
+        self.compression = compression;
+        self.children
+            .iter_mut()
+            .for_each(|c| c.set_compression(compression));
+    }
+
+    fn sub_field_mut(&mut self, path_components: &[&str]) -> Option<&mut Self> {
+        if path_components.is_empty() {
+            Some(self)
+        } else {
+            let first = path_components[0];
+            self.children
+                .iter_mut()
+                .find(|c| c.name == first)
+                .and_then(|c| c.sub_field_mut(&path_components[1..]))
+        }
+    }
+
     fn sub_field(&self, path_components: &[&str]) -> Option<&Self> {
         if path_components.is_empty() {
             Some(self)
@@ -365,6 +532,7 @@ impl Field {
             logical_type: self.logical_type.clone(),
             extension_name: self.extension_name.clone(),
             encoding: self.encoding.clone(),
+            compression: self.compression,
             nullable: self.nullable,
             children: vec![],
             dictionary: self.dictionary.clone(),
@@ -412,6 +580,7 @@ impl Field {
                 logical_type: self.logical_type.clone(),
                 extension_name: self.extension_name.clone(),
                 encoding: self.encoding.clone(),
+                compression: self.compression,
                 nullable: self.nullable,
                 children,
                 dictionary: self.dictionary.clone(),
@@ -431,6 +600,22 @@ impl Field {
         Ok(())
     }
 
+    /// Keep the field and its children whose IDs are in `ids`.
+    ///
+    /// Returns `None` if the field itself is not in `ids`.
+    fn project_by_ids(&self, ids: &[i32]) -> Option<Self> {
+        if !ids.contains(&self.id) {
+            return None;
+        }
+        let mut field = self.clone();
+        field.children = self
+            .children
+            .iter()
+            .filter_map(|c| c.project_by_ids(ids))
+            .collect();
+        Some(field)
+    }
+
     // Get the max field id of itself and all children.
     fn max_id(&self) -> i32 {
         max(
@@ -536,26 +721,42 @@ impl TryFrom<&ArrowField> for Field {
             }
             DataType::List(item) => vec![Self::try_from(item.as_ref())?],
             DataType::LargeList(item) => vec![Self::try_from(item.as_ref())?],
+            DataType::Map(entries, _) => vec![Self::try_from(entries.as_ref())?],
+            DataType::Union(fields, _, _) => {
+                fields.iter().map(Self::try_from).collect::<Result<_>>()?
+            }
             _ => vec![],
         };
-        Ok(Self {
+        let mut lance_field = Self {
             id: -1,
             parent_id: -1,
             name: field.name().clone(),
             logical_type: LogicalType::try_from(field.data_type())?,
-            encoding: match field.data_type() {
-                dt if dt.is_fixed_stride() => Some(Encoding::Plain),
-                dt if dt.is_binary_like() => Some(Encoding::VarBinary),
-                DataType::Dictionary(_, _) => Some(Encoding::Dictionary),
-                // Use plain encoder to store the offsets of list.
-                DataType::List(_) | DataType::LargeList(_) => Some(Encoding::Plain),
-                _ => None,
-            },
+            encoding: default_encoding(field.data_type()),
+            compression: None,
             extension_name: "".to_string(),
             nullable: field.is_nullable(),
             children,
             dictionary: None,
-        })
+        };
+        if let Some(encoding) = field.metadata().get(ENCODING_METADATA_KEY) {
+            lance_field.set_encoding(Encoding::from_str(encoding)?)?;
+        }
+        Ok(lance_field)
+    }
+}
+
+/// The encoding of a field of `data_type`, unless another one is selected.
+fn default_encoding(data_type: &DataType) -> Option<Encoding> {
+    match data_type {
+        dt if dt.is_fixed_stride() => Some(Encoding::Plain),
+        dt if dt.is_binary_like() => Some(Encoding::VarBinary),
+        DataType::Dictionary(_, _) => Some(Encoding::Dictionary),
+        // Use plain encoder to store the offsets of list and map.
+        DataType::List(_) | DataType::LargeList(_) | DataType::Map(_, _) => Some(Encoding::Plain),
+        // Use plain encoder to store the type IDs of union.
+        DataType::Union(_, _, _) => Some(Encoding::Plain),
+        _ => None,
     }
 }
 
@@ -565,25 +766,34 @@ impl From<&Field> for ArrowField {
     }
 }
 
-impl From<&pb::Field> for Field {
-    fn from(field: &pb::Field) -> Self {
-        Self {
+impl TryFrom<&pb::Field> for Field {
+    type Error = Error;
+
+    fn try_from(field: &pb::Field) -> Result<Self> {
+        let logical_type = LogicalType(field.logical_type.clone());
+        if logical_type.is_union() {
+            logical_type.union_params()?;
+        }
+        Ok(Self {
             name: field.name.clone(),
             id: field.id,
             parent_id: field.parent_id,
-            logical_type: LogicalType(field.logical_type.clone()),
+            logical_type,
             extension_name: field.extension_name.clone(),
             encoding: match field.encoding {
                 1 => Some(Encoding::Plain),
                 2 => Some(Encoding::VarBinary),
                 3 => Some(Encoding::Dictionary),
                 4 => Some(Encoding::RLE),
+                5 => Some(Encoding::BitPacked),
+                6 => Some(Encoding::Delta),
                 _ => None,
             },
+            compression: Compression::from_proto(field.compression),
             nullable: field.nullable,
             children: vec![],
             dictionary: field.dictionary.as_ref().map(Dictionary::from),
-        }
+        })
     }
 }
 
@@ -599,8 +809,14 @@ impl From<&Field> for pb::Field {
                 Some(Encoding::VarBinary) => 2,
                 Some(Encoding::Dictionary) => 3,
                 Some(Encoding::RLE) => 4,
+                Some(Encoding::BitPacked) => 5,
+                Some(Encoding::Delta) => 6,
                 _ => 0,
             },
+            compression: field
+                .compression
+                .map_or(pb::Compression::Uncompressed, pb::Compression::from)
+                as i32,
             nullable: field.nullable,
             dictionary: field.dictionary.as_ref().map(pb::Dictionary::from),
             extension_name: field.extension_name.clone(),
@@ -657,14 +873,14 @@ impl Schema {
     }
 
     pub fn project_by_ids(&self, column_ids: &[i32]) -> Result<Self> {
-        let protos: Vec<pb::Field> = self.into();
-
-        let filtered_protos: Vec<pb::Field> = protos
-            .iter()
-            .filter(|p| column_ids.contains(&p.id))
-            .cloned()
-            .collect();
-        Ok(Self::from(&filtered_protos))
+        Ok(Self {
+            fields: self
+                .fields
+                .iter()
+                .filter_map(|f| f.project_by_ids(column_ids))
+                .collect(),
+            metadata: self.metadata.clone(),
+        })
     }
 
     /// Exclude the fields from `other` Schema, and returns a new Schema.
@@ -697,6 +913,42 @@ impl Schema {
             .flatten()
     }
 
+    /// Get a mutable field by its full name, i.e., `"a.b"`.
+    fn field_mut(&mut self, name: &str) -> Result<&mut Field> {
+        let split = name.split('.').collect::<Vec<_>>();
+        self.fields
+            .iter_mut()
+            .find(|f| f.name == split[0])
+            .and_then(|f| f.sub_field_mut(&split[1..]))
+            .ok_or_else(|| Error::Schema(format!("column '{name}' does not exist in the schema")))
+    }
+
+    /// Set the compression of the data pages of all the fields to `default`, except for the
+    /// columns in `columns`, by their full names, i.e., `"a.b"`.
+    pub(crate) fn set_compression(
+        &mut self,
+        default: Option<Compression>,
+        columns: &HashMap<String, Option<Compression>>,
+    ) -> Result<()> {
+        self.fields
+            .iter_mut()
+            .for_each(|f| f.set_compression(default));
+        for (name, compression) in columns {
+            self.field_mut(name)?.set_compression(*compression);
+        }
+        Ok(())
+    }
+
+    /// Set the encodings of the columns, by their full names, i.e., `"a.b"`.
+    ///
+    /// Returns [Error::Schema] if a column can not be stored with its encoding.
+    pub(crate) fn set_encodings(&mut self, columns: &HashMap<String, Encoding>) -> Result<()> {
+        for (name, encoding) in columns {
+            self.field_mut(name)?.set_encoding(encoding.clone())?;
+        }
+        Ok(())
+    }
+
     pub(crate) fn field_id(&self, column: &str) -> Result<i32> {
         self.field(column)
             .map(|f| f.id)
@@ -748,6 +1000,40 @@ impl Schema {
         Ok(())
     }
 
+    /// Choose the encodings of the integer columns that store the batch in the fewest bytes.
+    pub(crate) fn choose_encodings(&mut self, batch: &RecordBatch) -> Result<()> {
+        for field in self.fields.as_mut_slice() {
+            let column = batch.column_by_name(&field.name).ok_or_else(|| {
+                Error::Schema(format!(
+                    "column '{}' does not exist in the record batch",
+                    field.name
+                ))
+            })?;
+            field.choose_encoding(column);
+        }
+        Ok(())
+    }
+
+    /// Append new top-level fields to the schema.
+    ///
+    /// The IDs of the new fields start from `next_field_id`, or after the existing fields,
+    /// whichever is larger.
+    pub(crate) fn extend(&mut self, fields: &[ArrowField], next_field_id: i32) -> Result<()> {
+        let mut current_id = max(self.max_field_id().unwrap_or(-1) + 1, next_field_id);
+        for arrow_field in fields {
+            if self.field(arrow_field.name()).is_some() {
+                return Err(Error::Schema(format!(
+                    "Field '{}' already exists in the schema",
+                    arrow_field.name()
+                )));
+            }
+            let mut field = Field::try_from(arrow_field)?;
+            field.set_id(-1, &mut current_id);
+            self.fields.push(field);
+        }
+        Ok(())
+    }
+
     fn set_field_id(&mut self) {
         let mut current_id = self.max_field_id().unwrap_or(-1) + 1;
         self.fields
@@ -809,23 +1095,30 @@ impl From<&Schema> for ArrowSchema {
 }
 
 /// Convert list of protobuf `Field` to a Schema.
-impl From<&Vec<pb::Field>> for Schema {
-    fn from(fields: &Vec<pb::Field>) -> Self {
+impl TryFrom<&Vec<pb::Field>> for Schema {
+    type Error = Error;
+
+    fn try_from(fields: &Vec<pb::Field>) -> Result<Self> {
         let mut schema = Self {
             fields: vec![],
             metadata: HashMap::default(),
         };
 
-        fields.iter().for_each(|f| {
+        for f in fields.iter() {
             if f.parent_id == -1 {
-                schema.fields.push(Field::from(f));
+                schema.fields.push(Field::try_from(f)?);
             } else {
-                let parent = schema.mut_field_by_id(f.parent_id).unwrap();
-                parent.children.push(Field::from(f));
+                let Some(parent) = schema.mut_field_by_id(f.parent_id) else {
+                    return Err(Error::Schema(format!(
+                        "The parent {} of field {} does not exist",
+                        f.parent_id, f.name
+                    )));
+                };
+                parent.children.push(Field::try_from(f)?);
             }
-        });
+        }
 
-        schema
+        Ok(schema)
     }
 }
 
@@ -844,7 +1137,7 @@ impl From<&Schema> for Vec<pb::Field> {
 mod tests {
     use super::*;
 
-    use arrow_schema::{Field as ArrowField, TimeUnit};
+    use arrow_schema::{Field as ArrowField, IntervalUnit, TimeUnit, UnionMode};
 
     #[test]
     fn arrow_field_to_field() {
@@ -876,6 +1169,26 @@ mod tests {
                 "timestamp:ns",
                 DataType::Timestamp(TimeUnit::Nanosecond, None),
             ),
+            (
+                "timestamp:us:America/New_York",
+                DataType::Timestamp(TimeUnit::Microsecond, Some("America/New_York".to_string())),
+            ),
+            (
+                "timestamp:ns:+08:00",
+                DataType::Timestamp(TimeUnit::Nanosecond, Some("+08:00".to_string())),
+            ),
+            (
+                "interval:year_month",
+                DataType::Interval(IntervalUnit::YearMonth),
+            ),
+            (
+                "interval:day_time",
+                DataType::Interval(IntervalUnit::DayTime),
+            ),
+            (
+                "interval:month_day_nano",
+                DataType::Interval(IntervalUnit::MonthDayNano),
+            ),
             ("time32:s", DataType::Time32(TimeUnit::Second)),
             ("time32:ms", DataType::Time32(TimeUnit::Millisecond)),
             ("time64:us", DataType::Time64(TimeUnit::Microsecond)),
@@ -892,6 +1205,13 @@ mod tests {
                     10,
                 ),
             ),
+            (
+                "fixed_size_list:fixed_size_binary:2:10",
+                DataType::FixedSizeList(
+                    Box::new(ArrowField::new("item", DataType::FixedSizeBinary(2), true)),
+                    10,
+                ),
+            ),
         ] {
             let arrow_field = ArrowField::new(name, data_type.clone(), true);
             let field = Field::try_from(&arrow_field).unwrap();
@@ -935,6 +1255,62 @@ mod tests {
         );
     }
 
+    #[test]
+    fn map_and_union_fields() {
+        let entries = ArrowField::new(
+            "entries",
+            DataType::Struct(vec![
+                ArrowField::new("keys", DataType::Utf8, false),
+                ArrowField::new("values", DataType::Utf8, true),
+            ]),
+            false,
+        );
+        let arrow_schema = ArrowSchema::new(vec![
+            ArrowField::new("map", DataType::Map(Box::new(entries.clone()), false), true),
+            ArrowField::new("sorted_map", DataType::Map(Box::new(entries), true), true),
+            ArrowField::new(
+                "sparse",
+                DataType::Union(
+                    vec![
+                        ArrowField::new("i", DataType::Int32, true),
+                        ArrowField::new("s", DataType::Utf8, true),
+                    ],
+                    vec![2, 5],
+                    UnionMode::Sparse,
+                ),
+                false,
+            ),
+            ArrowField::new(
+                "dense",
+                DataType::Union(
+                    vec![ArrowField::new("f", DataType::Float64, true)],
+                    vec![0],
+                    UnionMode::Dense,
+                ),
+                false,
+            ),
+        ]);
+        let schema = Schema::try_from(&arrow_schema).unwrap();
+        assert_eq!(schema.fields[0].logical_type.0, "map");
+        assert_eq!(schema.fields[1].logical_type.0, "map:sorted");
+        assert_eq!(schema.fields[2].logical_type.0, "union:sparse:2,5");
+        assert_eq!(schema.fields[3].logical_type.0, "union:dense:0");
+        assert_eq!(ArrowSchema::from(&schema), arrow_schema);
+
+        // Round trip through the protobuf fields of the manifest.
+        let protos: Vec<pb::Field> = (&schema).into();
+        let schema = Schema::try_from(&protos).unwrap();
+        assert_eq!(ArrowSchema::from(&schema), arrow_schema);
+
+        // The union parameters are validated when the schema is loaded.
+        let mut protos = protos;
+        let sparse = protos.iter().position(|f| f.name == "sparse").unwrap();
+        protos[sparse].logical_type = "union:unknown:2,5".to_string();
+        assert!(matches!(Schema::try_from(&protos), Err(Error::Schema(_))));
+        protos[sparse].logical_type = "union:sparse:a".to_string();
+        assert!(matches!(Schema::try_from(&protos), Err(Error::Schema(_))));
+    }
+
     #[test]
     fn struct_field() {
         let arrow_field = ArrowField::new(
@@ -1036,6 +1412,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_schema_extend() {
+        let arrow_schema = ArrowSchema::new(vec![
+            ArrowField::new("a", DataType::Int32, false),
+            ArrowField::new(
+                "b",
+                DataType::Struct(vec![ArrowField::new("f1", DataType::Utf8, true)]),
+                true,
+            ),
+        ]);
+        let mut schema = Schema::try_from(&arrow_schema).unwrap();
+        schema
+            .extend(
+                &[
+                    ArrowField::new(
+                        "c",
+                        DataType::Struct(vec![ArrowField::new("f2", DataType::Boolean, true)]),
+                        true,
+                    ),
+                    ArrowField::new("d", DataType::Float64, true),
+                ],
+                0,
+            )
+            .unwrap();
+
+        let protos: Vec<pb::Field> = (&schema).into();
+        assert_eq!(
+            protos.iter().map(|p| p.id).collect::<Vec<_>>(),
+            (0..6).collect::<Vec<_>>()
+        );
+        assert_eq!(schema.field("c.f2").unwrap().parent_id, 3);
+
+        assert!(schema
+            .extend(&[ArrowField::new("a", DataType::Int32, true)], 0)
+            .is_err());
+
+        // Do not reuse the IDs of the dropped fields.
+        schema
+            .extend(&[ArrowField::new("e", DataType::Int32, true)], 10)
+            .unwrap();
+        assert_eq!(schema.field("e").unwrap().id, 10);
+    }
+
     #[test]
     fn test_get_nested_field() {
         let arrow_schema = ArrowSchema::new(vec![ArrowField::new(
@@ -1083,4 +1502,67 @@ mod tests {
         ]);
         assert_eq!(ArrowSchema::from(&excluded), expected_arrow_schema);
     }
+
+    #[test]
+    fn test_select_encodings() {
+        let arrow_schema = ArrowSchema::new(vec![
+            ArrowField::new("a", DataType::Utf8, false).with_metadata(HashMap::from([(
+                ENCODING_METADATA_KEY.to_string(),
+                "rle".to_string(),
+            )])),
+            ArrowField::new(
+                "b",
+                DataType::Struct(vec![
+                    ArrowField::new("f1", DataType::Boolean, true),
+                    ArrowField::new("f2", DataType::Float32, false),
+                ]),
+                true,
+            ),
+            ArrowField::new("c", DataType::Int64, false),
+        ]);
+        let mut schema = Schema::try_from(&arrow_schema).unwrap();
+        assert_eq!(schema.field("a").unwrap().encoding, Some(Encoding::RLE));
+        assert_eq!(
+            schema.field("b.f1").unwrap().encoding,
+            Some(Encoding::Plain)
+        );
+
+        schema
+            .set_encodings(&HashMap::from([
+                ("b.f1".to_string(), Encoding::RLE),
+                ("c".to_string(), Encoding::Delta),
+            ]))
+            .unwrap();
+        assert_eq!(schema.field("b.f1").unwrap().encoding, Some(Encoding::RLE));
+        assert_eq!(schema.field("c").unwrap().encoding, Some(Encoding::Delta));
+
+        // The encodings are kept in the manifest.
+        let pb_schema: Vec<pb::Field> = (&schema).into();
+        let loaded = Schema::try_from(&pb_schema).unwrap();
+        for name in ["a", "b.f1", "c"] {
+            assert_eq!(
+                loaded.field(name).unwrap().encoding,
+                schema.field(name).unwrap().encoding
+            );
+        }
+
+        for (name, encoding) in [
+            ("b.f2", Encoding::Delta),
+            ("b", Encoding::RLE),
+            ("d", Encoding::RLE),
+        ] {
+            assert!(matches!(
+                schema.set_encodings(&HashMap::from([(name.to_string(), encoding)])),
+                Err(Error::Schema(_))
+            ));
+        }
+
+        let arrow_field = ArrowField::new("f", DataType::Float64, true).with_metadata(
+            HashMap::from([(ENCODING_METADATA_KEY.to_string(), "bit_packed".to_string())]),
+        );
+        assert!(matches!(
+            Field::try_from(&arrow_field),
+            Err(Error::Schema(_))
+        ));
+    }
 }
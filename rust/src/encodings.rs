@@ -1,18 +1,27 @@
 //! Data encodings
 //!
 
+use std::str::FromStr;
+
 use arrow_array::{Array, ArrayRef, UInt32Array};
 use async_trait::async_trait;
 
 pub mod binary;
+pub mod bitpacked;
+pub mod compression;
+pub mod delta;
 pub mod dictionary;
 pub mod plain;
 pub mod rle;
 
-use crate::error::Result;
+use crate::arrow::*;
+use crate::error::{Error, Result};
 use crate::format::pb;
 use crate::io::ReadBatchParams;
 
+/// Key of the Arrow field metadata to select the encoding of a column, i.e., `"rle"`.
+pub const ENCODING_METADATA_KEY: &str = "lance:encoding";
+
 /// Encoding enum.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Encoding {
@@ -24,6 +33,10 @@ pub enum Encoding {
     Dictionary,
     /// RLE encoding.
     RLE,
+    /// Bit-packed encoding of integers.
+    BitPacked,
+    /// Delta encoding of integers.
+    Delta,
 }
 
 impl From<Encoding> for pb::Encoding {
@@ -33,10 +46,49 @@ impl From<Encoding> for pb::Encoding {
             Encoding::VarBinary => Self::VarBinary,
             Encoding::Dictionary => Self::Dictionary,
             Encoding::RLE => Self::Rle,
+            Encoding::BitPacked => Self::BitPacked,
+            Encoding::Delta => Self::Delta,
         }
     }
 }
 
+impl FromStr for Encoding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "plain" => Ok(Self::Plain),
+            "var_binary" => Ok(Self::VarBinary),
+            "dictionary" => Ok(Self::Dictionary),
+            "rle" => Ok(Self::RLE),
+            "bit_packed" => Ok(Self::BitPacked),
+            "delta" => Ok(Self::Delta),
+            _ => Err(Error::Schema(format!("Unsupported encoding: {s}"))),
+        }
+    }
+}
+
+/// Choose the encoding that stores the values of an integer array in the fewest bytes.
+///
+/// The nulls are stored the same way in all the encodings, so they are not counted.
+/// [Encoding::Plain] is chosen if no other encoding is smaller.
+pub fn choose_integer_encoding(array: &dyn Array) -> Encoding {
+    if !array.data_type().is_integer_like() {
+        return Encoding::Plain;
+    }
+    let values = bitpacked::integer_values(array);
+    let plain_size = array.len() * array.data_type().byte_width();
+    let bitpacked_size = bitpacked::encoded_size(&values, array.data_type());
+    let delta_size = delta::encoded_size(&values);
+    if delta_size < bitpacked_size && delta_size < plain_size {
+        Encoding::Delta
+    } else if bitpacked_size < plain_size {
+        Encoding::BitPacked
+    } else {
+        Encoding::Plain
+    }
+}
+
 /// Encoder - Write an arrow array to the file.
 #[async_trait]
 pub trait Encoder {
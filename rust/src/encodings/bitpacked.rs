@@ -0,0 +1,497 @@
+// Copyright 2023 Lance Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bit-packed encoding of integers.
+//!
+//! A page stores its minimal value as the reference, followed by the difference of each value
+//! from the reference, packed in as many bits as the largest difference needs.
+//!
+//! ```text
+//! | reference: u64 | bit width: u8 | padding: [u8; 7] | packed values |
+//! ```
+//!
+//! The values are packed from the lowest bit of the first byte, so a range of rows is read
+//! from the bytes it is packed in, without reading the whole page. The nulls are stored in
+//! validity bitmaps after the values, as in the plain encoding.
+
+use std::ops::Range;
+
+use arrow_arith::arithmetic::subtract_scalar;
+use arrow_array::cast::{as_boolean_array, as_primitive_array};
+use arrow_array::{make_array, new_empty_array, Array, ArrayRef, UInt32Array};
+use arrow_buffer::{bit_util, MutableBuffer};
+use arrow_data::ArrayDataBuilder;
+use arrow_schema::DataType;
+use arrow_select::{concat::concat, take::take};
+use async_trait::async_trait;
+use byteorder::{ByteOrder, LittleEndian};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use tokio::io::AsyncWriteExt;
+
+use super::plain::{apply_validity, PlainDecoder};
+use super::{AsyncIndex, Decoder, Encoder};
+use crate::arrow::*;
+use crate::io::object_reader::ObjectReader;
+use crate::io::object_writer::ObjectWriter;
+use crate::io::ReadBatchParams;
+use crate::{Error, Result};
+
+/// Size of the page header in bytes.
+const HEADER_SIZE: usize = 16;
+
+/// Returns [Error::Schema] if the data type is not stored as integers.
+pub(crate) fn check_integer_type(data_type: &DataType) -> Result<()> {
+    if data_type.is_integer_like() {
+        Ok(())
+    } else {
+        Err(Error::Schema(format!(
+            "Only integer types can be bit-packed or delta encoded, got {data_type}"
+        )))
+    }
+}
+
+fn is_signed(data_type: &DataType) -> bool {
+    !matches!(
+        data_type,
+        DataType::UInt8 | DataType::UInt16 | DataType::UInt32 | DataType::UInt64
+    )
+}
+
+/// The values of an integer array, sign-extended to 64 bits for the signed types.
+///
+/// The arithmetic on the values wraps around, so it is the same for signed and unsigned
+/// values once they are truncated back to the byte width of the type.
+pub(crate) fn integer_values(array: &dyn Array) -> Vec<u64> {
+    let data_type = array.data_type();
+    let byte_width = data_type.byte_width();
+    let shift = 64 - 8 * byte_width as u32;
+    let signed = is_signed(data_type);
+    let data = array.data();
+    let bytes = &data.buffers()[0].as_slice()
+        [array.offset() * byte_width..(array.offset() + array.len()) * byte_width];
+    bytes
+        .chunks_exact(byte_width)
+        .map(|chunk| {
+            let mut buf = [0_u8; 8];
+            buf[..byte_width].copy_from_slice(chunk);
+            let value = u64::from_le_bytes(buf);
+            if signed {
+                ((value << shift) as i64 >> shift) as u64
+            } else {
+                value
+            }
+        })
+        .collect()
+}
+
+/// Make an array of `data_type` from the values, truncated to the byte width of the type.
+pub(crate) fn integer_array(data_type: &DataType, values: &[u64]) -> Result<ArrayRef> {
+    let byte_width = data_type.byte_width();
+    let mut buffer = MutableBuffer::new(values.len() * byte_width);
+    for value in values {
+        buffer.extend_from_slice(&value.to_le_bytes()[..byte_width]);
+    }
+    let array_data = ArrayDataBuilder::new(data_type.clone())
+        .len(values.len())
+        .null_count(0)
+        .add_buffer(buffer.into())
+        .build()?;
+    Ok(make_array(array_data))
+}
+
+/// Number of bits to store the values up to `max`.
+pub(crate) fn bit_width(max: u64) -> u8 {
+    (64 - max.leading_zeros()) as u8
+}
+
+/// Number of bytes of `num_values` values packed in `width` bits.
+pub(crate) fn packed_len(num_values: usize, width: u8) -> usize {
+    bit_util::ceil(num_values * width as usize, 8)
+}
+
+fn mask(width: u8) -> u64 {
+    if width >= 64 {
+        u64::MAX
+    } else {
+        (1 << width) - 1
+    }
+}
+
+/// Append the lowest `width` bits of each value to `out`, from the lowest bit of a byte.
+pub(crate) fn pack(values: impl IntoIterator<Item = u64>, width: u8, out: &mut Vec<u8>) {
+    if width == 0 {
+        return;
+    }
+    let mut acc: u128 = 0;
+    let mut bits = 0;
+    for value in values {
+        acc |= ((value & mask(width)) as u128) << bits;
+        bits += width as u32;
+        while bits >= 8 {
+            out.push(acc as u8);
+            acc >>= 8;
+            bits -= 8;
+        }
+    }
+    if bits > 0 {
+        out.push(acc as u8);
+    }
+}
+
+/// The value of `width` bits packed in `bytes`, from the bit at `bit_offset`.
+pub(crate) fn unpack(bytes: &[u8], bit_offset: usize, width: u8) -> u64 {
+    if width == 0 {
+        return 0;
+    }
+    let start = bit_offset / 8;
+    let end = bit_util::ceil(bit_offset + width as usize, 8);
+    let mut acc: u128 = 0;
+    for (i, byte) in bytes[start..end].iter().enumerate() {
+        acc |= (*byte as u128) << (8 * i);
+    }
+    ((acc >> (bit_offset % 8)) as u64) & mask(width)
+}
+
+/// The reference and the bit width to pack the values, which are compared as `i64` if
+/// `signed` is true.
+pub(crate) fn reference_and_width(values: &[u64], signed: bool) -> (u64, u8) {
+    let order = |v: &u64| {
+        if signed {
+            *v as i64 as i128
+        } else {
+            *v as i128
+        }
+    };
+    let Some(reference) = values.iter().copied().min_by_key(order) else {
+        return (0, 0);
+    };
+    let max = values.iter().copied().max_by_key(order).unwrap();
+    (reference, bit_width(max.wrapping_sub(reference)))
+}
+
+/// The size of the values encoded in a page, in bytes.
+pub(crate) fn encoded_size(values: &[u64], data_type: &DataType) -> usize {
+    let (_, width) = reference_and_width(values, is_signed(data_type));
+    HEADER_SIZE + packed_len(values.len(), width)
+}
+
+/// Encoder for bit-packed encoding.
+pub struct BitPackedEncoder<'a> {
+    writer: &'a mut ObjectWriter,
+    data_type: &'a DataType,
+}
+
+impl<'a> BitPackedEncoder<'a> {
+    pub fn new(writer: &'a mut ObjectWriter, data_type: &'a DataType) -> Self {
+        Self { writer, data_type }
+    }
+}
+
+#[async_trait]
+impl<'a> Encoder for BitPackedEncoder<'a> {
+    /// Encode the values of an array, and returns the position of the page.
+    ///
+    /// The nulls are not written, see [PlainEncoder::encode_validity](super::plain::PlainEncoder::encode_validity).
+    async fn encode(&mut self, array: &dyn Array) -> Result<usize> {
+        check_integer_type(self.data_type)?;
+        let values = integer_values(array);
+        let (reference, width) = reference_and_width(&values, is_signed(self.data_type));
+
+        let mut buf = Vec::with_capacity(HEADER_SIZE + packed_len(values.len(), width));
+        buf.extend_from_slice(&reference.to_le_bytes());
+        buf.push(width);
+        buf.resize(HEADER_SIZE, 0);
+        pack(
+            values.iter().map(|v| v.wrapping_sub(reference)),
+            width,
+            &mut buf,
+        );
+
+        let pos = self.writer.tell();
+        self.writer.write_all(&buf).await?;
+        Ok(pos)
+    }
+}
+
+/// Decoder for bit-packed encoding.
+pub struct BitPackedDecoder<'a> {
+    reader: &'a dyn ObjectReader,
+    data_type: &'a DataType,
+    /// The start position of the page in the file.
+    position: usize,
+    /// Number of the rows in the page.
+    length: usize,
+    /// The position of the validity bitmaps, if the page has nulls.
+    validity: Option<usize>,
+}
+
+impl<'a> BitPackedDecoder<'a> {
+    pub fn new(
+        reader: &'a dyn ObjectReader,
+        data_type: &'a DataType,
+        position: usize,
+        length: usize,
+    ) -> Self {
+        Self {
+            reader,
+            data_type,
+            position,
+            length,
+            validity: None,
+        }
+    }
+
+    /// Decode the nulls from the validity bitmaps at `position`.
+    pub fn with_validity(mut self, position: usize) -> Self {
+        self.validity = Some(position);
+        self
+    }
+
+    /// Read the reference and the bit width from the page header.
+    async fn read_header(&self) -> Result<(u64, u8)> {
+        let header = self
+            .reader
+            .get_range(self.position..self.position + HEADER_SIZE)
+            .await?;
+        Ok((LittleEndian::read_u64(&header[..8]), header[8]))
+    }
+
+    /// Decode the rows in `range`, with the header read by [Self::read_header].
+    async fn decode_range(&self, header: (u64, u8), range: Range<usize>) -> Result<ArrayRef> {
+        if range.end > self.length {
+            return Err(Error::IO(format!(
+                "BitPackedDecoder: request([{}..{}]) out of range: [0..{}]",
+                range.start, range.end, self.length
+            )));
+        }
+        if range.is_empty() {
+            return Ok(new_empty_array(self.data_type));
+        }
+
+        let (reference, width) = header;
+        let values = if width == 0 {
+            vec![reference; range.len()]
+        } else {
+            let data_start = self.position + HEADER_SIZE;
+            let first_byte = range.start * width as usize / 8;
+            let bytes = self
+                .reader
+                .get_range(data_start + first_byte..data_start + packed_len(range.end, width))
+                .await?;
+            range
+                .clone()
+                .map(|i| {
+                    let bit_offset = i * width as usize - first_byte * 8;
+                    reference.wrapping_add(unpack(&bytes, bit_offset, width))
+                })
+                .collect()
+        };
+        let array = integer_array(self.data_type, &values)?;
+
+        if let Some(position) = self.validity {
+            let validity_decoder =
+                PlainDecoder::new(self.reader, &DataType::Boolean, position, self.length)?;
+            let validity = validity_decoder.get(range).await?;
+            apply_validity(array, as_boolean_array(validity.as_ref()))
+        } else {
+            Ok(array)
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> Decoder for BitPackedDecoder<'a> {
+    async fn decode(&self) -> Result<ArrayRef> {
+        self.get(0..self.length).await
+    }
+
+    /// Take the rows at the sorted `indices`, only reading the bytes they are packed in.
+    async fn take(&self, indices: &UInt32Array) -> Result<ArrayRef> {
+        if indices.is_empty() {
+            return Ok(new_empty_array(self.data_type));
+        }
+        let header = self.read_header().await?;
+        let width = header.1 as usize;
+        let block_size = self.reader.prefetch_size() * 8;
+
+        let mut chunk_ranges = vec![];
+        let mut start = 0;
+        for j in 0..indices.len() - 1 {
+            if indices.value(j + 1) as usize * width
+                > indices.value(start) as usize * width + block_size
+            {
+                chunk_ranges.push(start..j + 1);
+                start = j + 1;
+            }
+        }
+        chunk_ranges.push(start..indices.len());
+
+        let arrays = stream::iter(chunk_ranges)
+            .map(|cr| async move {
+                let index_chunk = indices.slice(cr.start, cr.len());
+                let request: &UInt32Array = as_primitive_array(&index_chunk);
+
+                let start = request.value(0);
+                let end = request.value(request.len() - 1);
+                let array = self
+                    .decode_range(header, start as usize..end as usize + 1)
+                    .await?;
+                let adjusted_offsets = subtract_scalar(request, start)?;
+                Ok::<ArrayRef, Error>(take(&array, &adjusted_offsets, None)?)
+            })
+            .buffered(8)
+            .try_collect::<Vec<_>>()
+            .await?;
+        let references = arrays.iter().map(|a| a.as_ref()).collect::<Vec<_>>();
+        Ok(concat(&references)?)
+    }
+}
+
+#[async_trait]
+impl<'a> AsyncIndex<Range<usize>> for BitPackedDecoder<'a> {
+    type Output = Result<ArrayRef>;
+
+    async fn get(&self, index: Range<usize>) -> Self::Output {
+        if index.is_empty() {
+            return Ok(new_empty_array(self.data_type));
+        }
+        let header = self.read_header().await?;
+        self.decode_range(header, index).await
+    }
+}
+
+#[async_trait]
+impl<'a> AsyncIndex<ReadBatchParams> for BitPackedDecoder<'a> {
+    type Output = Result<ArrayRef>;
+
+    async fn get(&self, params: ReadBatchParams) -> Self::Output {
+        match params {
+            ReadBatchParams::Range(r) => self.get(r).await,
+            ReadBatchParams::RangeFull => self.get(0..self.length).await,
+            ReadBatchParams::RangeTo(r) => self.get(0..r.end).await,
+            ReadBatchParams::RangeFrom(r) => self.get(r.start..self.length).await,
+            ReadBatchParams::Indices(indices) => self.take(&indices).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use arrow_array::{
+        Int16Array, Int64Array, Int8Array, TimestampMicrosecondArray, UInt64Array, UInt8Array,
+    };
+    use object_store::path::Path;
+
+    use crate::encodings::plain::PlainEncoder;
+    use crate::io::ObjectStore;
+
+    #[test]
+    fn test_pack_and_unpack() {
+        for width in [0_u8, 1, 3, 7, 8, 13, 31, 57, 63, 64] {
+            let values = (0..100_u64)
+                .map(|v| v.wrapping_mul(0x9E37_79B9_7F4A_7C15) & mask(width))
+                .collect::<Vec<_>>();
+            let mut buf = vec![];
+            pack(values.iter().copied(), width, &mut buf);
+            assert_eq!(buf.len(), packed_len(values.len(), width));
+            for (i, value) in values.iter().enumerate() {
+                assert_eq!(unpack(&buf, i * width as usize, width), *value);
+            }
+        }
+    }
+
+    async fn test_round_trip(expected: ArrayRef) {
+        let data_type = expected.data_type().clone();
+        let store = ObjectStore::memory();
+        let path = Path::from("/bitpacked");
+        let mut object_writer = ObjectWriter::new(&store, &path).await.unwrap();
+        // Write something before the page, so the page does not start from zero.
+        object_writer.write_all(b"LANC").await.unwrap();
+        let mut encoder = BitPackedEncoder::new(&mut object_writer, &data_type);
+        let pos = encoder.encode(expected.as_ref()).await.unwrap();
+        let validity = PlainEncoder::new(&mut object_writer, &data_type)
+            .encode_validity(expected.as_ref())
+            .await
+            .unwrap();
+        object_writer.shutdown().await.unwrap();
+
+        let reader = store.open(&path).await.unwrap();
+        let mut decoder = BitPackedDecoder::new(reader.as_ref(), &data_type, pos, expected.len());
+        if let Some(validity) = validity {
+            decoder = decoder.with_validity(validity);
+        }
+        assert_eq!(decoder.decode().await.unwrap().as_ref(), expected.as_ref());
+        assert_eq!(
+            decoder.get(3..expected.len() - 5).await.unwrap().as_ref(),
+            expected.slice(3, expected.len() - 8).as_ref()
+        );
+
+        let indices = UInt32Array::from(vec![0, 1, 7, 8, 9, 50, expected.len() as u32 - 1]);
+        let actual = decoder.take(&indices).await.unwrap();
+        assert_eq!(
+            actual.as_ref(),
+            take(expected.as_ref(), &indices, None).unwrap().as_ref()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_encode_decode_bitpacked() {
+        let arrays: Vec<ArrayRef> = vec![
+            Arc::new(Int8Array::from_iter_values((0..100).map(|v| v - 50))),
+            Arc::new(Int16Array::from_iter(
+                (0..100).map(|v| (v % 3 != 0).then_some(v * 3)),
+            )),
+            Arc::new(Int64Array::from_iter_values((0..100).map(|v| {
+                if v % 2 == 0 {
+                    i64::MIN
+                } else {
+                    i64::MAX
+                }
+            }))),
+            Arc::new(UInt64Array::from_iter_values(
+                (0..100).map(|v| u64::MAX - v),
+            )),
+            Arc::new(UInt8Array::from_iter_values(vec![42; 100])),
+            TimestampMicrosecondArray::from_iter_values(
+                (0..100).map(|v| 1_680_000_000_000_000 + v * 1_000),
+            )
+            .slice(10, 80),
+        ];
+        for array in arrays {
+            test_round_trip(array).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_encoded_size() {
+        let values = integer_values(&Int64Array::from_iter_values(1000..1100));
+        // 100 values in 7 bits, following the header.
+        assert_eq!(encoded_size(&values, &DataType::Int64), 16 + 88);
+
+        let store = ObjectStore::memory();
+        let path = Path::from("/bitpacked");
+        let mut object_writer = ObjectWriter::new(&store, &path).await.unwrap();
+        let mut encoder = BitPackedEncoder::new(&mut object_writer, &DataType::Float32);
+        assert!(matches!(
+            encoder
+                .encode(&arrow_array::Float32Array::from(vec![1.0]))
+                .await,
+            Err(Error::Schema(_))
+        ));
+    }
+}
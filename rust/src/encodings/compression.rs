@@ -0,0 +1,109 @@
+// Copyright 2023 Lance Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! General-purpose compression of the data pages.
+//!
+//! The encoders write a page as if it was not compressed, then the bytes of the whole page
+//! are compressed and stored in place. A compressed page is read and decompressed as a whole,
+//! so that reading a few rows only decompresses the pages they are in.
+
+use std::str::FromStr;
+
+use crate::format::pb;
+use crate::{Error, Result};
+
+/// Compression codec of the data pages of a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Zstandard, at the default compression level.
+    Zstd,
+    /// LZ4 block format, prefixed with the decompressed size.
+    Lz4,
+}
+
+impl Compression {
+    /// Compress the bytes of a page.
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::Zstd => Ok(zstd::bulk::compress(data, zstd::DEFAULT_COMPRESSION_LEVEL)?),
+            Self::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+        }
+    }
+
+    /// Decompress the bytes of a page written by [Self::compress].
+    pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::Zstd => Ok(zstd::stream::decode_all(data)?),
+            Self::Lz4 => lz4_flex::decompress_size_prepended(data)
+                .map_err(|e| Error::IO(format!("Failed to decompress LZ4 page: {e}"))),
+        }
+    }
+}
+
+impl Compression {
+    /// The compression of a [pb::Compression] value, or `None` if it is uncompressed.
+    pub(crate) fn from_proto(value: i32) -> Option<Self> {
+        match pb::Compression::from_i32(value) {
+            Some(pb::Compression::Zstd) => Some(Self::Zstd),
+            Some(pb::Compression::Lz4) => Some(Self::Lz4),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "zstd" => Ok(Self::Zstd),
+            "lz4" => Ok(Self::Lz4),
+            _ => Err(Error::IO(format!("Unsupported compression: {s}"))),
+        }
+    }
+}
+
+impl From<Compression> for pb::Compression {
+    fn from(c: Compression) -> Self {
+        match c {
+            Compression::Zstd => Self::Zstd,
+            Compression::Lz4 => Self::Lz4,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_and_decompress() {
+        let data = (0..4096).map(|v| (v % 7) as u8).collect::<Vec<_>>();
+        for compression in [Compression::Zstd, Compression::Lz4] {
+            let compressed = compression.compress(&data).unwrap();
+            assert!(compressed.len() < data.len());
+            assert_eq!(compression.decompress(&compressed).unwrap(), data);
+
+            let compressed = compression.compress(&[]).unwrap();
+            assert!(compression.decompress(&compressed).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_parse_compression() {
+        assert_eq!(Compression::from_str("zstd").unwrap(), Compression::Zstd);
+        assert_eq!(Compression::from_str("LZ4").unwrap(), Compression::Lz4);
+        assert!(Compression::from_str("snappy").is_err());
+    }
+}
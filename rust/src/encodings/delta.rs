@@ -0,0 +1,414 @@
+// Copyright 2023 Lance Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Delta encoding of integers.
+//!
+//! The values are split into blocks of [DELTA_BLOCK_SIZE] values. A block stores its first
+//! value, and the differences between the consecutive values, bit-packed as in the
+//! [bit-packed encoding](super::bitpacked) relative to the minimal difference of the block.
+//!
+//! ```text
+//! | block header 0 | ... | block header N-1 | packed deltas 0 | ... | packed deltas N-1 |
+//! ```
+//!
+//! Each block header is 24 bytes:
+//!
+//! ```text
+//! | first value: u64 | min delta: u64 | offset: u32 | bit width: u8 | padding: [u8; 3] |
+//! ```
+//!
+//! where the offset is the position of the packed deltas of the block, relative to the end
+//! of the block headers. Reading a range of rows only reads the blocks it falls in.
+
+use std::ops::Range;
+
+use arrow_arith::arithmetic::subtract_scalar;
+use arrow_array::cast::{as_boolean_array, as_primitive_array};
+use arrow_array::{new_empty_array, Array, ArrayRef, UInt32Array};
+use arrow_buffer::bit_util;
+use arrow_schema::DataType;
+use arrow_select::{concat::concat, take::take};
+use async_trait::async_trait;
+use byteorder::{ByteOrder, LittleEndian};
+use bytes::Bytes;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use tokio::io::AsyncWriteExt;
+
+use super::bitpacked::{
+    check_integer_type, integer_array, integer_values, pack, packed_len, reference_and_width,
+    unpack,
+};
+use super::plain::{apply_validity, PlainDecoder};
+use super::{AsyncIndex, Decoder, Encoder};
+use crate::io::object_reader::ObjectReader;
+use crate::io::object_writer::ObjectWriter;
+use crate::io::ReadBatchParams;
+use crate::{Error, Result};
+
+/// Number of values in a block.
+pub const DELTA_BLOCK_SIZE: usize = 1024;
+
+/// Size of a block header in bytes.
+const BLOCK_HEADER_SIZE: usize = 24;
+
+/// The differences between the consecutive values of a block, wrapping around.
+fn deltas(block: &[u64]) -> Vec<u64> {
+    block.windows(2).map(|w| w[1].wrapping_sub(w[0])).collect()
+}
+
+/// The size of the values encoded in a page, in bytes.
+pub(crate) fn encoded_size(values: &[u64]) -> usize {
+    values
+        .chunks(DELTA_BLOCK_SIZE)
+        .map(|block| {
+            let deltas = deltas(block);
+            let (_, width) = reference_and_width(&deltas, true);
+            BLOCK_HEADER_SIZE + packed_len(deltas.len(), width)
+        })
+        .sum()
+}
+
+/// Encoder for delta encoding.
+pub struct DeltaEncoder<'a> {
+    writer: &'a mut ObjectWriter,
+    data_type: &'a DataType,
+}
+
+impl<'a> DeltaEncoder<'a> {
+    pub fn new(writer: &'a mut ObjectWriter, data_type: &'a DataType) -> Self {
+        Self { writer, data_type }
+    }
+}
+
+#[async_trait]
+impl<'a> Encoder for DeltaEncoder<'a> {
+    /// Encode the values of an array, and returns the position of the page.
+    ///
+    /// The nulls are not written, see [PlainEncoder::encode_validity](super::plain::PlainEncoder::encode_validity).
+    async fn encode(&mut self, array: &dyn Array) -> Result<usize> {
+        check_integer_type(self.data_type)?;
+        let values = integer_values(array);
+
+        let mut headers = vec![];
+        let mut data = vec![];
+        for block in values.chunks(DELTA_BLOCK_SIZE) {
+            let deltas = deltas(block);
+            let (min_delta, width) = reference_and_width(&deltas, true);
+            headers.extend_from_slice(&block[0].to_le_bytes());
+            headers.extend_from_slice(&min_delta.to_le_bytes());
+            headers.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            headers.push(width);
+            headers.resize(headers.len() + 3, 0);
+            pack(
+                deltas.iter().map(|d| d.wrapping_sub(min_delta)),
+                width,
+                &mut data,
+            );
+        }
+
+        let pos = self.writer.tell();
+        self.writer.write_all(&headers).await?;
+        self.writer.write_all(&data).await?;
+        Ok(pos)
+    }
+}
+
+/// Header of a block of delta encoded values.
+#[derive(Debug, Clone, Copy)]
+struct BlockHeader {
+    first_value: u64,
+    min_delta: u64,
+    offset: usize,
+    width: u8,
+}
+
+impl BlockHeader {
+    fn parse(bytes: &[u8]) -> Self {
+        Self {
+            first_value: LittleEndian::read_u64(&bytes[..8]),
+            min_delta: LittleEndian::read_u64(&bytes[8..16]),
+            offset: LittleEndian::read_u32(&bytes[16..20]) as usize,
+            width: bytes[20],
+        }
+    }
+}
+
+/// Decoder for delta encoding.
+pub struct DeltaDecoder<'a> {
+    reader: &'a dyn ObjectReader,
+    data_type: &'a DataType,
+    /// The start position of the page in the file.
+    position: usize,
+    /// Number of the rows in the page.
+    length: usize,
+    /// The position of the validity bitmaps, if the page has nulls.
+    validity: Option<usize>,
+}
+
+impl<'a> DeltaDecoder<'a> {
+    pub fn new(
+        reader: &'a dyn ObjectReader,
+        data_type: &'a DataType,
+        position: usize,
+        length: usize,
+    ) -> Self {
+        Self {
+            reader,
+            data_type,
+            position,
+            length,
+            validity: None,
+        }
+    }
+
+    /// Decode the nulls from the validity bitmaps at `position`.
+    pub fn with_validity(mut self, position: usize) -> Self {
+        self.validity = Some(position);
+        self
+    }
+
+    /// Decode the values in `range`, reading only the blocks the range falls in.
+    async fn decode_values(&self, range: Range<usize>) -> Result<Vec<u64>> {
+        let num_blocks = bit_util::ceil(self.length, DELTA_BLOCK_SIZE);
+        let first_block = range.start / DELTA_BLOCK_SIZE;
+        let last_block = (range.end - 1) / DELTA_BLOCK_SIZE;
+
+        let header_bytes = self
+            .reader
+            .get_range(
+                self.position + first_block * BLOCK_HEADER_SIZE
+                    ..self.position + (last_block + 1) * BLOCK_HEADER_SIZE,
+            )
+            .await?;
+        let headers = header_bytes
+            .chunks_exact(BLOCK_HEADER_SIZE)
+            .map(BlockHeader::parse)
+            .collect::<Vec<_>>();
+
+        // The deltas of the last block are only read up to the end of the range.
+        let data_start = self.position + num_blocks * BLOCK_HEADER_SIZE;
+        let last_header = headers[headers.len() - 1];
+        let last_deltas = range.end - last_block * DELTA_BLOCK_SIZE - 1;
+        let data_range = data_start + headers[0].offset
+            ..data_start + last_header.offset + packed_len(last_deltas, last_header.width);
+        let data = if data_range.is_empty() {
+            Bytes::new()
+        } else {
+            self.reader.get_range(data_range).await?
+        };
+
+        let mut values = Vec::with_capacity(range.len());
+        for (i, header) in headers.iter().enumerate() {
+            let block_start = (first_block + i) * DELTA_BLOCK_SIZE;
+            let block_end = (block_start + DELTA_BLOCK_SIZE).min(range.end);
+            let bytes = &data[header.offset - headers[0].offset..];
+            let mut value = header.first_value;
+            for row in block_start..block_end {
+                if row > block_start {
+                    let bit_offset = (row - block_start - 1) * header.width as usize;
+                    let delta = unpack(bytes, bit_offset, header.width);
+                    value = value.wrapping_add(header.min_delta).wrapping_add(delta);
+                }
+                if row >= range.start {
+                    values.push(value);
+                }
+            }
+        }
+        Ok(values)
+    }
+
+    async fn decode_range(&self, range: Range<usize>) -> Result<ArrayRef> {
+        if range.end > self.length {
+            return Err(Error::IO(format!(
+                "DeltaDecoder: request([{}..{}]) out of range: [0..{}]",
+                range.start, range.end, self.length
+            )));
+        }
+        if range.is_empty() {
+            return Ok(new_empty_array(self.data_type));
+        }
+
+        let values = self.decode_values(range.clone()).await?;
+        let array = integer_array(self.data_type, &values)?;
+
+        if let Some(position) = self.validity {
+            let validity_decoder =
+                PlainDecoder::new(self.reader, &DataType::Boolean, position, self.length)?;
+            let validity = validity_decoder.get(range).await?;
+            apply_validity(array, as_boolean_array(validity.as_ref()))
+        } else {
+            Ok(array)
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> Decoder for DeltaDecoder<'a> {
+    async fn decode(&self) -> Result<ArrayRef> {
+        self.decode_range(0..self.length).await
+    }
+
+    /// Take the rows at the sorted `indices`, decoding each block they fall in once.
+    async fn take(&self, indices: &UInt32Array) -> Result<ArrayRef> {
+        if indices.is_empty() {
+            return Ok(new_empty_array(self.data_type));
+        }
+
+        let mut chunk_ranges = vec![];
+        let mut start = 0;
+        for j in 0..indices.len() - 1 {
+            if indices.value(j + 1) as usize / DELTA_BLOCK_SIZE
+                != indices.value(start) as usize / DELTA_BLOCK_SIZE
+            {
+                chunk_ranges.push(start..j + 1);
+                start = j + 1;
+            }
+        }
+        chunk_ranges.push(start..indices.len());
+
+        let arrays = stream::iter(chunk_ranges)
+            .map(|cr| async move {
+                let index_chunk = indices.slice(cr.start, cr.len());
+                let request: &UInt32Array = as_primitive_array(&index_chunk);
+
+                let start = request.value(0);
+                let end = request.value(request.len() - 1);
+                let array = self.decode_range(start as usize..end as usize + 1).await?;
+                let adjusted_offsets = subtract_scalar(request, start)?;
+                Ok::<ArrayRef, Error>(take(&array, &adjusted_offsets, None)?)
+            })
+            .buffered(8)
+            .try_collect::<Vec<_>>()
+            .await?;
+        let references = arrays.iter().map(|a| a.as_ref()).collect::<Vec<_>>();
+        Ok(concat(&references)?)
+    }
+}
+
+#[async_trait]
+impl<'a> AsyncIndex<Range<usize>> for DeltaDecoder<'a> {
+    type Output = Result<ArrayRef>;
+
+    async fn get(&self, index: Range<usize>) -> Self::Output {
+        self.decode_range(index).await
+    }
+}
+
+#[async_trait]
+impl<'a> AsyncIndex<ReadBatchParams> for DeltaDecoder<'a> {
+    type Output = Result<ArrayRef>;
+
+    async fn get(&self, params: ReadBatchParams) -> Self::Output {
+        match params {
+            ReadBatchParams::Range(r) => self.get(r).await,
+            ReadBatchParams::RangeFull => self.get(0..self.length).await,
+            ReadBatchParams::RangeTo(r) => self.get(0..r.end).await,
+            ReadBatchParams::RangeFrom(r) => self.get(r.start..self.length).await,
+            ReadBatchParams::Indices(indices) => self.take(&indices).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use arrow_array::{
+        Date32Array, Int32Array, Int64Array, TimestampSecondArray, UInt16Array, UInt64Array,
+    };
+    use object_store::path::Path;
+
+    use crate::encodings::plain::PlainEncoder;
+    use crate::io::ObjectStore;
+
+    async fn test_round_trip(expected: ArrayRef) {
+        let data_type = expected.data_type().clone();
+        let store = ObjectStore::memory();
+        let path = Path::from("/delta");
+        let mut object_writer = ObjectWriter::new(&store, &path).await.unwrap();
+        object_writer.write_all(b"LANC").await.unwrap();
+        let mut encoder = DeltaEncoder::new(&mut object_writer, &data_type);
+        let pos = encoder.encode(expected.as_ref()).await.unwrap();
+        let validity = PlainEncoder::new(&mut object_writer, &data_type)
+            .encode_validity(expected.as_ref())
+            .await
+            .unwrap();
+        object_writer.shutdown().await.unwrap();
+
+        let reader = store.open(&path).await.unwrap();
+        let mut decoder = DeltaDecoder::new(reader.as_ref(), &data_type, pos, expected.len());
+        if let Some(validity) = validity {
+            decoder = decoder.with_validity(validity);
+        }
+        assert_eq!(decoder.decode().await.unwrap().as_ref(), expected.as_ref());
+
+        // Ranges within a block, across the blocks, and at the end of the last block.
+        let len = expected.len();
+        for range in [3..20, 1000..2100, len - 7..len] {
+            assert_eq!(
+                decoder.get(range.clone()).await.unwrap().as_ref(),
+                expected.slice(range.start, range.len()).as_ref()
+            );
+        }
+
+        let indices = UInt32Array::from(vec![0, 1, 1023, 1024, 1500, 2047, len as u32 - 1]);
+        let actual = decoder.take(&indices).await.unwrap();
+        assert_eq!(
+            actual.as_ref(),
+            take(expected.as_ref(), &indices, None).unwrap().as_ref()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_encode_decode_delta() {
+        let arrays: Vec<ArrayRef> = vec![
+            Arc::new(Int64Array::from_iter_values(
+                (0..3000).map(|v| v * 7 - 10_000),
+            )),
+            Arc::new(Int32Array::from_iter(
+                (0..3000).map(|v| (v % 5 != 0).then_some(3000 - v)),
+            )),
+            Arc::new(Int64Array::from_iter_values((0..3000).map(|v| {
+                if v % 3 == 0 {
+                    i64::MIN
+                } else {
+                    i64::MAX
+                }
+            }))),
+            Arc::new(UInt64Array::from_iter_values(
+                (0..3000).map(|v| u64::MAX - v * 11),
+            )),
+            Arc::new(UInt16Array::from_iter_values((0..3000).map(|v| v % 17))),
+            Arc::new(Date32Array::from_iter_values(vec![19_000; 3000])),
+            TimestampSecondArray::from_iter_values((0..3000).map(|v| 1_680_000_000 + v))
+                .slice(100, 2500),
+        ];
+        for array in arrays {
+            test_round_trip(array).await;
+        }
+    }
+
+    #[test]
+    fn test_encoded_size() {
+        // Sorted values with a constant step are stored in the block headers.
+        let values = integer_values(&Int64Array::from_iter_values((0..2048).map(|v| v * 100)));
+        assert_eq!(encoded_size(&values), 2 * BLOCK_HEADER_SIZE);
+
+        let values = integer_values(&Int64Array::from_iter_values(vec![1, 3, 2, 4]));
+        // Deltas 2, -1, 2 are stored as 3, 0, 3 in 2 bits.
+        assert_eq!(encoded_size(&values), BLOCK_HEADER_SIZE + 1);
+    }
+}
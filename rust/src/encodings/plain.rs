@@ -19,6 +19,9 @@
 //!
 //! Plain encoding works with fixed stride types, i.e., `boolean`, `i8...i64`, `f16...f64`,
 //! it stores the array directly in the file. It offers O(1) read access.
+//!
+//! The nulls are stored in validity bitmaps after the values, see
+//! [PlainEncoder::encode_validity].
 
 use std::ops::{Range, RangeFrom, RangeFull, RangeTo};
 use std::slice::from_raw_parts;
@@ -109,31 +112,110 @@ impl<'a> PlainEncoder<'a> {
         Ok(offset)
     }
 
+    /// Encode the validity bitmaps of an array, if it has nulls.
+    ///
+    /// For a fixed size list, the bitmap of the list is followed by the bitmaps of its items.
+    /// Returns the position of the bitmaps, or `None` without writing anything if the array
+    /// and its items do not have nulls.
+    pub async fn encode_validity(&mut self, array: &dyn Array) -> Result<Option<usize>> {
+        if !has_nulls(array, self.data_type) {
+            return Ok(None);
+        }
+        let offset = self.writer.tell();
+        self.encode_validity_internal(array, self.data_type).await?;
+        Ok(Some(offset))
+    }
+
+    #[async_recursion]
+    async fn encode_validity_internal(
+        &mut self,
+        array: &dyn Array,
+        data_type: &DataType,
+    ) -> Result<()> {
+        let validity = BooleanArray::from(
+            (0..array.len())
+                .map(|i| array.is_valid(i))
+                .collect::<Vec<_>>(),
+        );
+        self.encode_boolean(&validity).await?;
+        if let DataType::FixedSizeList(items, _) = data_type {
+            let values = fixed_size_list_values(array)?;
+            self.encode_validity_internal(values.as_ref(), items.data_type())
+                .await?;
+        }
+        Ok(())
+    }
+
     /// Encode fixed size list.
     async fn encode_fixed_size_list(&mut self, array: &dyn Array, items: &Field) -> Result<usize> {
-        let list_array = array
-            .as_any()
-            .downcast_ref::<FixedSizeListArray>()
-            .ok_or_else(|| {
-                Error::Schema(format!(
-                    "Needed a FixedSizeListArray but got {}",
-                    array.data_type()
-                ))
-            })?;
-        let offset = list_array.value_offset(0) as usize;
-        let length = list_array.len() as usize;
-        let value_length = list_array.value_length() as usize;
-        self.encode_internal(
-            list_array
-                .values()
-                .slice(offset, length * value_length)
-                .as_ref(),
-            items.data_type(),
-        )
-        .await
+        let values = fixed_size_list_values(array)?;
+        self.encode_internal(values.as_ref(), items.data_type())
+            .await
     }
 }
 
+/// The item values of the lists in a [FixedSizeListArray].
+fn fixed_size_list_values(array: &dyn Array) -> Result<ArrayRef> {
+    let list_array = array
+        .as_any()
+        .downcast_ref::<FixedSizeListArray>()
+        .ok_or_else(|| {
+            Error::Schema(format!(
+                "Needed a FixedSizeListArray but got {}",
+                array.data_type()
+            ))
+        })?;
+    let offset = list_array.value_offset(0) as usize;
+    let length = list_array.len();
+    let value_length = list_array.value_length() as usize;
+    Ok(list_array.values().slice(offset, length * value_length))
+}
+
+/// Returns true if the array, or the items of a fixed size list, have nulls.
+fn has_nulls(array: &dyn Array, data_type: &DataType) -> bool {
+    if array.null_count() > 0 {
+        return true;
+    }
+    match data_type {
+        DataType::FixedSizeList(items, _) if !array.is_empty() => {
+            match fixed_size_list_values(array) {
+                Ok(values) => has_nulls(values.as_ref(), items.data_type()),
+                Err(_) => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Set the nulls of `array` from `validity`, which has the same length and no nulls.
+pub(crate) fn apply_validity(array: ArrayRef, validity: &BooleanArray) -> Result<ArrayRef> {
+    let null_count = validity.len()
+        - validity
+            .values()
+            .count_set_bits_offset(validity.offset(), validity.len());
+    if null_count == 0 {
+        return Ok(array);
+    }
+    let data = array.data();
+    // The boolean arrays decoded from the same range start at the same bit offset.
+    let null_buffer = if data.offset() == validity.offset() {
+        validity.values().clone()
+    } else {
+        validity
+            .values()
+            .bit_slice(validity.offset(), validity.len())
+    };
+    let array_data = ArrayDataBuilder::new(data.data_type().clone())
+        .len(data.len())
+        .offset(data.offset())
+        .buffers(data.buffers().to_vec())
+        .child_data(data.child_data().to_vec())
+        .null_count(null_count)
+        .null_bit_buffer(Some(null_buffer))
+        .build()?;
+    Ok(make_array(array_data))
+}
+
 /// Decoder for plain encoding.
 pub struct PlainDecoder<'a> {
     reader: &'a dyn ObjectReader,
@@ -142,6 +224,8 @@ pub struct PlainDecoder<'a> {
     position: usize,
     /// Number of the rows in this batch.
     length: usize,
+    /// The position of the validity bitmaps, if the batch has nulls.
+    validity: Option<usize>,
 }
 
 /// Get byte range from the row offset range.
@@ -165,9 +249,17 @@ impl<'a> PlainDecoder<'a> {
             data_type,
             position,
             length,
+            validity: None,
         })
     }
 
+    /// Decode the nulls from the validity bitmaps at `position`, written by
+    /// [PlainEncoder::encode_validity].
+    pub fn with_validity(mut self, position: usize) -> Self {
+        self.validity = Some(position);
+        self
+    }
+
     /// Decode primitive values, from "offset" to "offset + length".
     ///
     async fn decode_primitive(&self, start: usize, end: usize) -> Result<ArrayRef> {
@@ -185,8 +277,14 @@ impl<'a> PlainDecoder<'a> {
 
         let data = self.reader.get_range(range).await?;
         let buf: Buffer = data.into();
+        let offset = match self.data_type {
+            // The bytes start at the byte boundary of `start`.
+            DataType::Boolean => start % 8,
+            _ => 0,
+        };
         let array_data = ArrayDataBuilder::new(self.data_type.clone())
             .len(end - start)
+            .offset(offset)
             .null_count(0)
             .add_buffer(buf)
             .build()?;
@@ -206,12 +304,16 @@ impl<'a> PlainDecoder<'a> {
                 items.data_type()
             )));
         };
-        let item_decoder = PlainDecoder::new(
+        let mut item_decoder = PlainDecoder::new(
             self.reader,
             items.data_type(),
             self.position,
             self.length * list_size as usize,
         )?;
+        if let Some(validity) = self.validity {
+            // The bitmaps of the items follow the bitmap of the lists.
+            item_decoder = item_decoder.with_validity(validity + bit_util::ceil(self.length, 8));
+        }
         let item_array = item_decoder
             .get(start * list_size as usize..end * list_size as usize)
             .await?;
@@ -247,8 +349,7 @@ impl<'a> PlainDecoder<'a> {
         let start = indices.value(0) as usize;
         let end = indices.value(indices.len() - 1) as usize;
         let array = self.get(start..end + 1).await?;
-        let array_byte_boundray = (start / 8 * 8) as u32;
-        let shifted_indices = subtract_scalar(indices, array_byte_boundray)?;
+        let shifted_indices = subtract_scalar(indices, start as u32)?;
         Ok(take(array.as_ref(), &shifted_indices, None)?)
     }
 }
@@ -321,16 +422,24 @@ impl AsyncIndex<Range<usize>> for PlainDecoder<'_> {
         if index.is_empty() {
             return Ok(new_empty_array(self.data_type));
         }
-        match self.data_type {
+        let array = match self.data_type {
             DataType::FixedSizeList(items, list_size) => {
                 self.decode_fixed_size_list(items, *list_size, index.start, index.end)
-                    .await
+                    .await?
             }
             DataType::FixedSizeBinary(stride) => {
                 self.decode_fixed_size_binary(*stride, index.start, index.end)
-                    .await
+                    .await?
             }
-            _ => self.decode_primitive(index.start, index.end).await,
+            _ => self.decode_primitive(index.start, index.end).await?,
+        };
+        if let Some(position) = self.validity {
+            let validity_decoder =
+                PlainDecoder::new(self.reader, &DataType::Boolean, position, self.length)?;
+            let validity = validity_decoder.get(index).await?;
+            apply_validity(array, as_boolean_array(validity.as_ref()))
+        } else {
+            Ok(array)
         }
     }
 }
@@ -383,7 +492,7 @@ mod tests {
 
     use arrow::compute::concat_batches;
     use arrow_array::*;
-    use arrow_schema::{Field, Schema as ArrowSchema};
+    use arrow_schema::{Field, Schema as ArrowSchema, TimeUnit};
     use object_store::path::Path;
     use rand::prelude::*;
 
@@ -428,14 +537,204 @@ mod tests {
         let mut encoder = PlainEncoder::new(&mut object_writer, &data_type);
 
         assert_eq!(encoder.encode(expected.as_ref()).await.unwrap(), 0);
+        let validity = encoder.encode_validity(expected.as_ref()).await.unwrap();
         object_writer.shutdown().await.unwrap();
 
         let reader = store.open(&path).await.unwrap();
         assert!(reader.size().await.unwrap() > 0);
-        let decoder = PlainDecoder::new(reader.as_ref(), &data_type, 0, expected.len()).unwrap();
+        let mut decoder =
+            PlainDecoder::new(reader.as_ref(), &data_type, 0, expected.len()).unwrap();
+        if let Some(validity) = validity {
+            decoder = decoder.with_validity(validity);
+        }
         let arr = decoder.decode().await.unwrap();
         let actual = arr.as_ref();
         assert_eq!(expected.as_ref(), actual);
+
+        // A range that does not start at a byte boundary.
+        let arr = decoder.get(3..expected.len() - 1).await.unwrap();
+        assert_eq!(expected.slice(3, expected.len() - 4).as_ref(), arr.as_ref());
+    }
+
+    /// Make an array of 126 values, where every third value is null.
+    fn make_nullable_array(data_type: &DataType) -> ArrayRef {
+        let values: Vec<i64> = (0..126 * 4).collect();
+        make_array(
+            ArrayDataBuilder::new(data_type.clone())
+                .len(126)
+                .add_buffer(Buffer::from_slice_ref(values.as_slice()))
+                .null_bit_buffer(Some(Buffer::from_iter((0..126).map(|i| i % 3 != 0))))
+                .build()
+                .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_encode_decode_nullable_primitive_arrays() {
+        let types = vec![
+            DataType::Int8,
+            DataType::Int16,
+            DataType::Int32,
+            DataType::Int64,
+            DataType::UInt8,
+            DataType::UInt16,
+            DataType::UInt32,
+            DataType::UInt64,
+            DataType::Float16,
+            DataType::Float32,
+            DataType::Float64,
+            DataType::Decimal128(7, 3),
+            DataType::Decimal256(7, 3),
+            DataType::Date32,
+            DataType::Date64,
+            DataType::Time32(TimeUnit::Second),
+            DataType::Time32(TimeUnit::Millisecond),
+            DataType::Time64(TimeUnit::Microsecond),
+            DataType::Time64(TimeUnit::Nanosecond),
+            DataType::Duration(TimeUnit::Second),
+            DataType::Duration(TimeUnit::Millisecond),
+            DataType::Duration(TimeUnit::Microsecond),
+            DataType::Duration(TimeUnit::Nanosecond),
+        ];
+        for t in types {
+            let arr = make_nullable_array(&t);
+            assert_eq!(arr.null_count(), 42);
+            test_round_trip(arr, t).await;
+        }
+
+        let arr = BooleanArray::from(
+            (0..200)
+                .map(|i| if i % 3 == 0 { None } else { Some(i % 2 == 0) })
+                .collect::<Vec<_>>(),
+        );
+        test_round_trip(Arc::new(arr) as ArrayRef, DataType::Boolean).await;
+
+        let arr = FixedSizeBinaryArray::try_from_sparse_iter((0..60_u8).map(|i| {
+            if i % 4 == 1 {
+                None
+            } else {
+                Some([i, i + 1])
+            }
+        }))
+        .unwrap();
+        test_round_trip(Arc::new(arr) as ArrayRef, DataType::FixedSizeBinary(2)).await;
+    }
+
+    #[tokio::test]
+    async fn test_encode_decode_nullable_fixed_size_list() {
+        // Null lists, and null items in the lists.
+        let item_type = DataType::Float32;
+        let list_type = DataType::FixedSizeList(Box::new(Field::new("item", item_type, true)), 3);
+        let items = Float32Array::from(
+            (0..126)
+                .map(|i| if i % 5 == 0 { None } else { Some(i as f32) })
+                .collect::<Vec<_>>(),
+        );
+        let arr = make_array(
+            ArrayDataBuilder::new(list_type.clone())
+                .len(42)
+                .add_child_data(items.into_data())
+                .null_bit_buffer(Some(Buffer::from_iter((0..42).map(|i| i % 4 != 2))))
+                .build()
+                .unwrap(),
+        );
+        test_round_trip(arr.clone(), list_type.clone()).await;
+
+        // Only the items have nulls.
+        let items = Int32Array::from(
+            (0..126)
+                .map(|i| if i % 7 == 0 { None } else { Some(i) })
+                .collect::<Vec<_>>(),
+        );
+        let arr = FixedSizeListArray::try_new(items, 3).unwrap();
+        let list_type =
+            DataType::FixedSizeList(Box::new(Field::new("item", DataType::Int32, true)), 3);
+        test_round_trip(Arc::new(arr) as ArrayRef, list_type).await;
+    }
+
+    #[tokio::test]
+    async fn test_read_and_take_nullable_columns() {
+        let store = ObjectStore::memory();
+        let path = Path::from("/nullable");
+
+        let arrow_schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("i", DataType::Int32, true),
+            Field::new("b", DataType::Boolean, true),
+            Field::new(
+                "vec",
+                DataType::FixedSizeList(Box::new(Field::new("item", DataType::Float32, true)), 2),
+                true,
+            ),
+        ]));
+        let schema = Schema::try_from(arrow_schema.as_ref()).unwrap();
+        let mut file_writer = FileWriter::try_new(&store, &path, &schema).await.unwrap();
+
+        let ints = Int32Array::from(
+            (0..100)
+                .map(|i| if i % 3 == 0 { None } else { Some(i) })
+                .collect::<Vec<_>>(),
+        );
+        let bools = BooleanArray::from(
+            (0..100)
+                .map(|i| if i % 4 == 0 { None } else { Some(i % 2 == 1) })
+                .collect::<Vec<_>>(),
+        );
+        let vectors = make_array(
+            ArrayDataBuilder::new(arrow_schema.field(2).data_type().clone())
+                .len(100)
+                .add_child_data(
+                    Float32Array::from_iter_values((0..200).map(|v| v as f32)).into_data(),
+                )
+                .null_bit_buffer(Some(Buffer::from_iter((0..100).map(|i| i % 5 != 0))))
+                .build()
+                .unwrap(),
+        );
+        let batch = RecordBatch::try_new(
+            arrow_schema.clone(),
+            vec![Arc::new(ints), Arc::new(bools), vectors],
+        )
+        .unwrap();
+        // The second batch starts in the middle of a byte of the bitmaps.
+        file_writer.write(&batch.slice(0, 50)).await.unwrap();
+        file_writer.write(&batch.slice(50, 50)).await.unwrap();
+        file_writer.finish().await.unwrap();
+
+        let actual = read_file_as_one_batch(&store, &path).await;
+        assert_eq!(actual.columns(), batch.columns());
+
+        let reader = FileReader::try_new(&store, &path).await.unwrap();
+        let indices = [1, 3, 10, 11, 60, 75, 99];
+        let actual = reader.take(&indices, &schema).await.unwrap();
+        let expected_indices = UInt32Array::from(indices.to_vec());
+        let expected = batch
+            .columns()
+            .iter()
+            .map(|c| take(c.as_ref(), &expected_indices, None).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(actual.columns(), expected.as_slice());
+    }
+
+    #[tokio::test]
+    async fn test_write_nulls_to_non_nullable_field() {
+        let store = ObjectStore::memory();
+        let path = Path::from("/non_nullable");
+
+        let field = Field::new("i", DataType::Int32, false);
+        let schema = Schema::try_from(&ArrowSchema::new(vec![field])).unwrap();
+        let mut file_writer = FileWriter::try_new(&store, &path, &schema).await.unwrap();
+
+        // The batch schema allows nulls, but the file schema does not.
+        let arrow_schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            true,
+        )]));
+        let batch = RecordBatch::try_new(
+            arrow_schema,
+            vec![Arc::new(Int32Array::from(vec![Some(1), None]))],
+        )
+        .unwrap();
+        assert!(file_writer.write(&batch).await.is_err());
     }
 
     #[tokio::test]
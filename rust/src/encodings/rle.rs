@@ -18,3 +18,426 @@
 //! Run-length encoding
 //!
 //! <https://en.wikipedia.org/wiki/Run-length_encoding>
+//!
+//! A page stores the end of each run of equal values, followed by the value of each run,
+//! and a header with the positions of both:
+//!
+//! ```text
+//! | run ends: [u32; N] | run values | validity of run values | header |
+//! header: | num runs: u64 | run ends position: u64 | values position: u64 | validity position: u64 |
+//! ```
+//!
+//! The run values are written with the plain encoding, or the var-binary encoding for the
+//! binary types. A run of nulls is stored as a null value. The validity position is zero if
+//! no run is null. The position of the page is the position of the header.
+
+use std::ops::Range;
+
+use arrow_array::cast::{as_boolean_array, as_primitive_array};
+use arrow_array::types::{BinaryType, ByteArrayType, LargeBinaryType, LargeUtf8Type, Utf8Type};
+use arrow_array::{new_empty_array, Array, ArrayRef, GenericByteArray, UInt32Array};
+use arrow_schema::DataType;
+use arrow_select::take::take;
+use async_trait::async_trait;
+use byteorder::{ByteOrder, LittleEndian};
+use tokio::io::AsyncWriteExt;
+
+use super::binary::BinaryEncoder;
+use super::plain::{apply_validity, PlainDecoder, PlainEncoder};
+use super::{AsyncIndex, Decoder, Encoder};
+use crate::arrow::*;
+use crate::io::object_reader::{read_binary_array, ObjectReader};
+use crate::io::object_writer::ObjectWriter;
+use crate::io::ReadBatchParams;
+use crate::{Error, Result};
+
+/// Size of the page header in bytes.
+const HEADER_SIZE: usize = 32;
+
+/// Returns true if the values of `data_type` can be run-length encoded.
+///
+/// These are the primitive types, including booleans and fixed size binary, and the
+/// var-length binary and string types.
+pub fn supports_rle(data_type: &DataType) -> bool {
+    data_type.is_binary_like()
+        || (data_type.is_fixed_stride() && !matches!(data_type, DataType::FixedSizeList(_, _)))
+}
+
+fn byte_values_equal<'a, T: ByteArrayType>(
+    array: &'a dyn Array,
+) -> Box<dyn Fn(usize, usize) -> bool + 'a> {
+    let arr = array
+        .as_any()
+        .downcast_ref::<GenericByteArray<T>>()
+        .unwrap();
+    Box::new(move |i, j| {
+        let left: &[u8] = arr.value(i).as_ref();
+        let right: &[u8] = arr.value(j).as_ref();
+        left == right
+    })
+}
+
+/// The ends of the runs of equal values in the array. Nulls are equal to each other.
+fn run_ends(array: &dyn Array) -> Vec<u32> {
+    let values_equal: Box<dyn Fn(usize, usize) -> bool + '_> = match array.data_type() {
+        DataType::Boolean => {
+            let arr = as_boolean_array(array);
+            Box::new(move |i, j| arr.value(i) == arr.value(j))
+        }
+        DataType::Utf8 => byte_values_equal::<Utf8Type>(array),
+        DataType::LargeUtf8 => byte_values_equal::<LargeUtf8Type>(array),
+        DataType::Binary => byte_values_equal::<BinaryType>(array),
+        DataType::LargeBinary => byte_values_equal::<LargeBinaryType>(array),
+        data_type => {
+            let byte_width = data_type.byte_width();
+            let data = array.data();
+            let bytes = &data.buffers()[0].as_slice()[array.offset() * byte_width..];
+            Box::new(move |i, j| {
+                bytes[i * byte_width..(i + 1) * byte_width]
+                    == bytes[j * byte_width..(j + 1) * byte_width]
+            })
+        }
+    };
+
+    let mut ends = vec![];
+    for i in 1..array.len() {
+        let equal = match (array.is_valid(i - 1), array.is_valid(i)) {
+            (true, true) => values_equal(i - 1, i),
+            (left, right) => left == right,
+        };
+        if !equal {
+            ends.push(i as u32);
+        }
+    }
+    if !array.is_empty() {
+        ends.push(array.len() as u32);
+    }
+    ends
+}
+
+/// Encoder for run-length encoding.
+pub struct RleEncoder<'a> {
+    writer: &'a mut ObjectWriter,
+    data_type: &'a DataType,
+}
+
+impl<'a> RleEncoder<'a> {
+    pub fn new(writer: &'a mut ObjectWriter, data_type: &'a DataType) -> Self {
+        Self { writer, data_type }
+    }
+}
+
+#[async_trait]
+impl<'a> Encoder for RleEncoder<'a> {
+    /// Encode an array, and returns the position of the page header.
+    async fn encode(&mut self, array: &dyn Array) -> Result<usize> {
+        if !supports_rle(self.data_type) {
+            return Err(Error::Schema(format!(
+                "RLE encoding does not support {}",
+                self.data_type
+            )));
+        }
+        let ends = run_ends(array);
+        let starts = UInt32Array::from_iter_values(
+            std::iter::once(0)
+                .chain(ends.iter().copied())
+                .take(ends.len()),
+        );
+        let values = take(array, &starts, None)?;
+
+        let run_ends_pos = PlainEncoder::new(self.writer, &DataType::UInt32)
+            .encode(&UInt32Array::from(ends.clone()))
+            .await?;
+        let values_pos = if self.data_type.is_binary_like() {
+            BinaryEncoder::new(self.writer)
+                .encode(values.as_ref())
+                .await?
+        } else {
+            PlainEncoder::new(self.writer, self.data_type)
+                .encode(values.as_ref())
+                .await?
+        };
+        let validity_pos = PlainEncoder::new(self.writer, self.data_type)
+            .encode_validity(values.as_ref())
+            .await?;
+
+        let pos = self.writer.tell();
+        let mut header = [0_u8; HEADER_SIZE];
+        LittleEndian::write_u64(&mut header[..8], ends.len() as u64);
+        LittleEndian::write_u64(&mut header[8..16], run_ends_pos as u64);
+        LittleEndian::write_u64(&mut header[16..24], values_pos as u64);
+        LittleEndian::write_u64(&mut header[24..32], validity_pos.unwrap_or(0) as u64);
+        self.writer.write_all(&header).await?;
+        Ok(pos)
+    }
+}
+
+/// The page header of a run-length encoded page.
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    num_runs: usize,
+    run_ends_position: usize,
+    values_position: usize,
+    validity_position: Option<usize>,
+}
+
+/// Decoder for run-length encoding.
+pub struct RleDecoder<'a> {
+    reader: &'a dyn ObjectReader,
+    data_type: &'a DataType,
+    /// The position of the page header in the file.
+    position: usize,
+    /// Number of the rows in the page.
+    length: usize,
+}
+
+impl<'a> RleDecoder<'a> {
+    pub fn new(
+        reader: &'a dyn ObjectReader,
+        data_type: &'a DataType,
+        position: usize,
+        length: usize,
+    ) -> Self {
+        Self {
+            reader,
+            data_type,
+            position,
+            length,
+        }
+    }
+
+    async fn read_header(&self) -> Result<Header> {
+        let bytes = self
+            .reader
+            .get_range(self.position..self.position + HEADER_SIZE)
+            .await?;
+        let validity_position = LittleEndian::read_u64(&bytes[24..32]) as usize;
+        Ok(Header {
+            num_runs: LittleEndian::read_u64(&bytes[..8]) as usize,
+            run_ends_position: LittleEndian::read_u64(&bytes[8..16]) as usize,
+            values_position: LittleEndian::read_u64(&bytes[16..24]) as usize,
+            validity_position: (validity_position > 0).then_some(validity_position),
+        })
+    }
+
+    /// Read the header and the ends of all the runs.
+    async fn read_run_ends(&self) -> Result<(Header, UInt32Array)> {
+        let header = self.read_header().await?;
+        let decoder = PlainDecoder::new(
+            self.reader,
+            &DataType::UInt32,
+            header.run_ends_position,
+            header.num_runs,
+        )?;
+        let run_ends = decoder.decode().await?;
+        Ok((header, as_primitive_array(run_ends.as_ref()).clone()))
+    }
+
+    /// Read the values of the runs in `runs`.
+    async fn read_values(&self, header: &Header, runs: Range<usize>) -> Result<ArrayRef> {
+        let values = if self.data_type.is_binary_like() {
+            read_binary_array(
+                self.reader,
+                self.data_type,
+                false,
+                header.values_position,
+                header.num_runs,
+                runs.clone(),
+            )
+            .await?
+        } else {
+            PlainDecoder::new(
+                self.reader,
+                self.data_type,
+                header.values_position,
+                header.num_runs,
+            )?
+            .get(runs.clone())
+            .await?
+        };
+        if let Some(position) = header.validity_position {
+            let validity_decoder =
+                PlainDecoder::new(self.reader, &DataType::Boolean, position, header.num_runs)?;
+            let validity = validity_decoder.get(runs).await?;
+            apply_validity(values, as_boolean_array(validity.as_ref()))
+        } else {
+            Ok(values)
+        }
+    }
+
+    /// Decode the rows at the sorted `rows`, by reading the values of the runs they are in.
+    async fn decode_rows(&self, rows: impl Iterator<Item = usize>) -> Result<ArrayRef> {
+        let (header, run_ends) = self.read_run_ends().await?;
+        let ends = run_ends.values();
+        let runs = rows
+            .map(|row| ends.partition_point(|end| *end as usize <= row))
+            .collect::<Vec<_>>();
+        let (Some(first), Some(last)) = (runs.first(), runs.last()) else {
+            return Ok(new_empty_array(self.data_type));
+        };
+        let values = self.read_values(&header, *first..*last + 1).await?;
+        let indices = UInt32Array::from_iter_values(runs.iter().map(|r| (r - first) as u32));
+        Ok(take(values.as_ref(), &indices, None)?)
+    }
+}
+
+#[async_trait]
+impl<'a> Decoder for RleDecoder<'a> {
+    async fn decode(&self) -> Result<ArrayRef> {
+        self.get(0..self.length).await
+    }
+
+    async fn take(&self, indices: &UInt32Array) -> Result<ArrayRef> {
+        if indices.is_empty() {
+            return Ok(new_empty_array(self.data_type));
+        }
+        let max_index = indices.value(indices.len() - 1) as usize;
+        if max_index >= self.length {
+            return Err(Error::IO(format!(
+                "RleDecoder: request([{}]) out of range: [0..{}]",
+                max_index, self.length
+            )));
+        }
+        self.decode_rows(indices.values().iter().map(|i| *i as usize))
+            .await
+    }
+}
+
+#[async_trait]
+impl<'a> AsyncIndex<Range<usize>> for RleDecoder<'a> {
+    type Output = Result<ArrayRef>;
+
+    async fn get(&self, index: Range<usize>) -> Self::Output {
+        if index.end > self.length {
+            return Err(Error::IO(format!(
+                "RleDecoder: request([{}..{}]) out of range: [0..{}]",
+                index.start, index.end, self.length
+            )));
+        }
+        self.decode_rows(index).await
+    }
+}
+
+#[async_trait]
+impl<'a> AsyncIndex<ReadBatchParams> for RleDecoder<'a> {
+    type Output = Result<ArrayRef>;
+
+    async fn get(&self, params: ReadBatchParams) -> Self::Output {
+        match params {
+            ReadBatchParams::Range(r) => self.get(r).await,
+            ReadBatchParams::RangeFull => self.get(0..self.length).await,
+            ReadBatchParams::RangeTo(r) => self.get(0..r.end).await,
+            ReadBatchParams::RangeFrom(r) => self.get(r.start..self.length).await,
+            ReadBatchParams::Indices(indices) => self.take(&indices).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use arrow_array::{
+        BooleanArray, Float64Array, Int32Array, LargeStringArray, StringArray,
+        TimestampMillisecondArray,
+    };
+    use object_store::path::Path;
+
+    use crate::io::ObjectStore;
+
+    async fn test_round_trip(expected: ArrayRef) -> usize {
+        let data_type = expected.data_type().clone();
+        let store = ObjectStore::memory();
+        let path = Path::from("/rle");
+        let mut object_writer = ObjectWriter::new(&store, &path).await.unwrap();
+        object_writer.write_all(b"LANC").await.unwrap();
+        let mut encoder = RleEncoder::new(&mut object_writer, &data_type);
+        let pos = encoder.encode(expected.as_ref()).await.unwrap();
+        object_writer.shutdown().await.unwrap();
+
+        let reader = store.open(&path).await.unwrap();
+        let decoder = RleDecoder::new(reader.as_ref(), &data_type, pos, expected.len());
+        assert_eq!(decoder.decode().await.unwrap().as_ref(), expected.as_ref());
+
+        let len = expected.len();
+        for range in [0..1, 3..40, len - 10..len, 20..20] {
+            assert_eq!(
+                decoder.get(range.clone()).await.unwrap().as_ref(),
+                expected.slice(range.start, range.len()).as_ref()
+            );
+        }
+
+        let indices = UInt32Array::from(vec![0, 1, 2, 30, 31, 77, len as u32 - 1]);
+        assert_eq!(
+            decoder.take(&indices).await.unwrap().as_ref(),
+            take(expected.as_ref(), &indices, None).unwrap().as_ref()
+        );
+        assert!(decoder.get(0..len + 1).await.is_err());
+
+        reader.size().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_encode_decode_rle() {
+        let arrays: Vec<ArrayRef> = vec![
+            Arc::new(BooleanArray::from_iter(
+                (0..100).map(|v| (v % 30 != 0).then_some(v < 55)),
+            )),
+            Arc::new(Int32Array::from_iter_values((0..100).map(|v| v / 7))),
+            Arc::new(Int32Array::from_iter(
+                (0..100).map(|v| (v % 20 > 2).then_some(v / 20)),
+            )),
+            Arc::new(Float64Array::from_iter_values(
+                (0..100).map(|v| (v / 10) as f64),
+            )),
+            Arc::new(TimestampMillisecondArray::from_iter_values(
+                (0..100).map(|v| 1_680_000_000_000 + (v / 25) * 3_600_000),
+            )),
+            Arc::new(StringArray::from_iter((0..100).map(|v| match v / 10 % 3 {
+                0 => Some("us-east"),
+                1 => None,
+                _ => Some(""),
+            }))),
+            Arc::new(LargeStringArray::from_iter_values(
+                (0..100).map(|v| format!("partition-{}", v / 50)),
+            )),
+            StringArray::from_iter_values((0..200).map(|v| format!("key-{}", v / 9)))
+                .slice(50, 100),
+        ];
+        for array in arrays {
+            test_round_trip(array).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rle_single_run() {
+        let array: ArrayRef = Arc::new(Int32Array::from_iter_values(vec![7; 4096]));
+        let size = test_round_trip(array).await;
+        // The magic, one run end, one value and the header.
+        assert_eq!(size, 4 + 4 + 4 + HEADER_SIZE);
+
+        let array: ArrayRef = Arc::new(Int32Array::from(vec![None; 100]));
+        test_round_trip(array).await;
+    }
+
+    #[tokio::test]
+    async fn test_rle_unsupported_type() {
+        let store = ObjectStore::memory();
+        let path = Path::from("/rle");
+        let mut object_writer = ObjectWriter::new(&store, &path).await.unwrap();
+        let data_type = DataType::List(Box::new(arrow_schema::Field::new(
+            "item",
+            DataType::Int32,
+            true,
+        )));
+        assert!(!supports_rle(&data_type));
+        let mut encoder = RleEncoder::new(&mut object_writer, &data_type);
+        let array = Int32Array::from(vec![1]);
+        assert!(matches!(
+            encoder.encode(&array).await,
+            Err(Error::Schema(_))
+        ));
+    }
+}
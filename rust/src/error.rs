@@ -23,6 +23,11 @@ pub enum Error {
     Schema(String),
     IO(String),
     Index(String),
+    /// Another writer has committed the same version of the dataset.
+    CommitConflict {
+        version: u64,
+        message: String,
+    },
     /// Stream early stop
     Stop(),
 }
@@ -36,6 +41,7 @@ impl std::fmt::Display for Error {
             Self::Schema(s) => ("Schema", s.as_str()),
             Self::IO(s) => ("I/O", s.as_str()),
             Self::Index(s) => ("Index", s.as_str()),
+            Self::CommitConflict { message, .. } => ("Commit conflict", message.as_str()),
             Self::Stop() => ("Early stop", ""),
         };
         write!(f, "LanceError({catalog}): {message}")
@@ -66,6 +72,12 @@ impl From<object_store::Error> for Error {
     }
 }
 
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::IO(e.to_string())
+    }
+}
+
 impl From<prost::DecodeError> for Error {
     fn from(e: prost::DecodeError) -> Self {
         Self::IO(e.to_string())
@@ -93,6 +105,7 @@ impl From<Error> for ArrowError {
             Error::IO(err) => Self::IoError(err),
             Error::Schema(err) => Self::SchemaError(err),
             Error::Index(err) => Self::IoError(err),
+            Error::CommitConflict { message, .. } => Self::IoError(message),
             Error::Stop() => Self::IoError("early stop".to_string()),
         }
     }
@@ -17,18 +17,20 @@
 
 use std::collections::BTreeSet;
 
+use serde::{Deserialize, Serialize};
+
 use crate::datatypes::Schema;
 use crate::format::pb;
 
 /// Lance Data File
 ///
 /// A data file is one piece of file storing data.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DataFile {
     /// Relative path of the data file to dataset root.
     pub path: String,
     /// The Ids of fields in this file.
-    fields: Vec<i32>,
+    pub fields: Vec<i32>,
 }
 
 impl DataFile {
@@ -58,6 +60,36 @@ impl From<&pb::DataFile> for DataFile {
     }
 }
 
+/// Deletion File
+///
+/// A deletion file stores the offsets of the rows deleted from one fragment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeletionFile {
+    /// Relative path of the deletion file to the deletion directory.
+    pub path: String,
+
+    /// Number of rows deleted.
+    pub num_deleted_rows: usize,
+}
+
+impl From<&DeletionFile> for pb::DeletionFile {
+    fn from(df: &DeletionFile) -> Self {
+        Self {
+            path: df.path.clone(),
+            num_deleted_rows: df.num_deleted_rows as u64,
+        }
+    }
+}
+
+impl From<&pb::DeletionFile> for DeletionFile {
+    fn from(proto: &pb::DeletionFile) -> Self {
+        Self {
+            path: proto.path.clone(),
+            num_deleted_rows: proto.num_deleted_rows as usize,
+        }
+    }
+}
+
 /// Data fragment.
 ///
 /// A fragment is a set of files which represent the different columns of the same rows.
@@ -69,11 +101,18 @@ pub struct Fragment {
 
     /// Files within the fragment.
     pub files: Vec<DataFile>,
+
+    /// Optional file with the deleted rows of this fragment.
+    pub deletion_file: Option<DeletionFile>,
 }
 
 impl Fragment {
     pub fn new(id: u64) -> Self {
-        Self { id, files: vec![] }
+        Self {
+            id,
+            files: vec![],
+            deletion_file: None,
+        }
     }
 
     /// Create a `Fragment` with one DataFile
@@ -81,9 +120,17 @@ impl Fragment {
         Self {
             id,
             files: vec![DataFile::new(path, schema)],
+            deletion_file: None,
         }
     }
 
+    /// Number of rows deleted from this fragment.
+    pub fn num_deleted_rows(&self) -> usize {
+        self.deletion_file
+            .as_ref()
+            .map_or(0, |f| f.num_deleted_rows)
+    }
+
     /// Get all field IDs from this fragment, sorted.
     pub fn field_ids(&self) -> Vec<i32> {
         BTreeSet::from_iter(self.files.iter().flat_map(|f| f.fields.clone()))
@@ -97,6 +144,7 @@ impl From<&pb::DataFragment> for Fragment {
         Self {
             id: p.id,
             files: p.files.iter().map(DataFile::from).collect(),
+            deletion_file: p.deletion_file.as_ref().map(DeletionFile::from),
         }
     }
 }
@@ -106,6 +154,7 @@ impl From<&Fragment> for pb::DataFragment {
         Self {
             id: f.id,
             files: f.files.iter().map(pb::DataFile::from).collect(),
+            deletion_file: f.deletion_file.as_ref().map(pb::DeletionFile::from),
         }
     }
 }
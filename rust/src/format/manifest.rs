@@ -24,6 +24,7 @@ use prost_types::Timestamp;
 use super::Fragment;
 use crate::datatypes::Schema;
 use crate::format::{pb, ProtoStruct};
+use crate::{Error, Result};
 
 /// Manifest of a dataset
 ///
@@ -43,7 +44,7 @@ pub struct Manifest {
     pub fragments: Arc<Vec<Fragment>>,
 
     /// The file position of the version aux data.
-    pub version_aux_data: usize,
+    pub version_aux_data: Option<usize>,
 
     /// The file position of the index metadata.
     pub index_section: Option<usize>,
@@ -53,6 +54,9 @@ pub struct Manifest {
 
     /// An optional string tag for this version
     pub tag: Option<String>,
+
+    /// The max fragment ID ever used in this dataset.
+    pub max_fragment_id: Option<u64>,
 }
 
 impl Manifest {
@@ -61,13 +65,47 @@ impl Manifest {
             schema: schema.clone(),
             version: 1,
             fragments,
-            version_aux_data: 0,
+            version_aux_data: None,
             index_section: None,
             timestamp_nanos: 0,
             tag: None,
+            max_fragment_id: None,
         }
     }
 
+    /// The ID to use for the next new fragment.
+    pub fn next_fragment_id(&self) -> u64 {
+        self.fragments
+            .iter()
+            .map(|f| f.id)
+            .chain(self.max_fragment_id)
+            .max()
+            .map_or(0, |id| id + 1)
+    }
+
+    /// The max field ID used by the schema and the data files.
+    ///
+    /// The data files might still store the fields that have been dropped from the schema,
+    /// so their IDs must not be reused.
+    pub fn max_field_id(&self) -> i32 {
+        self.fragments
+            .iter()
+            .flat_map(|f| f.files.iter().flat_map(|df| df.fields.iter().copied()))
+            .chain(self.schema.max_field_id())
+            .max()
+            .unwrap_or(-1)
+    }
+
+    /// Update `max_fragment_id` to cover the current fragments.
+    pub fn update_max_fragment_id(&mut self) {
+        self.max_fragment_id = self
+            .fragments
+            .iter()
+            .map(|f| f.id)
+            .chain(self.max_fragment_id)
+            .max();
+    }
+
     /// Return the `timestamp_nanos` value as a Utc DateTime
     pub fn timestamp(&self) -> DateTime<Utc> {
         let nanos = self.timestamp_nanos % 1_000_000_000;
@@ -83,22 +121,25 @@ impl ProtoStruct for Manifest {
     type Proto = pb::Manifest;
 }
 
-impl From<pb::Manifest> for Manifest {
-    fn from(p: pb::Manifest) -> Self {
+impl TryFrom<pb::Manifest> for Manifest {
+    type Error = Error;
+
+    fn try_from(p: pb::Manifest) -> Result<Self> {
         let timestamp_nanos = p.timestamp.map(|ts| {
             let sec = ts.seconds as u128 * 1e9 as u128;
             let nanos = ts.nanos as u128;
             sec + nanos
         });
-        Self {
-            schema: Schema::from(&p.fields),
+        Ok(Self {
+            schema: Schema::try_from(&p.fields)?,
             version: p.version,
             fragments: Arc::new(p.fragments.iter().map(Fragment::from).collect()),
-            version_aux_data: p.version_aux_data as usize,
+            version_aux_data: p.version_aux_data.map(|p| p as usize),
             index_section: p.index_section.map(|i| i as usize),
             timestamp_nanos: timestamp_nanos.unwrap_or(0),
             tag: if p.tag.is_empty() { None } else { Some(p.tag) },
-        }
+            max_fragment_id: p.max_fragment_id,
+        })
     }
 }
 
@@ -119,10 +160,11 @@ impl From<&Manifest> for pb::Manifest {
             version: m.version,
             fragments: m.fragments.iter().map(pb::DataFragment::from).collect(),
             metadata: HashMap::default(),
-            version_aux_data: m.version_aux_data as u64,
+            version_aux_data: m.version_aux_data.map(|p| p as u64),
             index_section: m.index_section.map(|i| i as u64),
             timestamp: timestamp_nanos,
             tag: m.tag.clone().unwrap_or("".to_string()),
+            max_fragment_id: m.max_fragment_id,
         }
     }
 }
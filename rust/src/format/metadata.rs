@@ -18,6 +18,7 @@
 use std::collections::BTreeMap;
 
 use crate::format::{pb, ProtoStruct};
+use crate::{Error, Result};
 
 /// Data File Metadata
 #[derive(Debug, Default, PartialEq)]
@@ -30,6 +31,12 @@ pub struct Metadata {
 
     /// The file position of the manifest block in the file.
     pub manifest_position: Option<usize>,
+
+    /// The file position of the validity table, if any page has nulls.
+    pub validity_table_position: Option<usize>,
+
+    /// The file position of the compression table, if any page is compressed.
+    pub compression_table_position: Option<usize>,
 }
 
 impl ProtoStruct for Metadata {
@@ -42,17 +49,25 @@ impl From<&Metadata> for pb::Metadata {
             batch_offsets: m.batch_offsets.clone(),
             page_table_position: m.page_table_position as u64,
             manifest_position: m.manifest_position.unwrap_or(0) as u64,
+            validity_table_position: m.validity_table_position.unwrap_or(0) as u64,
+            compression_table_position: m.compression_table_position.unwrap_or(0) as u64,
         }
     }
 }
 
-impl From<pb::Metadata> for Metadata {
-    fn from(m: pb::Metadata) -> Self {
-        Self {
+impl TryFrom<pb::Metadata> for Metadata {
+    type Error = Error;
+
+    fn try_from(m: pb::Metadata) -> Result<Self> {
+        Ok(Self {
             batch_offsets: m.batch_offsets.clone(),
             page_table_position: m.page_table_position as usize,
             manifest_position: Some(m.manifest_position as usize),
-        }
+            validity_table_position: Some(m.validity_table_position as usize)
+                .filter(|pos| *pos > 0),
+            compression_table_position: Some(m.compression_table_position as usize)
+                .filter(|pos| *pos > 0),
+        })
     }
 }
 
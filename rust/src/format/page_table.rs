@@ -19,11 +19,14 @@ use arrow_array::builder::Int64Builder;
 use arrow_array::{Array, Int64Array};
 use arrow_schema::DataType;
 use std::collections::BTreeMap;
+use std::ops::Range;
 use tokio::io::AsyncWriteExt;
 
+use crate::encodings::compression::Compression;
 use crate::encodings::plain::PlainDecoder;
 use crate::encodings::Decoder;
 use crate::error::Result;
+use crate::format::pb;
 use crate::io::object_reader::ObjectReader;
 use crate::io::object_writer::ObjectWriter;
 
@@ -31,11 +34,26 @@ use crate::io::object_writer::ObjectWriter;
 pub struct PageInfo {
     pub position: usize,
     pub length: usize,
+
+    /// The position of the validity bitmaps, if the page has nulls.
+    pub validity: Option<usize>,
+
+    /// The compression and the byte range of the compressed page in the file, if the page
+    /// is compressed.
+    ///
+    /// The positions of a compressed page point into the decompressed bytes, as if they
+    /// were stored from the start of this range.
+    pub compressed: Option<(Compression, Range<usize>)>,
 }
 
 impl PageInfo {
     pub fn new(position: usize, length: usize) -> Self {
-        Self { position, length }
+        Self {
+            position,
+            length,
+            validity: None,
+            compressed: None,
+        }
     }
 }
 
@@ -69,10 +87,7 @@ impl PageTable {
                 let batch_length = &arr.value((idx * 2 + 1) as usize);
                 pages.get_mut(&col).unwrap().insert(
                     batch,
-                    PageInfo {
-                        position: *batch_position as usize,
-                        length: *batch_length as usize,
-                    },
+                    PageInfo::new(*batch_position as usize, *batch_length as usize),
                 );
             }
         }
@@ -111,6 +126,134 @@ impl PageTable {
         Ok(pos)
     }
 
+    /// Load the validity table from disk, and set the validity positions of the pages.
+    pub async fn load_validity(
+        &mut self,
+        reader: &dyn ObjectReader,
+        position: usize,
+        num_columns: i32,
+        num_batches: i32,
+    ) -> Result<()> {
+        let length = num_columns * num_batches;
+        let decoder = PlainDecoder::new(reader, &DataType::Int64, position, length as usize)?;
+        let raw_arr = decoder.decode().await?;
+        let arr = raw_arr.as_any().downcast_ref::<Int64Array>().unwrap();
+
+        for (col, c_map) in self.pages.iter_mut() {
+            for (batch, page_info) in c_map.iter_mut() {
+                let validity = arr.value((col * num_batches + batch) as usize);
+                if validity >= 0 {
+                    page_info.validity = Some(validity as usize);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the validity table. Returns `None` without writing anything if none of the pages
+    /// has nulls.
+    pub async fn write_validity(&self, writer: &mut ObjectWriter) -> Result<Option<usize>> {
+        if !self
+            .pages
+            .values()
+            .flat_map(|c_map| c_map.values())
+            .any(|p| p.validity.is_some())
+        {
+            return Ok(None);
+        }
+        let pos = writer.tell();
+        let num_columns = self.pages.keys().max().unwrap() + 1;
+        let num_batches = self
+            .pages
+            .values()
+            .flat_map(|c_map| c_map.keys().max())
+            .max()
+            .unwrap()
+            + 1;
+
+        let mut builder = Int64Builder::with_capacity((num_columns * num_batches) as usize);
+        for col in 0..num_columns {
+            for batch in 0..num_batches {
+                let validity = self.get(col, batch).and_then(|p| p.validity);
+                builder.append_value(validity.map_or(-1, |v| v as i64));
+            }
+        }
+        let arr = builder.finish();
+        writer
+            .write_all(arr.into_data().buffers()[0].as_slice())
+            .await?;
+
+        Ok(Some(pos))
+    }
+
+    /// Load the compression table from disk, and set the compressed ranges of the pages.
+    pub async fn load_compression(
+        &mut self,
+        reader: &dyn ObjectReader,
+        position: usize,
+        num_columns: i32,
+        num_batches: i32,
+    ) -> Result<()> {
+        let length = num_columns * num_batches * 3;
+        let decoder = PlainDecoder::new(reader, &DataType::Int64, position, length as usize)?;
+        let raw_arr = decoder.decode().await?;
+        let arr = raw_arr.as_any().downcast_ref::<Int64Array>().unwrap();
+
+        for (col, c_map) in self.pages.iter_mut() {
+            for (batch, page_info) in c_map.iter_mut() {
+                let idx = (col * num_batches + batch) as usize;
+                if let Some(compression) = Compression::from_proto(arr.value(idx * 3) as i32) {
+                    let start = arr.value(idx * 3 + 1) as usize;
+                    let length = arr.value(idx * 3 + 2) as usize;
+                    page_info.compressed = Some((compression, start..start + length));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the compression table. Returns `None` without writing anything if none of the
+    /// pages is compressed.
+    pub async fn write_compression(&self, writer: &mut ObjectWriter) -> Result<Option<usize>> {
+        if !self
+            .pages
+            .values()
+            .flat_map(|c_map| c_map.values())
+            .any(|p| p.compressed.is_some())
+        {
+            return Ok(None);
+        }
+        let pos = writer.tell();
+        let num_columns = self.pages.keys().max().unwrap() + 1;
+        let num_batches = self
+            .pages
+            .values()
+            .flat_map(|c_map| c_map.keys().max())
+            .max()
+            .unwrap()
+            + 1;
+
+        let mut builder = Int64Builder::with_capacity((num_columns * num_batches * 3) as usize);
+        for col in 0..num_columns {
+            for batch in 0..num_batches {
+                match self.get(col, batch).and_then(|p| p.compressed.as_ref()) {
+                    Some((compression, range)) => {
+                        builder.append_value(pb::Compression::from(*compression) as i64);
+                        builder.append_value(range.start as i64);
+                        builder.append_value(range.len() as i64);
+                    }
+                    None => builder.append_slice(&[0, -1, -1]),
+                }
+            }
+        }
+        let arr = builder.finish();
+        writer
+            .write_all(arr.into_data().buffers()[0].as_slice())
+            .await?;
+
+        Ok(Some(pos))
+    }
+
     /// Set page lookup info for a page identified by `(column, batch)` pair.
     pub fn set(&mut self, column: i32, batch: i32, page_info: PageInfo) {
         self.pages
@@ -21,7 +21,7 @@
 use std::any::Any;
 use std::sync::Arc;
 
-use arrow_array::{Float32Array, RecordBatch};
+use arrow_array::{ArrayRef, Float32Array, RecordBatch};
 use async_trait::async_trait;
 
 pub mod flat;
@@ -31,6 +31,7 @@ mod pq;
 
 use super::IndexParams;
 use crate::{
+    arrow::ArrowFloatType,
     utils::distance::{cosine::cosine_distance, l2::l2_distance},
     Error, Result,
 };
@@ -39,8 +40,8 @@ use crate::{
 #[derive(Debug, Clone)]
 pub struct Query {
     pub column: String,
-    /// The vector to be searched.
-    pub key: Arc<Float32Array>,
+    /// The vector to be searched, of the same float type as the vector column.
+    pub key: ArrayRef,
     /// Top k results to return.
     pub k: usize,
     /// The number of probs to load and search.
@@ -73,8 +74,8 @@ pub trait VectorIndex {
     /// ]);
     /// ```
     ///
-    /// *WARNINGS*:
-    ///  - Only supports `f32` now. Will add f64/f16 later.
+    /// The vector column can be of `bf16`, `f16`, `f32` or `f64` values; the scores are
+    /// always `f32`.
     async fn search(&self, query: &Query) -> Result<RecordBatch>;
 }
 
@@ -85,14 +86,20 @@ pub enum MetricType {
     Cosine,
 }
 
+/// The distance function over the vectors of the float type `T`.
+pub type DistanceFunc<T> = dyn Fn(
+        &<T as ArrowFloatType>::ArrayType,
+        &<T as ArrowFloatType>::ArrayType,
+        usize,
+    ) -> Result<Arc<Float32Array>>
+    + Send
+    + Sync;
+
 impl MetricType {
-    pub fn func(
-        &self,
-    ) -> Arc<dyn Fn(&Float32Array, &Float32Array, usize) -> Result<Arc<Float32Array>> + Send + Sync>
-    {
+    pub fn func<T: ArrowFloatType>(&self) -> Arc<DistanceFunc<T>> {
         match self {
-            Self::L2 => Arc::new(l2_distance),
-            Self::Cosine => Arc::new(cosine_distance),
+            Self::L2 => Arc::new(l2_distance::<T>),
+            Self::Cosine => Arc::new(cosine_distance::<T>),
         }
     }
 }
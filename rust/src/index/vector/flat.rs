@@ -17,15 +17,20 @@
 
 //! Flat Vector Index.
 
-use arrow::array::as_primitive_array;
-use arrow_array::{cast::as_struct_array, ArrayRef, RecordBatch, StructArray};
+use std::sync::Arc;
+
+use arrow_array::{
+    cast::as_struct_array,
+    types::{Float16Type, Float32Type, Float64Type},
+    Array, ArrayRef, FixedSizeListArray, Float32Array, RecordBatch, StructArray,
+};
 use arrow_ord::sort::sort_to_indices;
 use arrow_schema::{DataType, Field as ArrowField};
 use arrow_select::{concat::concat_batches, take::take};
 use async_trait::async_trait;
 use futures::stream::{repeat_with, Stream, StreamExt, TryStreamExt};
 
-use super::{Query, VectorIndex};
+use super::{MetricType, Query, VectorIndex};
 use crate::arrow::*;
 use crate::dataset::Dataset;
 use crate::{Error, Result};
@@ -78,11 +83,16 @@ pub async fn flat_search(
                     Error::Schema(format!("column {} does not exist in dataset", query.column))
                 })?
                 .clone();
-            let flatten_vectors = as_fixed_size_list_array(vectors.as_ref()).values();
             let scores = tokio::task::spawn_blocking(move || {
-                mt.func()(&k, as_primitive_array(flatten_vectors.as_ref()), k.len()).unwrap()
+                let vectors = as_fixed_size_list_array(vectors.as_ref());
+                match FloatType::try_from(k.data_type())? {
+                    FloatType::BFloat16 => compute_scores::<BFloat16Type>(&k, vectors, mt),
+                    FloatType::Float16 => compute_scores::<Float16Type>(&k, vectors, mt),
+                    FloatType::Float32 => compute_scores::<Float32Type>(&k, vectors, mt),
+                    FloatType::Float64 => compute_scores::<Float64Type>(&k, vectors, mt),
+                }
             })
-            .await? as ArrayRef;
+            .await?? as ArrayRef;
 
             // TODO: use heap
             let indices = sort_to_indices(&scores, None, Some(query.k))?;
@@ -106,6 +116,31 @@ pub async fn flat_search(
     Ok(as_struct_array(&selected_arr).into())
 }
 
+/// Compute the distances from the query `key` to the `vectors`, of the float type `T`.
+fn compute_scores<T: ArrowFloatType>(
+    key: &dyn Array,
+    vectors: &FixedSizeListArray,
+    metric_type: MetricType,
+) -> Result<Arc<Float32Array>> {
+    let key = T::ArrayType::try_from_array(key)?;
+    let dimension = vectors.value_length() as usize;
+    if key.as_slice().len() != dimension {
+        return Err(Error::IO(format!(
+            "Query vector has {} dimensions, but the vectors have {dimension}",
+            key.as_slice().len()
+        )));
+    }
+    // The list array can be a slice, only take the values in it.
+    let values = vectors
+        .values()
+        .slice(vectors.value_offset(0) as usize, vectors.len() * dimension);
+    metric_type.func::<T>()(
+        &key,
+        &T::ArrayType::try_from_array(values.as_ref())?,
+        dimension,
+    )
+}
+
 #[async_trait]
 impl VectorIndex for FlatIndex<'_> {
     /// Search the flat index.
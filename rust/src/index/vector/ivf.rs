@@ -24,8 +24,9 @@ use arrow_arith::arithmetic::subtract_dyn;
 use arrow_array::builder::Float32Builder;
 use arrow_array::{
     cast::{as_primitive_array, as_struct_array},
+    types::{Float16Type, Float32Type, Float64Type},
     Array, ArrayRef, BooleanArray, FixedSizeListArray, Float32Array, RecordBatch, StructArray,
-    UInt32Array,
+    UInt32Array, UInt64Array, UInt8Array,
 };
 use arrow_ord::sort::sort_to_indices;
 use arrow_schema::{DataType, Field as ArrowField, Schema as ArrowSchema};
@@ -53,7 +54,7 @@ use crate::io::{
     read_message_from_buf, read_metadata_offset,
 };
 use crate::{
-    dataset::{scanner::Scanner, Dataset, ROW_ID},
+    dataset::{scanner::Scanner, Dataset, RowIdRemap, ROW_ID},
     index::{pb, pb::vector_index_stage::Stage, IndexBuilder, IndexType},
 };
 use crate::{Error, Result};
@@ -78,30 +79,7 @@ pub struct IvfPQIndex<'a> {
 impl<'a> IvfPQIndex<'a> {
     /// Open the IvfPQ index on dataset, specified by the index `name`.
     pub async fn new(dataset: &'a Dataset, uuid: &str) -> Result<IvfPQIndex<'a>> {
-        let index_dir = dataset.indices_dir().child(uuid);
-        let index_file = index_dir.child(INDEX_FILE_NAME);
-
-        let object_store = dataset.object_store();
-        let reader = object_store.open(&index_file).await?;
-
-        let file_size = reader.size().await?;
-        let prefetch_size = object_store.prefetch_size();
-        let begin = if file_size < prefetch_size {
-            0
-        } else {
-            file_size - prefetch_size
-        };
-        let tail_bytes = reader.get_range(begin..file_size).await?;
-        let metadata_pos = read_metadata_offset(&tail_bytes)?;
-        let proto: pb::Index = if metadata_pos < file_size - tail_bytes.len() {
-            // We have not read the metadata bytes yet.
-            read_message(reader.as_ref(), metadata_pos).await?
-        } else {
-            let offset = tail_bytes.len() - (file_size - metadata_pos);
-            read_message_from_buf(&tail_bytes.slice(offset..))?
-        };
-        let index_metadata = IvfPQIndexMetadata::try_from(&proto)?;
-
+        let (reader, index_metadata) = open_index_file(dataset, uuid).await?;
         Ok(Self {
             reader,
             ivf: index_metadata.ivf,
@@ -137,12 +115,14 @@ impl<'a> IvfPQIndex<'a> {
 #[async_trait]
 impl VectorIndex for IvfPQIndex<'_> {
     async fn search(&self, query: &Query) -> Result<RecordBatch> {
+        // The IVF centroids and the PQ codebooks are f32, so is the query vector.
+        let key = &cast_float_array::<Float32Type>(query.key.as_ref())?;
         let partition_ids = self
             .ivf
-            .find_partitions(&query.key, query.nprobs, self.metric_type)?;
+            .find_partitions(key, query.nprobs, self.metric_type)?;
         let candidates = stream::iter(partition_ids.values())
             .then(|part_id| async move {
-                self.search_in_partition(*part_id as usize, &query.key, query.k)
+                self.search_in_partition(*part_id as usize, key, query.k)
                     .await
             })
             .collect::<Vec<_>>()
@@ -167,6 +147,99 @@ impl VectorIndex for IvfPQIndex<'_> {
     }
 }
 
+/// Open the index file of the index `uuid`, and read its [IvfPQIndexMetadata].
+async fn open_index_file<'a>(
+    dataset: &'a Dataset,
+    uuid: &str,
+) -> Result<(Box<dyn ObjectReader + 'a>, IvfPQIndexMetadata)> {
+    let index_dir = dataset.indices_dir().child(uuid);
+    let index_file = index_dir.child(INDEX_FILE_NAME);
+
+    let object_store = dataset.object_store();
+    let reader = object_store.open(&index_file).await?;
+
+    let file_size = reader.size().await?;
+    let prefetch_size = object_store.prefetch_size();
+    let begin = if file_size < prefetch_size {
+        0
+    } else {
+        file_size - prefetch_size
+    };
+    let tail_bytes = reader.get_range(begin..file_size).await?;
+    let metadata_pos = read_metadata_offset(&tail_bytes)?;
+    let proto: pb::Index = if metadata_pos < file_size - tail_bytes.len() {
+        // We have not read the metadata bytes yet.
+        read_message(reader.as_ref(), metadata_pos).await?
+    } else {
+        let offset = tail_bytes.len() - (file_size - metadata_pos);
+        read_message_from_buf(&tail_bytes.slice(offset..))?
+    };
+    let index_metadata = IvfPQIndexMetadata::try_from(&proto)?;
+    Ok((reader, index_metadata))
+}
+
+/// Rewrite the IVF_PQ index `old_uuid` to a new index `new_uuid`, with the row IDs
+/// remapped by `mapping`.
+///
+/// The row IDs that are not in `mapping` are kept as is,
+/// and the rows mapped to `None` are removed from the index.
+pub(crate) async fn remap_index_file(
+    dataset: &Dataset,
+    old_uuid: &str,
+    new_uuid: &str,
+    mapping: &RowIdRemap,
+) -> Result<()> {
+    let (reader, mut metadata) = open_index_file(dataset, old_uuid).await?;
+
+    let path = dataset.indices_dir().child(new_uuid).child(INDEX_FILE_NAME);
+    let mut writer = dataset.object_store().create(&path).await?;
+
+    let num_sub_vectors = metadata.pq.num_sub_vectors;
+    let mut ivf = Ivf::new(metadata.ivf.centroids.clone());
+    for (offset, length) in metadata.ivf.offsets.iter().zip(metadata.ivf.lengths.iter()) {
+        let mut pq_codes: Vec<u8> = vec![];
+        let mut row_ids: Vec<u64> = vec![];
+        if *length > 0 {
+            let pq_index = PQIndex::load(
+                reader.as_ref(),
+                metadata.pq.as_ref(),
+                metadata.metric_type,
+                *offset,
+                *length as usize,
+            )
+            .await?;
+            let codes = pq_index.code.values();
+            for (i, row_id) in pq_index.row_ids.values().iter().enumerate() {
+                let new_row_id = match mapping.get(*row_id) {
+                    Some(Some(new_row_id)) => *new_row_id,
+                    Some(None) => continue,
+                    None => *row_id,
+                };
+                pq_codes.extend_from_slice(&codes[i * num_sub_vectors..(i + 1) * num_sub_vectors]);
+                row_ids.push(new_row_id);
+            }
+        }
+
+        ivf.add_partition(writer.tell(), row_ids.len() as u32);
+        if !row_ids.is_empty() {
+            writer
+                .write_plain_encoded_array(&UInt8Array::from(pq_codes))
+                .await?;
+            writer
+                .write_plain_encoded_array(&UInt64Array::from(row_ids))
+                .await?;
+        }
+    }
+    metadata.ivf = ivf;
+
+    let metadata = pb::Index::try_from(&metadata)?;
+    let pos = writer.write_protobuf(&metadata).await?;
+    writer.write_magics(pos).await?;
+    writer.shutdown().await?;
+
+    Ok(())
+}
+
 /// Ivf PQ index metadata.
 ///
 /// It contains the on-disk data for a IVF PQ index.
@@ -279,18 +352,23 @@ impl TryFrom<&pb::Index> for IvfPQIndexMetadata {
     }
 }
 
-fn compute_residual(
+/// Compute the residual vectors of the vectors of the float type `T`.
+///
+/// The residual vectors are `f32`, the same as the centroids.
+fn compute_residual<T: ArrowFloatType>(
     centroids: Arc<FixedSizeListArray>,
     vector_array: &FixedSizeListArray,
     partition_ids: &UInt32Array,
 ) -> Result<ArrayRef> {
     let mut residual_builder = Float32Builder::new();
     for i in 0..vector_array.len() {
-        let vector = vector_array.value(i);
-        let centroids = centroids.value(partition_ids.value(i) as usize);
-        let residual_vector = subtract_dyn(vector.as_ref(), centroids.as_ref())?;
-        let residual_float32: &Float32Array = as_primitive_array(residual_vector.as_ref());
-        residual_builder.append_slice(residual_float32.values());
+        let vector = T::ArrayType::try_from_array(vector_array.value(i).as_ref())?;
+        let centroid = centroids.value(partition_ids.value(i) as usize);
+        let centroid: &Float32Array = as_primitive_array(centroid.as_ref());
+        for (x, c) in vector.as_slice().iter().zip(centroid.values().iter()) {
+            let x: f64 = (*x).into();
+            residual_builder.append_value(x as f32 - c);
+        }
     }
     let values = residual_builder.finish();
     Ok(Arc::new(FixedSizeListArray::try_new(
@@ -299,6 +377,24 @@ fn compute_residual(
     )?))
 }
 
+/// Assign each vector, of the float type `T`, to the partition of its closest centroid.
+fn compute_partitions<T: ArrowFloatType>(
+    centroids: &dyn Array,
+    vectors: &FixedSizeListArray,
+    metric_type: MetricType,
+) -> Result<Vec<u32>> {
+    // The centroids are f32, and converted to the float type of the vectors.
+    let centroids = cast_float_array::<T>(centroids)?;
+    let dimension = vectors.value_length() as usize;
+    let dist_func = metric_type.func::<T>();
+    (0..vectors.len())
+        .map(|idx| {
+            let vector = T::ArrayType::try_from_array(vectors.value(idx).as_ref())?;
+            Ok(argmin(dist_func(&vector, &centroids, dimension)?.as_ref()).unwrap())
+        })
+        .collect()
+}
+
 /// Ivf Model
 #[derive(Debug)]
 struct Ivf {
@@ -343,7 +439,7 @@ impl Ivf {
                 self.dimension()
             )));
         }
-        let dist_func = metric_type.func();
+        let dist_func = metric_type.func::<Float32Type>();
         let centroid_values = self.centroids.values();
         let distances = dist_func(
             query,
@@ -379,18 +475,23 @@ impl Ivf {
                     Error::IO(format!("Dataset does not have column {column_name}"))
                 })?;
                 let vectors = as_fixed_size_list_array(arr).clone();
-                // let vec = vectors.clone();
-                let values = self.centroids.values();
-                let centroids = as_primitive_array(values.as_ref()).clone();
+                let centroids = self.centroids.values().clone();
                 let partition_ids = tokio::task::spawn_blocking(move || {
-                    let dist_func = metric_type.func();
-                    (0..vectors.len())
-                        .map(|idx| {
-                            let arr = vectors.value(idx);
-                            let f: &Float32Array = as_primitive_array(&arr);
-                            Ok(argmin(dist_func(f, &centroids, f.len())?.as_ref()).unwrap())
-                        })
-                        .collect::<Result<Vec<u32>>>()
+                    let centroids = centroids.as_ref();
+                    match FloatType::try_from(&vectors.value_type())? {
+                        FloatType::BFloat16 => {
+                            compute_partitions::<BFloat16Type>(centroids, &vectors, metric_type)
+                        }
+                        FloatType::Float16 => {
+                            compute_partitions::<Float16Type>(centroids, &vectors, metric_type)
+                        }
+                        FloatType::Float32 => {
+                            compute_partitions::<Float32Type>(centroids, &vectors, metric_type)
+                        }
+                        FloatType::Float64 => {
+                            compute_partitions::<Float64Type>(centroids, &vectors, metric_type)
+                        }
+                    }
                 })
                 .await??;
                 let partition_column = Arc::new(UInt32Array::from(partition_ids));
@@ -412,11 +513,30 @@ impl Ivf {
                 let vector = batch.column_by_name(column_name).unwrap().clone();
                 let partition_ids = batch.column_by_name(PARTITION_ID_COLUMN).unwrap().clone();
                 let residual = tokio::task::spawn_blocking(move || {
-                    compute_residual(
-                        centorids.clone(),
-                        as_fixed_size_list_array(vector.as_ref()),
-                        as_primitive_array(partition_ids.as_ref()),
-                    )
+                    let vectors = as_fixed_size_list_array(vector.as_ref());
+                    let partition_ids = as_primitive_array(partition_ids.as_ref());
+                    match FloatType::try_from(&vectors.value_type())? {
+                        FloatType::BFloat16 => compute_residual::<BFloat16Type>(
+                            centorids.clone(),
+                            vectors,
+                            partition_ids,
+                        ),
+                        FloatType::Float16 => compute_residual::<Float16Type>(
+                            centorids.clone(),
+                            vectors,
+                            partition_ids,
+                        ),
+                        FloatType::Float32 => compute_residual::<Float32Type>(
+                            centorids.clone(),
+                            vectors,
+                            partition_ids,
+                        ),
+                        FloatType::Float64 => compute_residual::<Float64Type>(
+                            centorids.clone(),
+                            vectors,
+                            partition_ids,
+                        ),
+                    }
                 })
                 .await??;
                 let residual_schema = Arc::new(ArrowSchema::new(vec![
@@ -540,21 +660,21 @@ impl<'a> IvfPqIndexBuilder<'a> {
     fn sanity_check(&self) -> Result<()> {
         // Step 1. Sanity check
         let Some(field) = self.dataset.schema().field(&self.column) else {
-    return Err(Error::IO(format!(
-        "Building index: column {} does not exist in dataset: {:?}",
-        self.column, self.dataset
-    )));
-};
+            return Err(Error::IO(format!(
+                "Building index: column {} does not exist in dataset: {:?}",
+                self.column, self.dataset
+            )));
+        };
         if let DataType::FixedSizeList(elem_type, _) = field.data_type() {
-            if !matches!(elem_type.data_type(), DataType::Float32) {
+            if FloatType::try_from(elem_type.data_type()).is_err() {
                 return Err(
             Error::Index(
-                format!("VectorIndex requires the column data type to be fixed size list of float32s, got {}",
+                format!("VectorIndex requires the column data type to be fixed size list of floats, got {}",
                 elem_type.data_type())));
             }
         } else {
             return Err(Error::Index(
-        format!("VectorIndex requires the column data type to be fixed size list of float32s, got {}",
+        format!("VectorIndex requires the column data type to be fixed size list of floats, got {}",
         field.data_type())));
         }
         Ok(())
@@ -708,9 +828,52 @@ async fn train_kmeans_model(
     let arrays = arr_list.iter().map(|l| l.as_ref()).collect::<Vec<_>>();
 
     let all_vectors = concat(&arrays)?;
-    let values: &Float32Array = as_primitive_array(&all_vectors);
-    let centroids =
-        super::kmeans::train_kmeans(values, dimension, k, max_iterations, rng, metric_type).await?;
+    let centroids = match FloatType::try_from(all_vectors.data_type())? {
+        FloatType::BFloat16 => {
+            super::kmeans::train_kmeans::<BFloat16Type>(
+                &FloatArray::try_from_array(all_vectors.as_ref())?,
+                dimension,
+                k,
+                max_iterations,
+                rng,
+                metric_type,
+            )
+            .await?
+        }
+        FloatType::Float16 => {
+            super::kmeans::train_kmeans::<Float16Type>(
+                &FloatArray::try_from_array(all_vectors.as_ref())?,
+                dimension,
+                k,
+                max_iterations,
+                rng,
+                metric_type,
+            )
+            .await?
+        }
+        FloatType::Float32 => {
+            super::kmeans::train_kmeans::<Float32Type>(
+                &FloatArray::try_from_array(all_vectors.as_ref())?,
+                dimension,
+                k,
+                max_iterations,
+                rng,
+                metric_type,
+            )
+            .await?
+        }
+        FloatType::Float64 => {
+            super::kmeans::train_kmeans::<Float64Type>(
+                &FloatArray::try_from_array(all_vectors.as_ref())?,
+                dimension,
+                k,
+                max_iterations,
+                rng,
+                metric_type,
+            )
+            .await?
+        }
+    };
     Ok(Arc::new(FixedSizeListArray::try_new(
         centroids,
         dimension as i32,
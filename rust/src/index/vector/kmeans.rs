@@ -15,27 +15,30 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use arrow_array::{
-    builder::Float32Builder, cast::as_primitive_array, types::Float32Type, Array, Float32Array,
-};
+use arrow_array::{builder::Float32Builder, types::Float32Type, Float32Array};
 use rand::{seq::IteratorRandom, Rng};
 
+use crate::arrow::{cast_float_array, ArrowFloatType, FloatArray};
+
 use crate::index::vector::MetricType;
 use crate::{
     utils::kmeans::{KMeans, KMeansParams},
     Result,
 };
 
-/// Train KMeans model and returns the centroids of each cluster.
-pub async fn train_kmeans(
-    array: &Float32Array,
+/// Train KMeans model over the vectors of the float type `T`, and returns the centroids of each cluster.
+///
+/// The centroids are `f32`, so only the sampled vectors are converted to `f32` for training.
+pub async fn train_kmeans<T: ArrowFloatType>(
+    array: &T::ArrayType,
     dimension: usize,
     k: usize,
     max_iterations: u32,
     mut rng: impl Rng,
     metric_type: MetricType,
 ) -> Result<Float32Array> {
-    let num_rows = array.len() / dimension;
+    let values = array.as_slice();
+    let num_rows = values.len() / dimension;
     if num_rows < k {
         return Err(crate::Error::Index(format!(
             "KMeans: can not train {k} centroids with {num_rows} vectors, choose a smaller K (< {num_rows}) instead"
@@ -46,7 +49,7 @@ pub async fn train_kmeans(
         println!(
             "Sample {} out of {} to train kmeans of {} dim, {} clusters",
             256 * k,
-            array.len() / dimension,
+            num_rows,
             dimension,
             k,
         );
@@ -54,12 +57,14 @@ pub async fn train_kmeans(
         let chosen = (0..num_rows).choose_multiple(&mut rng, sample_size);
         let mut builder = Float32Builder::with_capacity(sample_size * dimension);
         for idx in chosen.iter() {
-            let s = array.slice(idx * dimension, dimension);
-            builder.append_slice(as_primitive_array::<Float32Type>(s.as_ref()).values());
+            for v in &values[idx * dimension..(idx + 1) * dimension] {
+                let v: f64 = (*v).into();
+                builder.append_value(v as f32);
+            }
         }
         builder.finish()
     } else {
-        array.clone()
+        cast_float_array::<Float32Type>(array.clone().into_array_ref().as_ref())?
     };
 
     let params = KMeansParams {
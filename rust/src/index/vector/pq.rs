@@ -15,8 +15,8 @@
 use std::sync::Arc;
 
 use arrow_array::{
-    builder::Float32Builder, cast::as_primitive_array, Array, FixedSizeListArray, Float32Array,
-    RecordBatch,
+    builder::Float32Builder, cast::as_primitive_array, types::Float32Type, Array,
+    FixedSizeListArray, Float32Array, RecordBatch,
 };
 use arrow_array::{ArrayRef, UInt64Array, UInt8Array};
 use arrow_ord::sort::sort_to_indices;
@@ -99,7 +99,7 @@ impl<'a> PQIndex<'a> {
         for i in 0..self.num_sub_vectors {
             let from = key.slice(i * sub_vector_length, sub_vector_length);
             let subvec_centroids = self.pq.centroids(i);
-            let distances = l2_distance(
+            let distances = l2_distance::<Float32Type>(
                 as_primitive_array(&from),
                 &subvec_centroids,
                 sub_vector_length,
@@ -300,7 +300,7 @@ impl ProductQuantizer {
             .zip(stream::iter(all_centroids))
             .map(|(vec, centroid)| async move {
                 tokio::task::spawn_blocking(move || {
-                    let dist_func = metric_type.func();
+                    let dist_func = metric_type.func::<Float32Type>();
                     // TODO Use tiling to improve cache efficiency.
                     (0..vec.len())
                         .map(|i| {
@@ -359,7 +359,7 @@ impl ProductQuantizer {
             // Centroids for one sub vector.
             let values = sub_vec.values();
             let flatten_array: &Float32Array = as_primitive_array(&values);
-            let centroids = train_kmeans(
+            let centroids = train_kmeans::<Float32Type>(
                 flatten_array,
                 sub_vector_dimension,
                 num_centroids,
@@ -439,7 +439,6 @@ fn divide_to_subvectors(array: &FixedSizeListArray, m: i32) -> Vec<Arc<FixedSize
 mod tests {
 
     use super::*;
-    use arrow_array::types::Float32Type;
 
     #[test]
     fn test_divide_to_subvectors() {
@@ -27,6 +27,8 @@ use prost::bytes::Bytes;
 use prost::Message;
 use tokio::io::{AsyncWrite, AsyncWriteExt};
 
+pub mod commit;
+pub(crate) mod deletion;
 pub(crate) mod exec;
 pub mod local;
 pub mod object_reader;
@@ -37,6 +39,7 @@ mod writer;
 
 use crate::format::{ProtoStruct, INDEX_MAGIC, MAGIC};
 
+pub use self::commit::CommitHandler;
 pub use self::object_store::ObjectStore;
 pub use reader::read_manifest;
 pub use reader::FileReader;
@@ -76,11 +79,14 @@ pub fn read_message_from_buf<M: Message + Default>(buf: &Bytes) -> Result<M> {
 }
 
 /// Read a Protobuf-backed struct from a buffer.
-pub fn read_struct_from_buf<M: Message + Default, T: ProtoStruct<Proto = M> + From<M>>(
+pub fn read_struct_from_buf<
+    M: Message + Default,
+    T: ProtoStruct<Proto = M> + TryFrom<M, Error = crate::Error>,
+>(
     buf: &Bytes,
-) -> Result<T> {
+) -> crate::Result<T> {
     let msg: M = read_message_from_buf(buf)?;
-    Ok(T::from(msg))
+    T::try_from(msg)
 }
 
 /// Parameter to be used to read a batch.
@@ -0,0 +1,179 @@
+// Copyright 2023 Lance Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Commit protocol of the dataset versions.
+//!
+//! A new version is committed by writing its manifest to a staging path first, and then
+//! moving it to `_versions/{version}.manifest`. The move must fail if the version already
+//! exists, so two writers that start from the same version can not both commit the next one.
+
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use object_store::path::Path;
+
+use super::ObjectStore;
+use crate::{Error, Result};
+
+/// Commit a staged manifest file as a dataset version.
+#[async_trait]
+pub trait CommitHandler: Debug + Send + Sync {
+    /// Move the manifest at `staging_path` to `path`, the manifest path of `version`.
+    ///
+    /// Returns [Error::CommitConflict] if `path` already exists.
+    async fn commit(
+        &self,
+        object_store: &ObjectStore,
+        staging_path: &Path,
+        path: &Path,
+        version: u64,
+    ) -> Result<()>;
+}
+
+/// Commit with the atomic rename-if-not-exists of the object store.
+///
+/// It is supported by the local file system, via hard links, and by the in-memory store.
+#[derive(Debug, Default)]
+pub struct RenameCommitHandler;
+
+#[async_trait]
+impl CommitHandler for RenameCommitHandler {
+    async fn commit(
+        &self,
+        object_store: &ObjectStore,
+        staging_path: &Path,
+        path: &Path,
+        version: u64,
+    ) -> Result<()> {
+        match object_store
+            .inner
+            .rename_if_not_exists(staging_path, path)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(object_store::Error::AlreadyExists { .. }) => {
+                object_store.inner.delete(staging_path).await?;
+                Err(conflict(version))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Commit by checking the existence of the version first, which is not atomic.
+///
+/// Two concurrent writers can both commit the same version, and one of them is lost. It is
+/// only safe with a single writer, so it must be set explicitly on the object stores without
+/// conditional writes, i.e., S3. Otherwise, use a [CommitHandler] backed by a lock service.
+#[derive(Debug, Default)]
+pub struct UnsafeCommitHandler;
+
+#[async_trait]
+impl CommitHandler for UnsafeCommitHandler {
+    async fn commit(
+        &self,
+        object_store: &ObjectStore,
+        staging_path: &Path,
+        path: &Path,
+        version: u64,
+    ) -> Result<()> {
+        if object_store.exists(path).await? {
+            object_store.inner.delete(staging_path).await?;
+            return Err(conflict(version));
+        }
+        object_store.inner.rename(staging_path, path).await?;
+        Ok(())
+    }
+}
+
+/// Refuse to commit, the default of the object stores without conditional writes, i.e., S3.
+///
+/// Set a [CommitHandler] explicitly to write to these object stores: [UnsafeCommitHandler]
+/// for a single writer, or one backed by a lock service for concurrent writers.
+#[derive(Debug, Default)]
+pub struct UnsupportedCommitHandler;
+
+#[async_trait]
+impl CommitHandler for UnsupportedCommitHandler {
+    async fn commit(
+        &self,
+        _object_store: &ObjectStore,
+        _staging_path: &Path,
+        _path: &Path,
+        version: u64,
+    ) -> Result<()> {
+        Err(Error::IO(format!(
+            "Can not commit version {version}: the object store does not support atomic \
+            commits, set a CommitHandler explicitly, i.e., UnsafeCommitHandler for a single writer"
+        )))
+    }
+}
+
+fn conflict(version: u64) -> Error {
+    Error::CommitConflict {
+        version,
+        message: format!("version {version} has been committed by another writer"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rename_commit_conflict() {
+        let object_store = ObjectStore::memory();
+        let path = Path::from("_versions/1.manifest");
+        for (i, expected_ok) in [true, false].into_iter().enumerate() {
+            let staging_path = Path::from(format!("_versions/1.manifest-{i}.tmp"));
+            object_store
+                .inner
+                .put(&staging_path, vec![i as u8].into())
+                .await
+                .unwrap();
+            let result = RenameCommitHandler
+                .commit(&object_store, &staging_path, &path, 1)
+                .await;
+            assert_eq!(result.is_ok(), expected_ok);
+            if !expected_ok {
+                assert!(matches!(
+                    result,
+                    Err(Error::CommitConflict { version: 1, .. })
+                ));
+            }
+            assert!(!object_store.exists(&staging_path).await.unwrap());
+        }
+
+        // The first commit wins.
+        let bytes = object_store.inner.get(&path).await.unwrap().bytes().await;
+        assert_eq!(bytes.unwrap().as_ref(), &[0]);
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_commit() {
+        let object_store = ObjectStore::memory();
+        let staging_path = Path::from("_versions/1.manifest-0.tmp");
+        let path = Path::from("_versions/1.manifest");
+        object_store
+            .inner
+            .put(&staging_path, vec![0].into())
+            .await
+            .unwrap();
+        let result = UnsupportedCommitHandler
+            .commit(&object_store, &staging_path, &path, 1)
+            .await;
+        assert!(matches!(result, Err(Error::IO(msg)) if msg.contains("UnsafeCommitHandler")));
+        assert!(!object_store.exists(&path).await.unwrap());
+    }
+}
@@ -0,0 +1,278 @@
+// Copyright 2023 Lance Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deletion vectors.
+//!
+//! Deleted rows are not removed from the data files. Instead, the row offsets within a fragment
+//! are recorded in a per-fragment deletion file, and the readers skip these rows.
+
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use arrow_array::{cast::as_primitive_array, BooleanArray, RecordBatch, UInt32Array, UInt64Array};
+use arrow_schema::DataType;
+use arrow_select::filter::filter_record_batch;
+use object_store::path::Path;
+use tokio::sync::OnceCell;
+
+use crate::dataset::{Dataset, ROW_ID};
+use crate::encodings::{plain::PlainDecoder, Decoder};
+use crate::io::ObjectStore;
+use crate::{Error, Result};
+
+/// The offsets of the deleted rows in one fragment.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeletionVector(BTreeSet<u32>);
+
+impl DeletionVector {
+    /// Number of deleted rows.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns true if the row at `offset` has been deleted.
+    pub fn contains(&self, offset: u32) -> bool {
+        self.0.contains(&offset)
+    }
+
+    /// Iterate the deleted offsets in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.0.iter().copied()
+    }
+
+    /// Filter out the deleted rows from a batch.
+    ///
+    /// The rows in `batch` are the continuous rows starting from `start_offset` in the fragment.
+    pub fn filter_batch(&self, batch: &RecordBatch, start_offset: u32) -> Result<RecordBatch> {
+        let end_offset = start_offset + batch.num_rows() as u32;
+        if self.0.range(start_offset..end_offset).next().is_none() {
+            return Ok(batch.clone());
+        }
+        let mask = BooleanArray::from_iter(
+            (start_offset..end_offset).map(|offset| Some(!self.contains(offset))),
+        );
+        Ok(filter_record_batch(batch, &mask)?)
+    }
+
+    /// Map the positions of the rows that are not deleted to their offsets in the fragment.
+    ///
+    /// `positions` must be sorted in ascending order.
+    pub fn physical_offsets(&self, positions: &[u32]) -> Vec<u32> {
+        let mut deleted = self.0.iter().peekable();
+        let mut num_skipped = 0;
+        positions
+            .iter()
+            .map(|pos| {
+                while deleted
+                    .next_if(|offset| **offset <= pos + num_skipped)
+                    .is_some()
+                {
+                    num_skipped += 1;
+                }
+                pos + num_skipped
+            })
+            .collect()
+    }
+}
+
+impl Extend<u32> for DeletionVector {
+    fn extend<T: IntoIterator<Item = u32>>(&mut self, iter: T) {
+        self.0.extend(iter)
+    }
+}
+
+impl FromIterator<u32> for DeletionVector {
+    fn from_iter<T: IntoIterator<Item = u32>>(iter: T) -> Self {
+        Self(BTreeSet::from_iter(iter))
+    }
+}
+
+/// The deletion vectors of the fragments of one dataset version, keyed by fragment ID.
+///
+/// A deletion file is read on first use, and then shared by all the readers of the version.
+#[derive(Debug, Default)]
+pub(crate) struct DeletionVectorCache(Mutex<HashMap<u64, Arc<OnceCell<Arc<DeletionVector>>>>>);
+
+impl DeletionVectorCache {
+    /// Get the deletion vector of a fragment, or read it with `load` if it is not cached yet.
+    pub(crate) async fn get_or_load(
+        &self,
+        fragment_id: u64,
+        load: impl Future<Output = Result<DeletionVector>>,
+    ) -> Result<Arc<DeletionVector>> {
+        let cell = self
+            .0
+            .lock()
+            .unwrap()
+            .entry(fragment_id)
+            .or_default()
+            .clone();
+        let deletion_vector = cell
+            .get_or_try_init(|| async { load.await.map(Arc::new) })
+            .await?;
+        Ok(deletion_vector.clone())
+    }
+}
+
+/// Write a [DeletionVector] to `path`, as a plain encoded array of sorted `u32` offsets.
+pub(crate) async fn write_deletion_file(
+    object_store: &ObjectStore,
+    path: &Path,
+    deletion_vector: &DeletionVector,
+) -> Result<()> {
+    let mut object_writer = object_store.create(path).await?;
+    let offsets = UInt32Array::from_iter_values(deletion_vector.iter());
+    object_writer.write_plain_encoded_array(&offsets).await?;
+    object_writer.shutdown().await
+}
+
+/// Read a [DeletionVector] with `num_deleted_rows` offsets from `path`.
+pub(crate) async fn read_deletion_file(
+    object_store: &ObjectStore,
+    path: &Path,
+    num_deleted_rows: usize,
+) -> Result<DeletionVector> {
+    if num_deleted_rows == 0 {
+        return Ok(DeletionVector::default());
+    }
+    let object_reader = object_store.open(path).await?;
+    let decoder = PlainDecoder::new(
+        object_reader.as_ref(),
+        &DataType::UInt32,
+        0,
+        num_deleted_rows,
+    )?;
+    let arr = decoder.decode().await?;
+    let offsets: &UInt32Array = as_primitive_array(arr.as_ref());
+    Ok(offsets.values().iter().copied().collect())
+}
+
+/// Filter out the rows whose `_rowid` has been deleted from `dataset`.
+///
+/// Only the deletion vectors of the fragments in `batch` are loaded. The rows of the fragments
+/// that no longer exist, because all their rows were deleted, are filtered out as well.
+pub(crate) async fn filter_deleted_rows(
+    dataset: &Dataset,
+    batch: &RecordBatch,
+) -> Result<RecordBatch> {
+    let row_id_arr = batch.column_by_name(ROW_ID).ok_or_else(|| {
+        Error::IO(format!(
+            "{ROW_ID} column does not exist in batch: {}",
+            batch.schema()
+        ))
+    })?;
+    let row_ids: &UInt64Array = as_primitive_array(row_id_arr);
+    let fragment_ids: BTreeSet<u64> = row_ids.values().iter().map(|id| id >> 32).collect();
+
+    // The deletion vector of each fragment, or `None` if the fragment does not exist.
+    let mut deletion_vectors = HashMap::new();
+    for fragment_id in fragment_ids {
+        let deletion_vector = match dataset.fragments().iter().find(|f| f.id == fragment_id) {
+            Some(fragment) => Some(dataset.deletion_vector(fragment).await?.unwrap_or_default()),
+            None => None,
+        };
+        deletion_vectors.insert(fragment_id, deletion_vector);
+    }
+    if deletion_vectors
+        .values()
+        .all(|dv| dv.as_ref().map_or(false, |dv| dv.is_empty()))
+    {
+        return Ok(batch.clone());
+    }
+
+    let mask = BooleanArray::from_iter(row_ids.values().iter().map(|row_id| {
+        let fragment_id = row_id >> 32;
+        let offset = (row_id - (fragment_id << 32)) as u32;
+        Some(
+            deletion_vectors[&fragment_id]
+                .as_ref()
+                .map_or(false, |dv| !dv.contains(offset)),
+        )
+    }));
+    Ok(filter_record_batch(batch, &mask)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow_array::Int32Array;
+    use arrow_schema::{Field, Schema as ArrowSchema};
+
+    #[tokio::test]
+    async fn test_write_read_deletion_file() {
+        let store = ObjectStore::memory();
+        let path = Path::from("/deletion");
+
+        let deletion_vector = DeletionVector::from_iter([30, 1, 7, 1024]);
+        write_deletion_file(&store, &path, &deletion_vector)
+            .await
+            .unwrap();
+
+        let actual = read_deletion_file(&store, &path, deletion_vector.len())
+            .await
+            .unwrap();
+        assert_eq!(actual, deletion_vector);
+        assert_eq!(actual.iter().collect::<Vec<_>>(), vec![1, 7, 30, 1024]);
+    }
+
+    #[test]
+    fn test_filter_batch() {
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "i",
+            DataType::Int32,
+            false,
+        )]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from_iter_values(10..20))],
+        )
+        .unwrap();
+
+        let deletion_vector = DeletionVector::from_iter([3, 11, 12, 25]);
+        let actual = deletion_vector.filter_batch(&batch, 10).unwrap();
+        assert_eq!(
+            actual,
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int32Array::from_iter_values([
+                    10, 13, 14, 15, 16, 17, 18, 19
+                ]))],
+            )
+            .unwrap()
+        );
+
+        // No deleted rows within the range.
+        let actual = deletion_vector.filter_batch(&batch, 40).unwrap();
+        assert_eq!(actual, batch);
+    }
+
+    #[test]
+    fn test_physical_offsets() {
+        let deletion_vector = DeletionVector::from_iter([0, 3, 4, 8]);
+        assert_eq!(
+            deletion_vector.physical_offsets(&[0, 1, 2, 3, 4, 10]),
+            vec![1, 2, 5, 6, 7, 14]
+        );
+        assert_eq!(
+            DeletionVector::default().physical_offsets(&[2, 5]),
+            vec![2, 5]
+        );
+    }
+}
@@ -16,6 +16,7 @@
 // under the License.
 
 use std::any::Any;
+use std::cmp::min;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
@@ -36,6 +37,8 @@ use crate::dataset::{Dataset, ROW_ID};
 use crate::index::vector::flat::flat_search;
 use crate::index::vector::ivf::IvfPQIndex;
 use crate::index::vector::{Query, VectorIndex};
+use crate::io::deletion::filter_deleted_rows;
+use crate::{Error, Result};
 
 /// KNN node for post-filtering.
 pub struct KNNFlatStream {
@@ -176,23 +179,12 @@ impl KNNIndexStream {
         let q = query.clone();
         let name = index_name.to_string();
         let bg_thread = tokio::spawn(async move {
-            let index = match IvfPQIndex::new(&dataset, &name).await {
-                Ok(idx) => idx,
-                Err(e) => {
-                    tx.send(Err(datafusion::error::DataFusionError::Execution(format!(
-                        "Failed to open vector index: {name}: {e}"
-                    ))))
-                    .await
-                    .expect("KNNFlat failed to send message");
-                    return;
-                }
-            };
-            let result = match index.search(&q).await {
+            let result = match search_index(&dataset, &name, &q).await {
                 Ok(b) => b,
                 Err(e) => {
-                    tx.send(Err(datafusion::error::DataFusionError::Execution(format!(
-                        "Failed to compute scores: {e}"
-                    ))))
+                    tx.send(Err(datafusion::error::DataFusionError::Execution(
+                        e.to_string(),
+                    )))
                     .await
                     .expect("KNNIndex failed to send message");
                     return;
@@ -214,6 +206,38 @@ impl KNNIndexStream {
     }
 }
 
+/// Search the vector index `name` for the `query.k` nearest rows that are not deleted.
+///
+/// The index might still contain the rows deleted after it was built. The index is searched
+/// for `query.k` plus the number of deleted rows first, then for twice as many candidates,
+/// until there are `query.k` rows left after filtering out the deleted rows.
+async fn search_index(dataset: &Dataset, name: &str, query: &Query) -> Result<RecordBatch> {
+    let index = IvfPQIndex::new(dataset, name)
+        .await
+        .map_err(|e| Error::Index(format!("Failed to open vector index: {name}: {e}")))?;
+
+    let num_deleted_rows = dataset
+        .fragments()
+        .iter()
+        .map(|f| f.num_deleted_rows())
+        .sum::<usize>();
+    let mut index_query = query.clone();
+    index_query.k = query.k + num_deleted_rows;
+    loop {
+        let candidates = index
+            .search(&index_query)
+            .await
+            .map_err(|e| Error::Index(format!("Failed to compute scores: {e}")))?;
+        let num_candidates = candidates.num_rows();
+        // The candidates are sorted by the scores.
+        let result = filter_deleted_rows(dataset, &candidates).await?;
+        if result.num_rows() >= query.k || num_candidates < index_query.k {
+            return Ok(result.slice(0, min(query.k, result.num_rows())));
+        }
+        index_query.k *= 2;
+    }
+}
+
 impl DFRecordBatchStream for KNNIndexStream {
     fn schema(&self) -> arrow_schema::SchemaRef {
         todo!()
@@ -311,15 +335,21 @@ mod tests {
     use std::sync::Arc;
 
     use arrow_array::{
-        cast::as_primitive_array, FixedSizeListArray, Int32Array, RecordBatchReader, StringArray,
+        cast::as_primitive_array, FixedSizeListArray, Float16Array, Float64Array, Int32Array,
+        RecordBatchReader, StringArray,
     };
     use arrow_schema::{DataType, Field as ArrowField, Schema as ArrowSchema};
+    use arrow_select::concat::concat_batches;
     use futures::TryStreamExt;
+    use half::{bf16, f16};
     use tempfile::tempdir;
 
     use crate::arrow::*;
     use crate::dataset::{Dataset, WriteParams};
-    use crate::index::vector::MetricType;
+    use crate::index::{
+        vector::{MetricType, VectorIndexParams},
+        IndexType,
+    };
     use crate::utils::testing::generate_random_array;
 
     #[tokio::test]
@@ -375,7 +405,7 @@ mod tests {
         let dataset = Dataset::open(test_uri).await.unwrap();
         let stream = dataset
             .scan()
-            .nearest("vector", as_primitive_array(&q), 10)
+            .nearest("vector", q.as_ref(), 10)
             .unwrap()
             .try_into_stream()
             .await
@@ -391,7 +421,7 @@ mod tests {
             stream,
             &Query {
                 column: "vector".to_string(),
-                key: Arc::new(as_primitive_array(&q).clone()),
+                key: q.clone(),
                 k: 10,
                 nprobs: 0,
                 refine_factor: None,
@@ -404,4 +434,176 @@ mod tests {
 
         assert_eq!(expected, results[0]);
     }
+
+    #[tokio::test]
+    async fn knn_index_search_with_deleted_rows() {
+        let dimension = 16;
+        let schema = Arc::new(ArrowSchema::new(vec![
+            ArrowField::new("i", DataType::Int32, false),
+            ArrowField::new(
+                "vector",
+                DataType::FixedSizeList(
+                    Box::new(ArrowField::new("item", DataType::Float32, true)),
+                    dimension,
+                ),
+                false,
+            ),
+        ]));
+        let vectors =
+            FixedSizeListArray::try_new(generate_random_array(512 * dimension as usize), dimension)
+                .unwrap();
+        let batches = RecordBatchBuffer::new(vec![RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from_iter_values(0..512)),
+                Arc::new(vectors.clone()),
+            ],
+        )
+        .unwrap()]);
+
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut write_params = WriteParams::default();
+        write_params.max_rows_per_file = 128;
+        let mut reader: Box<dyn RecordBatchReader> = Box::new(batches);
+        let dataset = Dataset::write(&mut reader, test_uri, Some(write_params))
+            .await
+            .unwrap();
+
+        let mut params = VectorIndexParams::default();
+        params.num_partitions = 2;
+        params.num_sub_vectors = 2;
+        let dataset = dataset
+            .create_index(&["vector"], IndexType::Vector, None, &params, false)
+            .await
+            .unwrap();
+
+        let q = vectors.value(100);
+        let search = |dataset: Dataset| {
+            let q = q.clone();
+            async move {
+                let batches = dataset
+                    .scan()
+                    .nearest("vector", q.as_ref(), 10)
+                    .unwrap()
+                    .nprobs(2)
+                    .try_into_stream()
+                    .await
+                    .unwrap()
+                    .try_collect::<Vec<_>>()
+                    .await
+                    .unwrap();
+                let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+                let i_arr: &Int32Array = as_primitive_array(batch.column_by_name("i").unwrap());
+                i_arr.values().to_vec()
+            }
+        };
+        let ids = search(dataset.clone()).await;
+        assert_eq!(ids.len(), 10);
+
+        // The index still returns k rows after its nearest rows are deleted.
+        let predicate = ids
+            .iter()
+            .map(|i| format!("i = {i}"))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let dataset = dataset.delete(&predicate).await.unwrap();
+        assert_eq!(dataset.load_indices().await.unwrap().len(), 1);
+        let new_ids = search(dataset).await;
+        assert_eq!(new_ids.len(), 10);
+        assert!(new_ids.iter().all(|i| !ids.contains(i)));
+    }
+
+    #[tokio::test]
+    async fn knn_search_bf16_f16_and_f64_vectors() {
+        let dimension = 16;
+        let float_arr = generate_random_array(512 * dimension as usize);
+        let bf16_arr = BFloat16Array::from_values(
+            float_arr
+                .values()
+                .iter()
+                .map(|v| bf16::from_f32(*v))
+                .collect(),
+        );
+        let f16_arr =
+            Float16Array::from_iter_values(float_arr.values().iter().map(|v| f16::from_f32(*v)));
+        let f64_arr = Float64Array::from_iter_values(float_arr.values().iter().map(|v| *v as f64));
+
+        for values in [
+            bf16_arr.into_array_ref(),
+            Arc::new(f16_arr),
+            Arc::new(f64_arr),
+        ] {
+            let test_dir = tempdir().unwrap();
+            let schema = Arc::new(ArrowSchema::new(vec![
+                ArrowField::new("i", DataType::Int32, false),
+                ArrowField::new(
+                    "embeddings",
+                    DataType::FixedSizeList(
+                        Box::new(ArrowField::new("item", values.data_type().clone(), true)),
+                        dimension,
+                    ),
+                    false,
+                ),
+            ]));
+            let vectors = FixedSizeListArray::try_new(values.clone(), dimension).unwrap();
+            let batches = RecordBatchBuffer::new(vec![RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int32Array::from_iter_values(0..512)),
+                    Arc::new(vectors.clone()),
+                ],
+            )
+            .unwrap()]);
+            let test_uri = test_dir.path().to_str().unwrap();
+            let mut reader: Box<dyn RecordBatchReader> = Box::new(batches);
+            let dataset = Dataset::write(&mut reader, test_uri, None).await.unwrap();
+            assert_eq!(ArrowSchema::from(dataset.schema()), *schema);
+
+            // The f32 query vector is converted to the float type of the column.
+            let q = float_arr.slice(100 * dimension as usize, dimension as usize);
+            let search = |dataset: Dataset| {
+                let q = q.clone();
+                async move {
+                    let batches = dataset
+                        .scan()
+                        .nearest("embeddings", q.as_ref(), 10)
+                        .unwrap()
+                        .nprobs(2)
+                        .refine(10)
+                        .try_into_stream()
+                        .await
+                        .unwrap()
+                        .try_collect::<Vec<_>>()
+                        .await
+                        .unwrap();
+                    let results = concat_batches(&batches[0].schema(), &batches).unwrap();
+                    assert_eq!(results.num_rows(), 10);
+                    let i_arr: &Int32Array =
+                        as_primitive_array(results.column_by_name("i").unwrap());
+                    let embeddings =
+                        as_fixed_size_list_array(results.column_by_name("embeddings").unwrap());
+                    assert_eq!(embeddings.value_type(), *values.data_type());
+                    i_arr.value(0)
+                }
+            };
+            assert_eq!(search(dataset.clone()).await, 100);
+
+            let mut params = VectorIndexParams::default();
+            params.num_partitions = 2;
+            params.num_sub_vectors = 2;
+            let dataset = dataset
+                .create_index(&["embeddings"], IndexType::Vector, None, &params, false)
+                .await
+                .unwrap();
+            assert_eq!(dataset.load_indices().await.unwrap().len(), 1);
+            assert_eq!(search(dataset.clone()).await, 100);
+
+            // Only vector columns of floats can be searched.
+            assert!(matches!(
+                dataset.scan().nearest("i", q.as_ref(), 10),
+                Err(Error::IO(_))
+            ));
+        }
+    }
 }
@@ -28,8 +28,8 @@ use datafusion::{
 };
 use sqlparser::{
     ast::{
-        BinaryOperator, Expr as SQLExpr, Function, FunctionArg, FunctionArgExpr, Ident, SetExpr,
-        Statement, Value,
+        BinaryOperator, Expr as SQLExpr, Function, FunctionArg, FunctionArgExpr, Ident, SelectItem,
+        SetExpr, Statement, Value,
     },
     dialect::GenericDialect,
     parser::Parser,
@@ -207,6 +207,33 @@ impl Planner {
         resolve_expr(&expr, &schema)
     }
 
+    /// Create Logical [Expr] from a SQL expression, i.e., `"i + 1"` or `'cat'`.
+    pub fn parse_expr(&self, expr: &str) -> Result<Expr> {
+        let sql = format!("SELECT {expr} FROM t");
+
+        let dialect = GenericDialect {};
+        let stmts = Parser::parse_sql(&dialect, sql.as_str())?;
+        if stmts.len() != 1 {
+            return Err(Error::IO(format!("Expression is not valid: {expr}")));
+        }
+        let projection: &[SelectItem] = if let Statement::Query(query) = &stmts[0] {
+            if let SetExpr::Select(s) = query.body.as_ref() {
+                s.projection.as_slice()
+            } else {
+                &[]
+            }
+        } else {
+            &[]
+        };
+        let [SelectItem::UnnamedExpr(sql_expr)] = projection else {
+            return Err(Error::IO(format!("Expression is not valid: {expr}")));
+        };
+
+        let expr = self.parse_sql_expr(sql_expr)?;
+        let schema = Schema::try_from(self.schema.as_ref())?;
+        resolve_expr(&expr, &schema)
+    }
+
     /// Create the [`PhysicalExpr`] from a logical [`Expr`]
     pub fn create_physical_expr(&self, expr: &Expr) -> Result<Arc<dyn PhysicalExpr>> {
         use crate::datafusion::physical_expr::Column;
@@ -329,4 +356,35 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    fn test_parse_expr() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("i", DataType::Int32, false),
+            Field::new("s", DataType::Utf8, true),
+        ]));
+        let planner = Planner::new(schema.clone());
+
+        let expr = planner.parse_expr("i + 1").unwrap();
+        assert_eq!(expr, col("i") + lit(1_i32));
+        assert_eq!(planner.parse_expr("'cat'").unwrap(), lit("cat"));
+        assert!(planner.parse_expr("i, s").is_err());
+
+        let physical_expr = planner.create_physical_expr(&expr).unwrap();
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from_iter_values(0..10)) as ArrayRef,
+                Arc::new(StringArray::from_iter_values(
+                    (0..10).map(|v| format!("str-{}", v)),
+                )),
+            ],
+        )
+        .unwrap();
+        let values = physical_expr.evaluate(&batch).unwrap();
+        assert_eq!(
+            values.into_array(batch.num_rows()).as_ref(),
+            &Int32Array::from_iter_values(1..11)
+        );
+    }
 }
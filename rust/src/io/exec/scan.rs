@@ -28,10 +28,9 @@ use futures::stream::Stream;
 use tokio::sync::mpsc::{self, Receiver};
 use tokio::task::JoinHandle;
 
-use crate::dataset::{Dataset, ROW_ID};
+use crate::dataset::{fragment::FragmentReader, Dataset, ROW_ID};
 use crate::datatypes::Schema;
 use crate::format::Fragment;
-use crate::io::FileReader;
 
 /// Dataset Scan Node.
 #[derive(Debug)]
@@ -67,30 +66,20 @@ impl LanceStream {
         let (tx, rx) = mpsc::channel(prefetch_size);
 
         let project_schema = projection.clone();
-        let data_dir = dataset.data_dir();
         let io_thread = tokio::spawn(async move {
             'outer: for frag in fragments.as_ref() {
                 if tx.is_closed() {
                     return;
                 }
-                let data_file = &frag.files[0];
-                let path = data_dir.child(data_file.path.clone());
-                let reader = match FileReader::try_new_with_fragment(
-                    dataset.object_store.as_ref(),
-                    &path,
-                    frag.id,
-                    Some(dataset.manifest.as_ref()),
-                )
-                .await
-                {
+                let reader = match FragmentReader::try_new(dataset.as_ref(), frag).await {
                     Ok(mut r) => {
-                        r.set_projection(project_schema.as_ref().clone());
                         r.with_row_id(with_row_id);
                         r
                     }
                     Err(e) => {
                         tx.send(Err(DataFusionError::Execution(format!(
-                            "Failed to open file: {path}: {e}"
+                            "Failed to open fragment {}: {e}",
+                            frag.id
                         ))))
                         .await
                         .expect("Scanner sending error message");
@@ -99,7 +88,21 @@ impl LanceStream {
                     }
                 };
 
+                let deletion_vector = match dataset.deletion_vector(frag).await {
+                    Ok(dv) => dv,
+                    Err(e) => {
+                        tx.send(Err(DataFusionError::Execution(format!(
+                            "Failed to read deletion file of fragment {}: {e}",
+                            frag.id
+                        ))))
+                        .await
+                        .expect("Scanner sending error message");
+                        break;
+                    }
+                };
+
                 let r = &reader;
+                let mut batch_offset = 0;
                 for batch_id in 0..reader.num_batches() as i32 {
                     let rows_in_batch = reader.num_rows_in_batch(batch_id);
                     for start in (0..rows_in_batch).step_by(read_size) {
@@ -110,6 +113,13 @@ impl LanceStream {
                                 project_schema.as_ref(),
                             )
                             .await;
+                        // Skip the deleted rows.
+                        let result = match (result, deletion_vector.as_ref()) {
+                            (Ok(batch), Some(dv)) => {
+                                dv.filter_batch(&batch, (batch_offset + start) as u32)
+                            }
+                            (result, _) => result,
+                        };
                         if tx.is_closed() {
                             // Early stop
                             break 'outer;
@@ -119,6 +129,7 @@ impl LanceStream {
                             break 'outer;
                         }
                     }
+                    batch_offset += rows_in_batch;
                 }
             }
 
@@ -33,6 +33,7 @@ use tokio::task::JoinHandle;
 use crate::arrow::*;
 use crate::dataset::{Dataset, ROW_ID};
 use crate::datatypes::Schema;
+use crate::io::deletion::filter_deleted_rows;
 
 /// Dataset Take Node.
 ///
@@ -66,7 +67,8 @@ impl Take {
                     (dataset.clone(), projection.clone())
                 }))
                 .then(|(batch, (dataset, projection))| async move {
-                    let batch = batch?;
+                    // The upstream rows, i.e., from an index, might have been deleted.
+                    let batch = filter_deleted_rows(&dataset, &batch?).await?;
                     // println!("GlobalTake Batch is {:?}", batch);
                     let row_id_arr = batch.column_by_name(ROW_ID).unwrap();
                     let row_ids: &UInt64Array = as_primitive_array(row_id_arr);
@@ -253,12 +255,19 @@ impl LocalTake {
                     // TODO: need to cache the fragments.
                     let batch = b?;
                     if take_schema.fields.is_empty() {
-                        return Ok(batch);
+                        return if drop_row_id {
+                            Ok(batch.drop_column(ROW_ID)?)
+                        } else {
+                            Ok(batch)
+                        };
                     };
-                    let projection_schema = ArrowSchema::from(projection.as_ref());
                     if batch.num_rows() == 0 {
-                        return Ok(RecordBatch::new_empty(Arc::new(projection_schema)));
+                        return Ok(RecordBatch::new_empty(projection_with_row_id(
+                            &projection,
+                            drop_row_id,
+                        )));
                     }
+                    let projection_schema = ArrowSchema::from(projection.as_ref());
 
                     let row_id_arr = batch.column_by_name(ROW_ID).unwrap();
                     let row_ids: &UInt64Array = as_primitive_array(row_id_arr);
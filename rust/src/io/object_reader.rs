@@ -118,14 +118,13 @@ pub(crate) async fn read_message<M: Message + Default>(
 pub(crate) async fn read_struct<
     'm,
     M: Message + Default + 'static,
-    T: ProtoStruct<Proto = M> + From<M>,
+    T: ProtoStruct<Proto = M> + TryFrom<M, Error = Error>,
 >(
     reader: &dyn ObjectReader,
     pos: usize,
 ) -> Result<T> {
     let msg = read_message::<M>(reader, pos).await?;
-    let obj = T::from(msg);
-    Ok(obj)
+    T::try_from(msg)
 }
 
 /// Read a fixed stride array from disk.
@@ -28,6 +28,7 @@ use shellexpand::tilde;
 use url::{ParseError, Url};
 
 use crate::error::{Error, Result};
+use crate::io::commit::{CommitHandler, RenameCommitHandler, UnsupportedCommitHandler};
 use crate::io::object_reader::CloudObjectReader;
 use crate::io::object_writer::ObjectWriter;
 
@@ -144,6 +145,16 @@ impl ObjectStore {
         &self.base_path
     }
 
+    /// The default [CommitHandler] of this object store.
+    ///
+    /// S3 has no atomic rename-if-not-exists, so its commit handler must be set explicitly.
+    pub(crate) fn commit_handler(&self) -> Arc<dyn CommitHandler> {
+        match self.scheme.as_str() {
+            "s3" => Arc::new(UnsupportedCommitHandler),
+            _ => Arc::new(RenameCommitHandler),
+        }
+    }
+
     /// Open a file for path
     pub async fn open(&self, path: &Path) -> Result<Box<dyn ObjectReader>> {
         match self.scheme.as_str() {
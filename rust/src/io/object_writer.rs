@@ -15,6 +15,7 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use std::ops::Range;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
@@ -24,7 +25,7 @@ use pin_project::pin_project;
 use prost::Message;
 use tokio::io::{AsyncWrite, AsyncWriteExt};
 
-use crate::encodings::plain::PlainEncoder;
+use crate::encodings::{compression::Compression, plain::PlainEncoder};
 use crate::format::{ProtoStruct, MAGIC, MAJOR_VERSION, MINOR_VERSION};
 use crate::io::ObjectStore;
 use crate::Result;
@@ -40,6 +41,9 @@ pub struct ObjectWriter {
     writer: Box<dyn AsyncWrite + Unpin + Send>,
     multipart_id: MultipartId,
     cursor: usize,
+
+    /// The bytes of the page being buffered for compression, and the position it starts at.
+    page: Option<(usize, Vec<u8>)>,
 }
 
 impl ObjectWriter {
@@ -51,6 +55,7 @@ impl ObjectWriter {
             writer,
             multipart_id,
             cursor: 0,
+            page: None,
         })
     }
 
@@ -59,6 +64,30 @@ impl ObjectWriter {
         self.cursor
     }
 
+    /// Buffer the bytes written from now on in memory, until [ObjectWriter::finish_page].
+    ///
+    /// The positions told while buffering are the positions the bytes would have been
+    /// written at, if the page was not compressed.
+    pub fn start_page(&mut self) {
+        assert!(self.page.is_none(), "A page is already being buffered");
+        self.page = Some((self.cursor, vec![]));
+    }
+
+    /// Compress the bytes buffered since [ObjectWriter::start_page], and write them at the
+    /// position where the page started.
+    ///
+    /// Returns the byte range of the compressed page in the file.
+    pub async fn finish_page(&mut self, compression: Compression) -> Result<Range<usize>> {
+        let (start, buf) = self
+            .page
+            .take()
+            .expect("finish_page called without start_page");
+        let compressed = compression.compress(&buf)?;
+        self.cursor = start;
+        self.write_all(&compressed).await?;
+        Ok(start..self.cursor)
+    }
+
     /// Write a protobuf message to the object, and returns the file position of the protobuf.
     pub async fn write_protobuf(&mut self, msg: &impl Message) -> Result<usize> {
         let offset = self.tell();
@@ -108,6 +137,11 @@ impl AsyncWrite for ObjectWriter {
         buf: &[u8],
     ) -> Poll<std::io::Result<usize>> {
         let mut this = self.project();
+        if let Some((_, page)) = this.page.as_mut() {
+            page.extend_from_slice(buf);
+            *this.cursor += buf.len();
+            return Poll::Ready(Ok(buf.len()));
+        }
         this.writer.as_mut().poll_write(cx, buf).map_ok(|n| {
             *this.cursor += n;
             n
@@ -129,7 +163,7 @@ mod tests {
     use tokio::io::AsyncWriteExt;
 
     use crate::format::Metadata;
-    use crate::io::object_reader::{read_struct, CloudObjectReader};
+    use crate::io::object_reader::{read_struct, CloudObjectReader, ObjectReader};
     use crate::io::ObjectStore;
 
     use super::*;
@@ -157,6 +191,29 @@ mod tests {
         object_writer.shutdown().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_write_compressed_page() {
+        let store = ObjectStore::new(":memory:").await.unwrap();
+        let path = Path::from("/foo");
+
+        let mut object_writer = ObjectWriter::new(&store, &path).await.unwrap();
+        object_writer.write_all(&[1, 2, 3, 4]).await.unwrap();
+
+        object_writer.start_page();
+        let page = vec![7_u8; 1024];
+        object_writer.write_all(&page).await.unwrap();
+        assert_eq!(object_writer.tell(), 4 + 1024);
+        let range = object_writer.finish_page(Compression::Zstd).await.unwrap();
+        assert_eq!(range.start, 4);
+        assert!(range.len() < page.len());
+        assert_eq!(object_writer.tell(), range.end);
+        object_writer.shutdown().await.unwrap();
+
+        let object_reader = CloudObjectReader::new(&store, path, 1024).unwrap();
+        let compressed = object_reader.get_range(range).await.unwrap();
+        assert_eq!(Compression::Zstd.decompress(&compressed).unwrap(), page);
+    }
+
     #[tokio::test]
     async fn test_write_proto_structs() {
         let store = ObjectStore::new(":memory:").await.unwrap();
@@ -19,15 +19,20 @@ use std::ops::Range;
 use std::sync::Arc;
 
 use arrow_arith::arithmetic::subtract_scalar;
-use arrow_array::cast::as_primitive_array;
+use arrow_array::cast::{as_boolean_array, as_primitive_array};
 use arrow_array::{
-    ArrayRef, Int64Array, LargeListArray, ListArray, NullArray, RecordBatch, StructArray,
-    UInt64Array,
+    make_array, new_empty_array, Array, ArrayRef, Int32Array, Int64Array, Int8Array,
+    LargeListArray, ListArray, NullArray, RecordBatch, StructArray, UInt32Array, UInt64Array,
+    UnionArray,
 };
-use arrow_schema::{DataType, Field as ArrowField, Schema as ArrowSchema};
-use arrow_select::concat::concat_batches;
+use arrow_buffer::Buffer;
+use arrow_data::ArrayDataBuilder;
+use arrow_schema::{DataType, Field as ArrowField, Schema as ArrowSchema, UnionMode};
+use arrow_select::{concat::concat_batches, take::take};
 use async_recursion::async_recursion;
+use async_trait::async_trait;
 use byteorder::{ByteOrder, LittleEndian};
+use bytes::Bytes;
 use futures::stream::{self, Stream, TryStreamExt};
 use futures::StreamExt;
 use object_store::path::Path;
@@ -35,7 +40,14 @@ use prost::Message;
 
 use super::ReadBatchParams;
 use crate::arrow::*;
-use crate::encodings::{dictionary::DictionaryDecoder, AsyncIndex};
+use crate::encodings::{
+    bitpacked::BitPackedDecoder,
+    delta::DeltaDecoder,
+    dictionary::DictionaryDecoder,
+    plain::{apply_validity, PlainDecoder},
+    rle::RleDecoder,
+    AsyncIndex, Encoding,
+};
 use crate::error::{Error, Result};
 use crate::format::Manifest;
 use crate::format::{pb, Metadata, PageTable};
@@ -71,7 +83,7 @@ pub async fn read_manifest(object_store: &ObjectStore, path: &Path) -> Result<Ma
     assert!(file_size - manifest_pos <= buf.len());
     let proto =
         pb::Manifest::decode(&buf[buf.len() - (file_size - manifest_pos) + 4..buf.len() - 16])?;
-    Ok(Manifest::from(proto))
+    Manifest::try_from(proto)
 }
 
 /// Compute row id from `fragment_id` and the `offset` of the row in the fragment.
@@ -99,11 +111,14 @@ pub struct FileReader<'a> {
 
 impl<'a> FileReader<'a> {
     /// Open file reader
+    ///
+    /// `schema` is the dataset schema of the fields stored in this file. If not provided,
+    /// the schema is read from the manifest stored in the file.
     pub(crate) async fn try_new_with_fragment(
         object_store: &'a ObjectStore,
         path: &Path,
         fragment_id: u64,
-        manifest: Option<&Manifest>,
+        schema: Option<&Schema>,
     ) -> Result<FileReader<'a>> {
         let object_reader = object_store.open(path).await?;
 
@@ -124,21 +139,47 @@ impl<'a> FileReader<'a> {
             read_struct_from_buf(&tail_bytes.slice(offset..))?
         };
 
-        let (projection, num_columns) = if let Some(m) = manifest {
-            (m.schema.clone(), m.schema.max_field_id().unwrap() + 1)
+        let (projection, num_columns) = if let Some(s) = schema {
+            (s.clone(), s.max_field_id().unwrap_or(-1) + 1)
         } else {
             let mut m: Manifest =
                 read_struct(object_reader.as_ref(), metadata.manifest_position.unwrap()).await?;
             m.schema.load_dictionary(object_reader.as_ref()).await?;
             (m.schema.clone(), m.schema.max_field_id().unwrap() + 1)
         };
-        let page_table = PageTable::load(
-            object_reader.as_ref(),
-            metadata.page_table_position,
-            num_columns,
-            metadata.num_batches() as i32,
-        )
-        .await?;
+        let page_table = if num_columns > 0 {
+            let mut page_table = PageTable::load(
+                object_reader.as_ref(),
+                metadata.page_table_position,
+                num_columns,
+                metadata.num_batches() as i32,
+            )
+            .await?;
+            if let Some(position) = metadata.validity_table_position {
+                page_table
+                    .load_validity(
+                        object_reader.as_ref(),
+                        position,
+                        num_columns,
+                        metadata.num_batches() as i32,
+                    )
+                    .await?;
+            }
+            if let Some(position) = metadata.compression_table_position {
+                page_table
+                    .load_compression(
+                        object_reader.as_ref(),
+                        position,
+                        num_columns,
+                        metadata.num_batches() as i32,
+                    )
+                    .await?;
+            }
+            page_table
+        } else {
+            // All the fields in this file have been dropped from the dataset.
+            PageTable::default()
+        };
 
         Ok(Self {
             object_reader,
@@ -189,6 +230,27 @@ impl<'a> FileReader<'a> {
         self.metadata.is_empty()
     }
 
+    /// Compute the row IDs of the rows selected by `params` in a batch.
+    pub(crate) fn row_ids(&self, batch_id: i32, params: &ReadBatchParams) -> Result<UInt64Array> {
+        let rows_in_batch = self.num_rows_in_batch(batch_id);
+        let ids_in_batch: Vec<i32> = match params {
+            ReadBatchParams::Indices(indices) => {
+                indices.values().iter().map(|v| *v as i32).collect()
+            }
+            ReadBatchParams::Range(r) => r.clone().map(|v| v as i32).collect(),
+            ReadBatchParams::RangeFull => (0..rows_in_batch as i32).collect(),
+            ReadBatchParams::RangeTo(r) => (0..r.end).map(|v| v as i32).collect(),
+            ReadBatchParams::RangeFrom(r) => (r.start..rows_in_batch).map(|v| v as i32).collect(),
+        };
+        let batch_offset = self
+            .metadata
+            .get_offset(batch_id)
+            .ok_or_else(|| Error::IO(format!("batch {batch_id} does not exist")))?;
+        Ok(UInt64Array::from_iter_values(ids_in_batch.iter().map(
+            |o| compute_row_id(self.fragment_id, *o + batch_offset),
+        )))
+    }
+
     /// Read a batch of data from the file.
     ///
     /// The schema of the returned [RecordBatch] is set by [`FileReader::schema()`].
@@ -264,29 +326,10 @@ async fn read_batch(
         .await?;
     let mut batch = RecordBatch::try_new(Arc::new(schema.into()), arrs)?;
     if with_row_id {
-        let ids_in_batch: Vec<i32> = match params {
-            ReadBatchParams::Indices(indices) => {
-                indices.values().iter().map(|v| *v as i32).collect()
-            }
-            ReadBatchParams::Range(r) => r.clone().map(|v| v as i32).collect(),
-            ReadBatchParams::RangeFull => (0..batch.num_rows() as i32).collect(),
-            ReadBatchParams::RangeTo(r) => (0..r.end).map(|v| v as i32).collect(),
-            ReadBatchParams::RangeFrom(r) => (r.start..r.start + batch.num_rows())
-                .map(|v| v as i32)
-                .collect(),
-        };
-        let batch_offset = reader
-            .metadata
-            .get_offset(batch_id)
-            .ok_or_else(|| Error::IO(format!("batch {batch_id} does not exist")))?;
-        let row_id_arr = Arc::new(UInt64Array::from_iter_values(
-            ids_in_batch
-                .iter()
-                .map(|o| compute_row_id(reader.fragment_id, *o + batch_offset)),
-        ));
+        let row_id_arr = reader.row_ids(batch_id, params)?;
         batch = batch.try_with_column(
             ArrowField::new("_rowid", DataType::UInt64, false),
-            row_id_arr,
+            Arc::new(row_id_arr),
         )?;
     }
     Ok(batch)
@@ -303,7 +346,9 @@ async fn read_array(
 
     use DataType::*;
 
-    if data_type.is_fixed_stride() {
+    if field.encoding == Some(Encoding::RLE) {
+        read_rle_array(reader, field, batch_id, params).await
+    } else if data_type.is_fixed_stride() {
         _read_fixed_stride_array(reader, field, batch_id, params).await
     } else {
         match data_type {
@@ -315,6 +360,8 @@ async fn read_array(
             Dictionary(_, _) => read_dictionary_array(reader, field, batch_id, params).await,
             List(_) => read_list_array(reader, field, batch_id, params).await,
             LargeList(_) => read_large_list_array(reader, field, batch_id, params).await,
+            Map(_, _) => read_map_array(reader, field, batch_id, params).await,
+            Union(_, _, _) => read_union_array(reader, field, batch_id, params).await,
             _ => {
                 unimplemented!("{}", format!("No support for {data_type} yet"));
             }
@@ -335,6 +382,64 @@ fn get_page_info<'a>(
     })
 }
 
+/// Reads a page of a data file, from the decompressed bytes if the page is compressed.
+///
+/// The decoders read a page by the positions in the page table. The positions of a compressed
+/// page are served from its decompressed bytes, so the decoders do not need to know about it.
+struct PageReader<'b> {
+    inner: &'b dyn ObjectReader,
+
+    /// The start position and the decompressed bytes of a compressed page.
+    page: Option<(usize, Bytes)>,
+}
+
+impl<'b> PageReader<'b> {
+    /// Open the page described by `page_info`, and decompress it if it is compressed.
+    async fn try_new(reader: &'b FileReader<'_>, page_info: &PageInfo) -> Result<PageReader<'b>> {
+        let inner = reader.object_reader.as_ref();
+        let page = match &page_info.compressed {
+            Some((compression, range)) => {
+                let compressed = inner.get_range(range.clone()).await?;
+                let data = compression.decompress(&compressed)?;
+                Some((range.start, Bytes::from(data)))
+            }
+            None => None,
+        };
+        Ok(Self { inner, page })
+    }
+}
+
+#[async_trait]
+impl<'b> ObjectReader for PageReader<'b> {
+    fn prefetch_size(&self) -> usize {
+        self.inner.prefetch_size()
+    }
+
+    async fn size(&self) -> Result<usize> {
+        match &self.page {
+            Some((start, data)) => Ok(start + data.len()),
+            None => self.inner.size().await,
+        }
+    }
+
+    async fn get_range(&self, range: Range<usize>) -> Result<Bytes> {
+        match &self.page {
+            Some((start, data)) => {
+                if range.start < *start || range.end > start + data.len() {
+                    return Err(Error::IO(format!(
+                        "PageReader: request({:?}) out of the compressed page: [{}..{}]",
+                        range,
+                        start,
+                        start + data.len()
+                    )));
+                }
+                Ok(data.slice(range.start - start..range.end - start))
+            }
+            None => self.inner.get_range(range).await,
+        }
+    }
+}
+
 /// Read primitive array for batch `batch_idx`.
 async fn _read_fixed_stride_array(
     reader: &FileReader<'_>,
@@ -343,15 +448,47 @@ async fn _read_fixed_stride_array(
     params: &ReadBatchParams,
 ) -> Result<ArrayRef> {
     let page_info = get_page_info(&reader.page_table, field, batch_id)?;
+    let page_reader = PageReader::try_new(reader, page_info).await?;
+    let data_type = field.data_type();
 
-    read_fixed_stride_array(
-        reader.object_reader.as_ref(),
-        &field.data_type(),
-        page_info.position,
-        page_info.length,
-        params.clone(),
-    )
-    .await
+    match field.encoding {
+        Some(Encoding::BitPacked) => {
+            let mut decoder = BitPackedDecoder::new(
+                &page_reader,
+                &data_type,
+                page_info.position,
+                page_info.length,
+            );
+            if let Some(validity) = page_info.validity {
+                decoder = decoder.with_validity(validity);
+            }
+            decoder.get(params.clone()).await
+        }
+        Some(Encoding::Delta) => {
+            let mut decoder = DeltaDecoder::new(
+                &page_reader,
+                &data_type,
+                page_info.position,
+                page_info.length,
+            );
+            if let Some(validity) = page_info.validity {
+                decoder = decoder.with_validity(validity);
+            }
+            decoder.get(params.clone()).await
+        }
+        _ => {
+            let mut decoder = PlainDecoder::new(
+                &page_reader,
+                &data_type,
+                page_info.position,
+                page_info.length,
+            )?;
+            if let Some(validity) = page_info.validity {
+                decoder = decoder.with_validity(validity);
+            }
+            decoder.get(params.clone()).await
+        }
+    }
 }
 
 fn read_null_array(
@@ -405,10 +542,11 @@ async fn read_binary_array(
     params: &ReadBatchParams,
 ) -> Result<ArrayRef> {
     let page_info = get_page_info(&reader.page_table, field, batch_id)?;
+    let page_reader = PageReader::try_new(reader, page_info).await?;
 
     use crate::io::object_reader::read_binary_array;
     read_binary_array(
-        reader.object_reader.as_ref(),
+        &page_reader,
         &field.data_type(),
         field.nullable,
         page_info.position,
@@ -418,6 +556,24 @@ async fn read_binary_array(
     .await
 }
 
+async fn read_rle_array(
+    reader: &FileReader<'_>,
+    field: &Field,
+    batch_id: i32,
+    params: &ReadBatchParams,
+) -> Result<ArrayRef> {
+    let page_info = get_page_info(&reader.page_table, field, batch_id)?;
+    let page_reader = PageReader::try_new(reader, page_info).await?;
+    let data_type = field.data_type();
+    let decoder = RleDecoder::new(
+        &page_reader,
+        &data_type,
+        page_info.position,
+        page_info.length,
+    );
+    decoder.get(params.clone()).await
+}
+
 async fn read_dictionary_array(
     reader: &FileReader<'_>,
     field: &Field,
@@ -425,9 +581,10 @@ async fn read_dictionary_array(
     params: &ReadBatchParams,
 ) -> Result<ArrayRef> {
     let page_info = get_page_info(&reader.page_table, field, batch_id)?;
+    let page_reader = PageReader::try_new(reader, page_info).await?;
     let data_type = field.data_type();
     let decoder = DictionaryDecoder::new(
-        reader.object_reader.as_ref(),
+        &page_reader,
         page_info.position,
         page_info.length,
         &data_type,
@@ -466,9 +623,10 @@ async fn read_list_array(
     params: &ReadBatchParams,
 ) -> Result<ArrayRef> {
     let page_info = get_page_info(&reader.page_table, field, batch_id)?;
+    let page_reader = PageReader::try_new(reader, page_info).await?;
 
     let position_arr = read_fixed_stride_array(
-        reader.object_reader.as_ref(),
+        &page_reader,
         &DataType::Int32,
         page_info.position,
         page_info.length,
@@ -491,8 +649,9 @@ async fn read_large_list_array(
     params: &ReadBatchParams,
 ) -> Result<ArrayRef> {
     let page_info = get_page_info(&reader.page_table, field, batch_id)?;
+    let page_reader = PageReader::try_new(reader, page_info).await?;
     let position_arr = read_fixed_stride_array(
-        reader.object_reader.as_ref(),
+        &page_reader,
         &DataType::Int64,
         page_info.position,
         page_info.length,
@@ -508,20 +667,193 @@ async fn read_large_list_array(
     Ok(Arc::new(LargeListArray::try_new(value_arrs, &offset_arr)?))
 }
 
+/// The range of rows covered by `params`, in a page of `length` rows.
+fn params_to_range(params: &ReadBatchParams, length: usize) -> Range<usize> {
+    match params {
+        ReadBatchParams::Indices(indices) => {
+            if indices.is_empty() {
+                0..0
+            } else {
+                let min = *indices.values().iter().min().unwrap() as usize;
+                let max = *indices.values().iter().max().unwrap() as usize;
+                min..max + 1
+            }
+        }
+        ReadBatchParams::Range(r) => r.clone(),
+        ReadBatchParams::RangeFull => 0..length,
+        ReadBatchParams::RangeTo(r) => 0..r.end,
+        ReadBatchParams::RangeFrom(r) => r.start..length,
+    }
+}
+
+async fn read_map_array(
+    reader: &FileReader<'_>,
+    field: &Field,
+    batch_id: i32,
+    params: &ReadBatchParams,
+) -> Result<ArrayRef> {
+    let page_info = get_page_info(&reader.page_table, field, batch_id)?;
+    let page_reader = PageReader::try_new(reader, page_info).await?;
+    // The offsets have one more value than the number of maps.
+    let num_maps = page_info.length - 1;
+    let range = params_to_range(params, num_maps);
+    if range.is_empty() {
+        return Ok(new_empty_array(&field.data_type()));
+    }
+
+    let decoder = PlainDecoder::new(
+        &page_reader,
+        &DataType::Int32,
+        page_info.position,
+        page_info.length,
+    )?;
+    let positions = decoder.get(range.start..range.end + 1).await?;
+    let positions: &Int32Array = as_primitive_array(positions.as_ref());
+    let start_position = positions.value(0);
+    let entries_range = start_position as usize..positions.value(positions.len() - 1) as usize;
+    let mut entries = read_array(
+        reader,
+        &field.children[0],
+        batch_id,
+        &ReadBatchParams::Range(entries_range),
+    )
+    .await?;
+
+    let offsets = if let ReadBatchParams::Indices(indices) = params {
+        // Take the entries of each selected map.
+        let mut offsets = vec![0_i32];
+        let mut entry_indices = vec![];
+        for index in indices.values().iter() {
+            let i = *index as usize - range.start;
+            let map_start = (positions.value(i) - start_position) as u32;
+            let map_end = (positions.value(i + 1) - start_position) as u32;
+            entry_indices.extend(map_start..map_end);
+            offsets.push(entry_indices.len() as i32);
+        }
+        entries = take(&entries, &UInt32Array::from(entry_indices), None)?;
+        Int32Array::from(offsets)
+    } else {
+        subtract_scalar(positions, start_position)?
+    };
+
+    let array_data = ArrayDataBuilder::new(field.data_type())
+        .len(offsets.len() - 1)
+        .add_buffer(offsets.into_data().buffers()[0].clone())
+        .add_child_data(entries.into_data())
+        .build()?;
+    let array = make_array(array_data);
+    if let Some(validity) = page_info.validity {
+        let validity_decoder =
+            PlainDecoder::new(&page_reader, &DataType::Boolean, validity, num_maps)?;
+        let validity = validity_decoder.get(params.clone()).await?;
+        apply_validity(array, as_boolean_array(validity.as_ref()))
+    } else {
+        Ok(array)
+    }
+}
+
+async fn read_union_array(
+    reader: &FileReader<'_>,
+    field: &Field,
+    batch_id: i32,
+    params: &ReadBatchParams,
+) -> Result<ArrayRef> {
+    let page_info = get_page_info(&reader.page_table, field, batch_id)?;
+    let page_reader = PageReader::try_new(reader, page_info).await?;
+    let DataType::Union(fields, field_type_ids, mode) = field.data_type() else {
+        unreachable!()
+    };
+
+    let type_ids = PlainDecoder::new(
+        &page_reader,
+        &DataType::Int8,
+        page_info.position,
+        page_info.length,
+    )?
+    .get(params.clone())
+    .await?;
+    let type_ids: &Int8Array = as_primitive_array(type_ids.as_ref());
+
+    let mut children = vec![];
+    let value_offsets = match mode {
+        UnionMode::Sparse => {
+            for (child, arrow_field) in field.children.iter().zip(fields) {
+                let values = read_array(reader, child, batch_id, params).await?;
+                children.push((arrow_field, values));
+            }
+            None
+        }
+        UnionMode::Dense => {
+            // The offsets are stored after the type IDs.
+            let offsets = PlainDecoder::new(
+                &page_reader,
+                &DataType::Int32,
+                page_info.position + page_info.length,
+                page_info.length,
+            )?
+            .get(params.clone())
+            .await?;
+            let offsets: &Int32Array = as_primitive_array(offsets.as_ref());
+
+            // Take the values of each child, and point the new offsets to them.
+            let mut new_offsets = vec![0_i32; offsets.len()];
+            for ((child, arrow_field), type_id) in
+                field.children.iter().zip(fields).zip(field_type_ids.iter())
+            {
+                let mut value_indices = vec![];
+                for i in 0..type_ids.len() {
+                    if type_ids.value(i) == *type_id {
+                        new_offsets[i] = value_indices.len() as i32;
+                        value_indices.push(offsets.value(i) as u32);
+                    }
+                }
+                let values = if value_indices.is_empty() {
+                    new_empty_array(arrow_field.data_type())
+                } else {
+                    let start = *value_indices.iter().min().unwrap();
+                    let end = *value_indices.iter().max().unwrap() + 1;
+                    let values = read_array(
+                        reader,
+                        child,
+                        batch_id,
+                        &ReadBatchParams::Range(start as usize..end as usize),
+                    )
+                    .await?;
+                    let indices =
+                        UInt32Array::from_iter_values(value_indices.iter().map(|i| *i - start));
+                    take(&values, &indices, None)?
+                };
+                children.push((arrow_field, values));
+            }
+            Some(Buffer::from_slice_ref(new_offsets.as_slice()))
+        }
+    };
+    Ok(Arc::new(UnionArray::try_new(
+        &field_type_ids,
+        Buffer::from_slice_ref(type_ids.values()),
+        value_offsets,
+        children,
+    )?))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use std::collections::HashMap;
+
     use arrow_array::{
-        builder::{Int32Builder, ListBuilder, StringBuilder},
-        cast::{as_primitive_array, as_string_array, as_struct_array},
+        builder::{Int32Builder, ListBuilder, MapBuilder, StringBuilder},
+        cast::{as_primitive_array, as_string_array, as_struct_array, as_union_array},
         types::UInt8Type,
-        DictionaryArray, Float32Array, Int64Array, NullArray, StringArray, StructArray,
+        DictionaryArray, Float32Array, Float64Array, Int64Array, IntervalDayTimeArray,
+        IntervalMonthDayNanoArray, IntervalYearMonthArray, NullArray, StringArray, StructArray,
         UInt32Array, UInt8Array,
     };
-    use arrow_schema::{Field as ArrowField, Schema as ArrowSchema};
+    use arrow_schema::{Field as ArrowField, Schema as ArrowSchema, TimeUnit};
     use futures::StreamExt;
 
+    use crate::encodings::compression::Compression;
     use crate::io::FileWriter;
 
     #[tokio::test]
@@ -682,6 +1014,111 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_read_compressed_pages() {
+        let arrow_schema = Arc::new(ArrowSchema::new(vec![
+            ArrowField::new("i", DataType::Int64, true),
+            ArrowField::new("s", DataType::Utf8, true),
+            ArrowField::new(
+                "l",
+                DataType::List(Box::new(ArrowField::new("item", DataType::Int32, true))),
+                true,
+            ),
+            ArrowField::new(
+                "d",
+                DataType::Dictionary(Box::new(DataType::UInt8), Box::new(DataType::Utf8)),
+                false,
+            ),
+        ]));
+        let values = StringArray::from_iter_values(["a", "b", "c"]);
+        let batches = (0..3)
+            .map(|batch_id| {
+                let value_range = batch_id * 100..batch_id * 100 + 100;
+                let mut list_builder = ListBuilder::new(Int32Builder::new());
+                for v in value_range.clone() {
+                    list_builder.values().append_slice(&[v, v + 1]);
+                    list_builder.append(true);
+                }
+                let keys = UInt8Array::from_iter_values(value_range.clone().map(|v| (v % 3) as u8));
+                let columns: Vec<ArrayRef> = vec![
+                    Arc::new(Int64Array::from_iter(
+                        value_range
+                            .clone()
+                            .map(|v| (v % 7 != 0).then_some(v as i64)),
+                    )),
+                    Arc::new(StringArray::from_iter(
+                        value_range
+                            .clone()
+                            .map(|v| (v % 5 != 0).then(|| format!("str-{v}"))),
+                    )),
+                    Arc::new(list_builder.finish()),
+                    Arc::new(DictionaryArray::<UInt8Type>::try_new(&keys, &values).unwrap()),
+                ];
+                RecordBatch::try_new(arrow_schema.clone(), columns).unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let store = ObjectStore::memory();
+        let mut file_sizes = vec![];
+        for (path, compression) in [("/plain", None), ("/compressed", Some(Compression::Zstd))] {
+            let mut schema = Schema::try_from(arrow_schema.as_ref()).unwrap();
+            schema.set_dictionary(&batches[0]).unwrap();
+            if compression.is_some() {
+                let columns = HashMap::from([
+                    ("s".to_string(), Some(Compression::Lz4)),
+                    ("l.item".to_string(), None),
+                ]);
+                schema.set_compression(compression, &columns).unwrap();
+            }
+
+            let path = Path::from(path);
+            let mut file_writer = FileWriter::try_new(&store, &path, &schema).await.unwrap();
+            for batch in batches.iter() {
+                file_writer.write(batch).await.unwrap();
+            }
+            file_writer.finish().await.unwrap();
+            file_sizes.push(file_writer.tell());
+
+            let reader = FileReader::try_new(&store, &path).await.unwrap();
+            let compression_of = |name: &str| reader.schema().field(name).unwrap().compression;
+            assert_eq!(compression_of("i"), compression);
+            assert_eq!(compression_of("s"), compression.map(|_| Compression::Lz4));
+            assert_eq!(compression_of("l"), compression);
+            assert_eq!(compression_of("l.item"), None);
+
+            // The lists are only read in full.
+            let projection = reader.schema().project(&["i", "s", "d"]).unwrap();
+            for (batch_id, batch) in batches.iter().enumerate() {
+                let actual = reader
+                    .read_batch(batch_id as i32, .., reader.schema())
+                    .await
+                    .unwrap();
+                assert_eq!(&actual, batch);
+                let actual = reader
+                    .read_batch(batch_id as i32, 10..30, &projection)
+                    .await
+                    .unwrap();
+                assert_eq!(actual, batch.slice(10, 20).project(&[0, 1, 3]).unwrap());
+            }
+
+            let actual = reader
+                .take(&[1, 15, 120, 255, 299], &projection)
+                .await
+                .unwrap();
+            let expected = [(0, 1), (0, 15), (1, 20), (2, 55), (2, 99)]
+                .iter()
+                .map(|(batch_id, offset)| {
+                    batches[*batch_id]
+                        .slice(*offset, 1)
+                        .project(&[0, 1, 3])
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(actual, concat_batches(&actual.schema(), &expected).unwrap());
+        }
+        assert!(file_sizes[1] < file_sizes[0]);
+    }
+
     async fn test_write_null_string_in_struct(field_nullable: bool) {
         let arrow_schema = Arc::new(ArrowSchema::new(vec![ArrowField::new(
             "parent",
@@ -869,4 +1306,146 @@ mod tests {
         let arr = read_array(&reader, &schema.fields[1], 0, &params);
         assert!(arr.await.is_err());
     }
+
+    /// Assert that `actual` has the values of `expected` at `indices`.
+    fn assert_values_eq(actual: &dyn Array, expected: &dyn Array, indices: &[usize]) {
+        assert_eq!(actual.data_type(), expected.data_type());
+        assert_eq!(actual.len(), indices.len());
+        for (i, index) in indices.iter().enumerate() {
+            if let DataType::Union(_, _, _) = expected.data_type() {
+                let actual = as_union_array(actual);
+                let expected = as_union_array(expected);
+                assert_eq!(actual.type_id(i), expected.type_id(*index));
+                assert_eq!(&actual.value(i), &expected.value(*index));
+            } else {
+                assert_eq!(&actual.slice(i, 1), &expected.slice(*index, 1));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_timestamp_interval_map_and_union_arrays() {
+        let timestamps = make_array(
+            ArrayDataBuilder::new(DataType::Timestamp(
+                TimeUnit::Microsecond,
+                Some("America/New_York".to_string()),
+            ))
+            .len(100)
+            .add_buffer(Buffer::from_slice_ref(
+                (0..100).map(|v| v * 1_000_000).collect::<Vec<i64>>(),
+            ))
+            .build()
+            .unwrap(),
+        );
+
+        let mut map_builder = MapBuilder::new(None, StringBuilder::new(), StringBuilder::new());
+        for i in 0..100 {
+            for j in 0..i % 3 {
+                map_builder.keys().append_value(format!("k{j}"));
+                if j == 1 {
+                    map_builder.values().append_null();
+                } else {
+                    map_builder.values().append_value(format!("v{i}-{j}"));
+                }
+            }
+            map_builder.append(i % 5 != 0).unwrap();
+        }
+        let map = map_builder.finish();
+
+        let sparse_union = UnionArray::try_new(
+            &[0, 3],
+            Buffer::from_slice_ref(
+                (0..100)
+                    .map(|i| if i % 3 == 0 { 3 } else { 0 })
+                    .collect::<Vec<i8>>(),
+            ),
+            None,
+            vec![
+                (
+                    ArrowField::new("i", DataType::Int32, true),
+                    Arc::new(Int32Array::from_iter_values(0..100)) as ArrayRef,
+                ),
+                (
+                    ArrowField::new("s", DataType::Utf8, true),
+                    Arc::new(StringArray::from_iter_values(
+                        (0..100).map(|i| format!("s-{i}")),
+                    )) as ArrayRef,
+                ),
+            ],
+        )
+        .unwrap();
+
+        // 60 floats and 40 strings.
+        let type_ids = (0..100)
+            .map(|i| if i % 5 < 3 { 0 } else { 1 })
+            .collect::<Vec<i8>>();
+        let offsets = (0..100)
+            .map(|i| {
+                if i % 5 < 3 {
+                    i / 5 * 3 + i % 5
+                } else {
+                    i / 5 * 2 + i % 5 - 3
+                }
+            })
+            .collect::<Vec<i32>>();
+        let dense_union = UnionArray::try_new(
+            &[0, 1],
+            Buffer::from_slice_ref(type_ids),
+            Some(Buffer::from_slice_ref(offsets)),
+            vec![
+                (
+                    ArrowField::new("f", DataType::Float64, true),
+                    Arc::new(Float64Array::from_iter_values((0..60).map(|v| v as f64))) as ArrayRef,
+                ),
+                (
+                    ArrowField::new("s", DataType::Utf8, true),
+                    Arc::new(StringArray::from_iter_values(
+                        (0..40).map(|i| format!("d-{i}")),
+                    )) as ArrayRef,
+                ),
+            ],
+        )
+        .unwrap();
+
+        let columns: Vec<ArrayRef> = vec![
+            timestamps,
+            Arc::new(IntervalYearMonthArray::from_iter_values(0..100)),
+            Arc::new(IntervalDayTimeArray::from_iter_values(0..100)),
+            Arc::new(IntervalMonthDayNanoArray::from_iter_values(0..100)),
+            Arc::new(map),
+            Arc::new(sparse_union),
+            Arc::new(dense_union),
+        ];
+        let arrow_schema = ArrowSchema::new(
+            ["ts", "ym", "dt", "mdn", "tags", "sparse", "dense"]
+                .iter()
+                .zip(columns.iter())
+                .map(|(name, c)| ArrowField::new(*name, c.data_type().clone(), true))
+                .collect(),
+        );
+        let schema = Schema::try_from(&arrow_schema).unwrap();
+        let batch = RecordBatch::try_new(Arc::new(arrow_schema), columns).unwrap();
+
+        let store = ObjectStore::memory();
+        let path = Path::from("/types");
+        let mut file_writer = FileWriter::try_new(&store, &path, &schema).await.unwrap();
+        file_writer.write(&batch).await.unwrap();
+        file_writer.finish().await.unwrap();
+
+        let reader = FileReader::try_new(&store, &path).await.unwrap();
+        assert_eq!(reader.schema(), &schema);
+        for (params, indices) in [
+            (ReadBatchParams::RangeFull, (0..100).collect::<Vec<_>>()),
+            (ReadBatchParams::Range(10..31), (10..31).collect()),
+            (
+                ReadBatchParams::Indices(UInt32Array::from(vec![0, 3, 4, 10, 11, 57, 99])),
+                vec![0, 3, 4, 10, 11, 57, 99],
+            ),
+        ] {
+            let actual = reader.read_batch(0, params, &schema).await.unwrap();
+            for (actual, expected) in actual.columns().iter().zip(batch.columns()) {
+                assert_values_eq(actual.as_ref(), expected.as_ref(), &indices);
+            }
+        }
+    }
 }
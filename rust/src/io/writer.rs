@@ -15,15 +15,20 @@
 use std::sync::Arc;
 
 use arrow_arith::arithmetic::subtract_scalar;
-use arrow_array::cast::{as_large_list_array, as_list_array, as_struct_array};
-use arrow_array::{Array, ArrayRef, Int32Array, Int64Array, RecordBatch, StructArray};
-use arrow_schema::DataType;
+use arrow_array::cast::{
+    as_large_list_array, as_list_array, as_map_array, as_struct_array, as_union_array,
+};
+use arrow_array::{Array, ArrayRef, Int32Array, Int64Array, Int8Array, RecordBatch, StructArray};
+use arrow_schema::{DataType, UnionMode};
 use async_recursion::async_recursion;
 use object_store::path::Path;
 
 use crate::arrow::*;
 use crate::datatypes::{Field, Schema};
+use crate::encodings::bitpacked::BitPackedEncoder;
+use crate::encodings::delta::DeltaEncoder;
 use crate::encodings::dictionary::DictionaryEncoder;
+use crate::encodings::rle::RleEncoder;
 use crate::encodings::{binary::BinaryEncoder, plain::PlainEncoder, Encoder, Encoding};
 use crate::format::{pb, Index, Manifest, Metadata, PageInfo, PageTable};
 use crate::io::object_writer::ObjectWriter;
@@ -99,24 +104,20 @@ pub async fn write_manifest(
 /// // Need to close file writer to flush buffer and footer.
 /// file_writer.shutdown();
 /// ```
-pub struct FileWriter<'a> {
+pub struct FileWriter {
     object_writer: ObjectWriter,
-    schema: &'a Schema,
+    schema: Arc<Schema>,
     batch_id: i32,
     page_table: PageTable,
     metadata: Metadata,
 }
 
-impl<'a> FileWriter<'a> {
-    pub async fn try_new(
-        object_store: &ObjectStore,
-        path: &Path,
-        schema: &'a Schema,
-    ) -> Result<FileWriter<'a>> {
+impl FileWriter {
+    pub async fn try_new(object_store: &ObjectStore, path: &Path, schema: &Schema) -> Result<Self> {
         let object_writer = object_store.create(path).await?;
         Ok(Self {
             object_writer,
-            schema,
+            schema: Arc::new(schema.clone()),
             batch_id: 0,
             page_table: PageTable::default(),
             metadata: Metadata::default(),
@@ -127,7 +128,8 @@ impl<'a> FileWriter<'a> {
     ///
     /// Returns [Err] if the schema does not match with the batch.
     pub async fn write(&mut self, batch: &RecordBatch) -> Result<()> {
-        for field in self.schema.fields.as_slice() {
+        let schema = self.schema.clone();
+        for field in schema.fields.iter() {
             let column_id = batch.schema().index_of(&field.name)?;
             let array = batch.column(column_id);
             self.write_array(field, array).await?;
@@ -151,8 +153,16 @@ impl<'a> FileWriter<'a> {
         self.len() == 0
     }
 
+    /// Total bytes written to this file so far.
+    pub fn tell(&self) -> usize {
+        self.object_writer.tell()
+    }
+
     #[async_recursion]
     async fn write_array(&mut self, field: &Field, array: &ArrayRef) -> Result<()> {
+        if field.encoding == Some(Encoding::RLE) {
+            return self.write_rle_array(field, array).await;
+        }
         let data_type = array.data_type();
         match data_type {
             DataType::Null => self.write_null_array(field, array).await,
@@ -170,6 +180,8 @@ impl<'a> FileWriter<'a> {
             }
             DataType::List(_) => self.write_list_array(field, array).await,
             DataType::LargeList(_) => self.write_large_list_array(field, array).await,
+            DataType::Map(_, _) => self.write_map_array(field, array).await,
+            DataType::Union(_, _, _) => self.write_union_array(field, array).await,
             _ => {
                 return Err(Error::Schema(format!(
                     "FileWriter::write: unsupported data type: {data_type}"
@@ -185,23 +197,75 @@ impl<'a> FileWriter<'a> {
     }
 
     /// Write fixed size array, including, primtiives, fixed size binary, and fixed size list.
+    ///
+    /// If the array has nulls, the validity bitmaps are written after the values.
     async fn write_fixed_stride_array(&mut self, field: &Field, array: &ArrayRef) -> Result<()> {
-        assert_eq!(field.encoding, Some(Encoding::Plain));
-        let mut encoder = PlainEncoder::new(&mut self.object_writer, array.data_type());
+        if !matches!(
+            field.encoding,
+            Some(Encoding::Plain) | Some(Encoding::BitPacked) | Some(Encoding::Delta)
+        ) {
+            return Err(Error::Schema(format!(
+                "FileWriter: field {} can not be written with encoding {:?}",
+                field.name, field.encoding
+            )));
+        }
+        if !field.nullable && array.null_count() > 0 {
+            return Err(Error::Schema(format!(
+                "FileWriter: non-nullable field {} has {} nulls",
+                field.name,
+                array.null_count()
+            )));
+        }
+        self.start_page(field);
+        let data_type = array.data_type();
+        let pos = match field.encoding {
+            Some(Encoding::BitPacked) => {
+                BitPackedEncoder::new(&mut self.object_writer, data_type)
+                    .encode(array)
+                    .await?
+            }
+            Some(Encoding::Delta) => {
+                DeltaEncoder::new(&mut self.object_writer, data_type)
+                    .encode(array)
+                    .await?
+            }
+            _ => {
+                PlainEncoder::new(&mut self.object_writer, data_type)
+                    .encode(array)
+                    .await?
+            }
+        };
+        let mut page_info = PageInfo::new(pos, array.len());
+        page_info.validity = PlainEncoder::new(&mut self.object_writer, data_type)
+            .encode_validity(array)
+            .await?;
+        self.finish_page(field, page_info).await
+    }
+
+    /// Write run-length encoded arrays.
+    async fn write_rle_array(&mut self, field: &Field, array: &ArrayRef) -> Result<()> {
+        if !field.nullable && array.null_count() > 0 {
+            return Err(Error::Schema(format!(
+                "FileWriter: non-nullable field {} has {} nulls",
+                field.name,
+                array.null_count()
+            )));
+        }
+        self.start_page(field);
+        let mut encoder = RleEncoder::new(&mut self.object_writer, array.data_type());
         let pos = encoder.encode(array).await?;
         let page_info = PageInfo::new(pos, array.len());
-        self.page_table.set(field.id, self.batch_id, page_info);
-        Ok(())
+        self.finish_page(field, page_info).await
     }
 
     /// Write var-length binary arrays.
     async fn write_binary_array(&mut self, field: &Field, array: &ArrayRef) -> Result<()> {
         assert_eq!(field.encoding, Some(Encoding::VarBinary));
+        self.start_page(field);
         let mut encoder = BinaryEncoder::new(&mut self.object_writer);
         let pos = encoder.encode(array).await?;
         let page_info = PageInfo::new(pos, array.len());
-        self.page_table.set(field.id, self.batch_id, page_info);
-        Ok(())
+        self.finish_page(field, page_info).await
     }
 
     async fn write_dictionary_arr(
@@ -213,11 +277,11 @@ impl<'a> FileWriter<'a> {
         assert_eq!(field.encoding, Some(Encoding::Dictionary));
 
         // Write the dictionary keys.
+        self.start_page(field);
         let mut encoder = DictionaryEncoder::new(&mut self.object_writer, key_type);
         let pos = encoder.encode(array).await?;
         let page_info = PageInfo::new(pos, array.len());
-        self.page_table.set(field.id, self.batch_id, page_info);
-        Ok(())
+        self.finish_page(field, page_info).await
     }
 
     #[async_recursion]
@@ -257,13 +321,98 @@ impl<'a> FileWriter<'a> {
             .await
     }
 
+    /// Write the offsets of the entries and the validity of the maps, then the entries.
+    async fn write_map_array(&mut self, field: &Field, array: &ArrayRef) -> Result<()> {
+        assert_eq!(field.encoding, Some(Encoding::Plain));
+        let map_arr = as_map_array(array);
+        let offsets: Int32Array = map_arr.value_offsets().iter().copied().collect();
+        assert!(!offsets.is_empty());
+        let start = offsets.value(0);
+        let end = offsets.value(offsets.len() - 1);
+        let offsets = subtract_scalar(&offsets, start)?;
+
+        self.start_page(field);
+        let mut encoder = PlainEncoder::new(&mut self.object_writer, &DataType::Int32);
+        let pos = encoder.encode(&offsets).await?;
+        let mut page_info = PageInfo::new(pos, offsets.len());
+        page_info.validity = encoder.encode_validity(map_arr).await?;
+        self.finish_page(field, page_info).await?;
+
+        let entries = StructArray::from(map_arr.data().child_data()[0].clone());
+        let entries = entries.slice(start as usize, (end - start) as usize);
+        self.write_array(&field.children[0], &entries).await
+    }
+
+    /// Write the type IDs of the union, followed by the offsets for a dense union,
+    /// then the children.
+    ///
+    /// The children of a sparse union have the same length as the union. The children of
+    /// a dense union are written as they are, and the offsets point into them.
+    async fn write_union_array(&mut self, field: &Field, array: &ArrayRef) -> Result<()> {
+        assert_eq!(field.encoding, Some(Encoding::Plain));
+        let DataType::Union(_, type_ids, mode) = array.data_type() else {
+            unreachable!()
+        };
+        let union_arr = as_union_array(array);
+
+        let ids = Int8Array::from_iter_values((0..union_arr.len()).map(|i| union_arr.type_id(i)));
+        self.start_page(field);
+        let mut encoder = PlainEncoder::new(&mut self.object_writer, &DataType::Int8);
+        let pos = encoder.encode(&ids).await?;
+        if matches!(mode, UnionMode::Dense) {
+            let offsets = Int32Array::from_iter_values(
+                (0..union_arr.len()).map(|i| union_arr.value_offset(i)),
+            );
+            let mut encoder = PlainEncoder::new(&mut self.object_writer, &DataType::Int32);
+            encoder.encode(&offsets).await?;
+        }
+        let page_info = PageInfo::new(pos, union_arr.len());
+        self.finish_page(field, page_info).await?;
+
+        for (child, type_id) in field.children.iter().zip(type_ids.iter()) {
+            let values = union_arr.child(*type_id);
+            let values = match mode {
+                UnionMode::Sparse => values.slice(union_arr.offset(), union_arr.len()),
+                UnionMode::Dense => values.clone(),
+            };
+            self.write_array(child, &values).await?;
+        }
+        Ok(())
+    }
+
+    /// Start buffering the page of `field` in memory, if the field is compressed.
+    fn start_page(&mut self, field: &Field) {
+        if field.compression.is_some() {
+            self.object_writer.start_page();
+        }
+    }
+
+    /// Compress the page started by [Self::start_page] if the field is compressed, and add
+    /// the page to the page table.
+    async fn finish_page(&mut self, field: &Field, mut page_info: PageInfo) -> Result<()> {
+        if let Some(compression) = field.compression {
+            let range = self.object_writer.finish_page(compression).await?;
+            page_info.compressed = Some((compression, range));
+        }
+        self.page_table.set(field.id, self.batch_id, page_info);
+        Ok(())
+    }
+
     async fn write_footer(&mut self) -> Result<()> {
         // Step 1. Write page table.
         let pos = self.page_table.write(&mut self.object_writer).await?;
         self.metadata.page_table_position = pos;
+        self.metadata.validity_table_position = self
+            .page_table
+            .write_validity(&mut self.object_writer)
+            .await?;
+        self.metadata.compression_table_position = self
+            .page_table
+            .write_compression(&mut self.object_writer)
+            .await?;
 
         // Step 2. Write manifest and dictionary values.
-        let mut manifest = Manifest::new(self.schema, Arc::new(vec![]));
+        let mut manifest = Manifest::new(&self.schema, Arc::new(vec![]));
         let pos = write_manifest(&mut self.object_writer, &mut manifest, None).await?;
 
         // Step 3. Write metadata.
@@ -446,4 +595,24 @@ mod tests {
         let actual = reader.read_batch(0, .., reader.schema()).await.unwrap();
         assert_eq!(actual, batch);
     }
+
+    #[tokio::test]
+    async fn test_write_unsupported_encoding() {
+        let arrow_schema = ArrowSchema::new(vec![ArrowField::new("i", DataType::Int64, false)]);
+        let mut schema = Schema::try_from(&arrow_schema).unwrap();
+        schema.fields[0].encoding = Some(Encoding::VarBinary);
+        let batch = RecordBatch::try_new(
+            Arc::new(arrow_schema),
+            vec![Arc::new(Int64Array::from_iter_values(0..10))],
+        )
+        .unwrap();
+
+        let store = ObjectStore::memory();
+        let path = Path::from("/foo");
+        let mut file_writer = FileWriter::try_new(&store, &path, &schema).await.unwrap();
+        assert!(matches!(
+            file_writer.write(&batch).await,
+            Err(Error::Schema(_))
+        ));
+    }
 }
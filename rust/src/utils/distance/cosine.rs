@@ -18,30 +18,35 @@
 use std::sync::Arc;
 
 use arrow_array::Float32Array;
+use half::{bf16, f16};
 
 use super::compute::normalize;
+use crate::arrow::{ArrowFloatType, FloatArray};
 use crate::Result;
 
+/// The float types of the vector values that the cosine distance is computed on.
+pub trait Cosine: Sized {
+    /// Cosine distances from the vector `from` to each vector of `dimension` in `to`.
+    fn cosine_batch(from: &[Self], to: &[Self], dimension: usize) -> Float32Array;
+}
+
 /// Fallback Cosine Distance function.
-fn cosine_dist(from: &Float32Array, to: &Float32Array, dimension: usize) -> Arc<Float32Array> {
+fn cosine_dist(from: &[f32], to: &[f32], dimension: usize) -> Float32Array {
     assert_eq!(from.len(), dimension);
-    let n = to.len() / dimension;
 
-    let distances: Float32Array = (0..n)
-        .map(|idx| {
-            let vector = &to.values()[idx * dimension..(idx + 1) * dimension];
+    to.chunks_exact(dimension)
+        .map(|vector| {
             let mut x_sq = 0_f32;
             let mut y_sq = 0_f32;
             let mut xy = 0_f32;
-            from.values().iter().zip(vector.iter()).for_each(|(x, y)| {
+            from.iter().zip(vector.iter()).for_each(|(x, y)| {
                 xy += x * y;
                 x_sq += x.powi(2);
                 y_sq += y.powi(2);
             });
             1.0 - xy / (x_sq.sqrt() * y_sq.sqrt())
         })
-        .collect();
-    Arc::new(distances)
+        .collect()
 }
 
 #[cfg(any(target_arch = "aarch64"))]
@@ -82,14 +87,12 @@ unsafe fn cosine_dist_fma(x_vector: &[f32], y_vector: &[f32], x_norm: f32) -> f3
 }
 
 #[inline]
-fn cosine_dist_simd(from: &Float32Array, to: &Float32Array, dimension: usize) -> Arc<Float32Array> {
-    assert!(to.len() % dimension == 0);
+fn cosine_dist_simd(x: &[f32], to_values: &[f32], dimension: usize) -> Float32Array {
+    assert!(to_values.len() % dimension == 0);
     use arrow::array::Float32Builder;
 
-    let x = from.values();
-    let to_values = to.values();
     let x_norm = normalize(x);
-    let n = to.len() / dimension;
+    let n = to_values.len() / dimension;
     let mut builder = Float32Builder::with_capacity(n);
     for y in to_values.chunks_exact(dimension) {
         #[cfg(any(target_arch = "aarch64"))]
@@ -101,34 +104,95 @@ fn cosine_dist_simd(from: &Float32Array, to: &Float32Array, dimension: usize) ->
             builder.append_value(unsafe { cosine_dist_fma(x, y, x_norm) });
         }
     }
-    Arc::new(builder.finish())
+    builder.finish()
 }
 
-/// Cosine Distance
-///
-/// <https://en.wikipedia.org/wiki/Cosine_similarity>
-pub fn cosine_distance(
-    from: &Float32Array,
-    to: &Float32Array,
-    dimension: usize,
-) -> Result<Arc<Float32Array>> {
-    #[cfg(target_arch = "aarch64")]
-    {
-        use std::arch::is_aarch64_feature_detected;
-        if is_aarch64_feature_detected!("neon") && from.len() % 4 == 0 {
-            return Ok(cosine_dist_simd(from, to, dimension));
+impl Cosine for f32 {
+    fn cosine_batch(from: &[Self], to: &[Self], dimension: usize) -> Float32Array {
+        #[cfg(target_arch = "aarch64")]
+        {
+            use std::arch::is_aarch64_feature_detected;
+            if is_aarch64_feature_detected!("neon") && from.len() % 4 == 0 {
+                return cosine_dist_simd(from, to, dimension);
+            }
         }
-    }
 
-    #[cfg(target_arch = "x86_64")]
-    {
-        if is_x86_feature_detected!("fma") && from.len() % 8 == 0 {
-            return Ok(cosine_dist_simd(from, to, dimension));
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("fma") && from.len() % 8 == 0 {
+                return cosine_dist_simd(from, to, dimension);
+            }
         }
+
+        // Fallback
+        cosine_dist(from, to, dimension)
+    }
+}
+
+impl Cosine for f64 {
+    fn cosine_batch(from: &[Self], to: &[Self], dimension: usize) -> Float32Array {
+        let x_norm = from.iter().map(|x| x.powi(2)).sum::<f64>().sqrt();
+        to.chunks_exact(dimension)
+            .map(|vector| {
+                let mut y_sq = 0_f64;
+                let mut xy = 0_f64;
+                from.iter().zip(vector.iter()).for_each(|(x, y)| {
+                    xy += x * y;
+                    y_sq += y.powi(2);
+                });
+                (1.0 - xy / (x_norm * y_sq.sqrt())) as f32
+            })
+            .collect()
+    }
+}
+
+/// The cosine distances of the 16-bit floats, which are summed up in `f32`.
+fn cosine_batch_in_f32<T: Copy + Into<f32>>(
+    from: &[T],
+    to: &[T],
+    dimension: usize,
+) -> Float32Array {
+    let from = from.iter().map(|v| (*v).into()).collect::<Vec<f32>>();
+    let x_norm = from.iter().map(|x| x.powi(2)).sum::<f32>().sqrt();
+    to.chunks_exact(dimension)
+        .map(|vector| {
+            let mut y_sq = 0_f32;
+            let mut xy = 0_f32;
+            from.iter().zip(vector.iter()).for_each(|(x, y)| {
+                let y: f32 = (*y).into();
+                xy += x * y;
+                y_sq += y.powi(2);
+            });
+            1.0 - xy / (x_norm * y_sq.sqrt())
+        })
+        .collect()
+}
+
+impl Cosine for f16 {
+    fn cosine_batch(from: &[Self], to: &[Self], dimension: usize) -> Float32Array {
+        cosine_batch_in_f32(from, to, dimension)
     }
+}
 
-    // Fallback
-    Ok(cosine_dist(from, to, dimension))
+impl Cosine for bf16 {
+    fn cosine_batch(from: &[Self], to: &[Self], dimension: usize) -> Float32Array {
+        cosine_batch_in_f32(from, to, dimension)
+    }
+}
+
+/// Cosine Distance
+///
+/// <https://en.wikipedia.org/wiki/Cosine_similarity>
+pub fn cosine_distance<T: ArrowFloatType>(
+    from: &T::ArrayType,
+    to: &T::ArrayType,
+    dimension: usize,
+) -> Result<Arc<Float32Array>> {
+    Ok(Arc::new(T::Native::cosine_batch(
+        from.as_slice(),
+        to.as_slice(),
+        dimension,
+    )))
 }
 
 #[cfg(test)]
@@ -136,19 +200,44 @@ mod tests {
     use super::*;
 
     use approx::assert_relative_eq;
+    use arrow_array::types::{Float16Type, Float32Type, Float64Type};
+    use arrow_array::{Float16Array, Float64Array};
 
     #[test]
     fn test_cosine() {
         let x: Float32Array = (1..9).map(|v| v as f32).collect();
         let y: Float32Array = (100..108).map(|v| v as f32).collect();
-        let d = cosine_distance(&x, &y, 8).unwrap();
+        let d = cosine_distance::<Float32Type>(&x, &y, 8).unwrap();
         // from scipy.spatial.distance.cosine
         assert_relative_eq!(d.value(0), 1.0 - 0.90095701);
 
         let x = Float32Array::from_iter_values([3.0, 45.0, 7.0, 2.0, 5.0, 20.0, 13.0, 12.0]);
         let y = Float32Array::from_iter_values([2.0, 54.0, 13.0, 15.0, 22.0, 34.0, 50.0, 1.0]);
-        let d = cosine_distance(&x, &y, 8).unwrap();
+        let d = cosine_distance::<Float32Type>(&x, &y, 8).unwrap();
         // from sklearn.metrics.pairwise import cosine_similarity
         assert_relative_eq!(d.value(0), 1.0 - 0.8735806510613104);
     }
+
+    #[test]
+    fn test_cosine_float_types() {
+        let x = [3.0, 45.0, 7.0, 2.0, 5.0, 20.0, 13.0, 12.0];
+        let y = [2.0, 54.0, 13.0, 15.0, 22.0, 34.0, 50.0, 1.0];
+        let expected = 1.0 - 0.8735806510613104;
+
+        let d = cosine_distance::<Float64Type>(
+            &Float64Array::from_iter_values(x),
+            &Float64Array::from_iter_values(y),
+            8,
+        )
+        .unwrap();
+        assert_relative_eq!(d.value(0), expected as f32);
+
+        let d = cosine_distance::<Float16Type>(
+            &Float16Array::from_iter_values(x.map(f16::from_f64)),
+            &Float16Array::from_iter_values(y.map(f16::from_f64)),
+            8,
+        )
+        .unwrap();
+        assert_relative_eq!(d.value(0), expected as f32, epsilon = 1e-6);
+    }
 }
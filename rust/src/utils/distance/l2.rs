@@ -16,10 +16,18 @@
 
 use std::sync::Arc;
 
-use arrow_array::{Array, Float32Array};
+use arrow_array::Float32Array;
+use half::{bf16, f16};
 
+use crate::arrow::{ArrowFloatType, FloatArray};
 use crate::Result;
 
+/// The float types of the vector values that the L2 distance is computed on.
+pub trait L2: Sized {
+    /// L2 distances from the vector `from` to each vector of `dimension` in `to`.
+    fn l2_batch(from: &[Self], to: &[Self], dimension: usize) -> Float32Array;
+}
+
 // TODO: wait [std::simd] to be stable to replace manually written AVX/FMA code.
 //
 // `from` and `to` must have the same length.
@@ -57,8 +65,11 @@ unsafe fn euclidean_distance_fma(from: &[f32], to: &[f32]) -> f32 {
 ///
 #[inline]
 pub fn l2_distance_arrow(from: &Float32Array, to: &Float32Array) -> f32 {
-    let a = from.values();
-    let b = to.values();
+    l2_distance_scalar(from.values(), to.values())
+}
+
+#[inline]
+fn l2_distance_scalar(a: &[f32], b: &[f32]) -> f32 {
     let mut d = 0.0;
     // Better chance to auto-vectorization.
     let l = a.len();
@@ -86,16 +97,10 @@ unsafe fn l2_distance_neon(from: &[f32], to: &[f32]) -> f32 {
     vaddvq_f32(sum)
 }
 
-fn l2_distance_simd(
-    from: &Float32Array,
-    to: &Float32Array,
-    dimension: usize,
-) -> Result<Arc<Float32Array>> {
-    let n = to.len() / dimension;
-    let from_vector = from.values();
-    let to_buffer = to.values();
+fn l2_distance_simd(from_vector: &[f32], to_buffer: &[f32], dimension: usize) -> Float32Array {
+    let n = to_buffer.len() / dimension;
 
-    let scores: Float32Array = unsafe {
+    unsafe {
         Float32Array::from_trusted_len_iter(
             (0..n)
                 .map(|idx| {
@@ -117,49 +122,84 @@ fn l2_distance_simd(
                 })
                 .map(Some),
         )
-    };
-    Ok(Arc::new(scores))
+    }
 }
 
-pub fn l2_distance(
-    from: &Float32Array,
-    to: &Float32Array,
-    dimension: usize,
-) -> Result<Arc<Float32Array>> {
-    assert_eq!(from.len(), dimension);
-    assert_eq!(to.len() % dimension, 0);
+impl L2 for f32 {
+    fn l2_batch(from: &[Self], to: &[Self], dimension: usize) -> Float32Array {
+        #[cfg(any(target_arch = "x86_64"))]
+        {
+            if is_x86_feature_detected!("fma") && from.len() % 8 == 0 {
+                return l2_distance_simd(from, to, dimension);
+            }
+        }
 
-    #[cfg(any(target_arch = "x86_64"))]
-    {
-        if is_x86_feature_detected!("fma") && from.len() % 8 == 0 {
-            return l2_distance_simd(from, to, dimension);
+        #[cfg(any(target_arch = "aarch64"))]
+        {
+            use std::arch::is_aarch64_feature_detected;
+            if is_aarch64_feature_detected!("neon") && from.len() % 4 == 0 {
+                return l2_distance_simd(from, to, dimension);
+            }
         }
+
+        // Fallback
+        Float32Array::from_iter_values(
+            to.chunks_exact(dimension)
+                .map(|vector| l2_distance_scalar(from, vector)),
+        )
     }
+}
 
-    #[cfg(any(target_arch = "aarch64"))]
-    {
-        use std::arch::is_aarch64_feature_detected;
-        if is_aarch64_feature_detected!("neon") && from.len() % 4 == 0 {
-            return l2_distance_simd(from, to, dimension);
-        }
+impl L2 for f64 {
+    fn l2_batch(from: &[Self], to: &[Self], dimension: usize) -> Float32Array {
+        Float32Array::from_iter_values(to.chunks_exact(dimension).map(|vector| {
+            from.iter()
+                .zip(vector.iter())
+                .map(|(x, y)| (x - y).powi(2))
+                .sum::<f64>() as f32
+        }))
     }
+}
 
-    // Fallback
-    use arrow_array::cast::as_primitive_array;
-    let n = to.len() / dimension;
-    let scores: Float32Array = unsafe {
-        Float32Array::from_trusted_len_iter(
-            (0..n)
-                .map(|idx| {
-                    l2_distance_arrow(
-                        from,
-                        as_primitive_array(to.slice(idx * dimension, dimension).as_ref()),
-                    )
-                })
-                .map(Some),
-        )
-    };
-    Ok(Arc::new(scores))
+/// The L2 distances of the 16-bit floats, which are summed up in `f32`.
+fn l2_batch_in_f32<T: Copy + Into<f32>>(from: &[T], to: &[T], dimension: usize) -> Float32Array {
+    let from = from.iter().map(|v| (*v).into()).collect::<Vec<f32>>();
+    Float32Array::from_iter_values(to.chunks_exact(dimension).map(|vector| {
+        from.iter()
+            .zip(vector.iter())
+            .map(|(x, y)| {
+                let y: f32 = (*y).into();
+                (x - y).powi(2)
+            })
+            .sum::<f32>()
+    }))
+}
+
+impl L2 for f16 {
+    fn l2_batch(from: &[Self], to: &[Self], dimension: usize) -> Float32Array {
+        l2_batch_in_f32(from, to, dimension)
+    }
+}
+
+impl L2 for bf16 {
+    fn l2_batch(from: &[Self], to: &[Self], dimension: usize) -> Float32Array {
+        l2_batch_in_f32(from, to, dimension)
+    }
+}
+
+/// L2 distances from the vector `from` to each vector of `dimension` in `to`,
+/// of the float type `T`.
+pub fn l2_distance<T: ArrowFloatType>(
+    from: &T::ArrayType,
+    to: &T::ArrayType,
+    dimension: usize,
+) -> Result<Arc<Float32Array>> {
+    let from = from.as_slice();
+    let to = to.as_slice();
+    assert_eq!(from.len(), dimension);
+    assert_eq!(to.len() % dimension, 0);
+
+    Ok(Arc::new(T::Native::l2_batch(from, to, dimension)))
 }
 
 #[cfg(test)]
@@ -168,7 +208,10 @@ mod tests {
 
     use approx::assert_relative_eq;
     use arrow::array::{as_primitive_array, FixedSizeListArray};
-    use arrow_array::types::Float32Type;
+    use arrow_array::types::{Float16Type, Float32Type, Float64Type};
+    use arrow_array::{Float16Array, Float64Array};
+
+    use crate::arrow::BFloat16Type;
 
     #[test]
     fn test_euclidean_distance() {
@@ -182,7 +225,9 @@ mod tests {
             8,
         );
         let point = Float32Array::from((2..10).map(|v| Some(v as f32)).collect::<Vec<_>>());
-        let scores = l2_distance(&point, as_primitive_array(mat.values().as_ref()), 8).unwrap();
+        let scores =
+            l2_distance::<Float32Type>(&point, as_primitive_array(mat.values().as_ref()), 8)
+                .unwrap();
 
         assert_eq!(
             scores.as_ref(),
@@ -194,7 +239,7 @@ mod tests {
     fn test_odd_length_vector() {
         let mat = Float32Array::from_iter((0..5).map(|v| Some(v as f32)));
         let point = Float32Array::from((2..7).map(|v| Some(v as f32)).collect::<Vec<_>>());
-        let scores = l2_distance(&point, &mat, 5).unwrap();
+        let scores = l2_distance::<Float32Type>(&point, &mat, 5).unwrap();
 
         assert_eq!(scores.as_ref(), &Float32Array::from(vec![20.0]));
     }
@@ -246,7 +291,38 @@ mod tests {
         ]
         .into();
 
-        let d = l2_distance(&q, &values, 32).unwrap();
+        let d = l2_distance::<Float32Type>(&q, &values, 32).unwrap();
         assert_relative_eq!(0.31935785197341404, d.value(0));
     }
+
+    #[test]
+    fn test_l2_distance_float_types() {
+        let from = (2..10).map(|v| v as f32).collect::<Vec<_>>();
+        let to = (0..32).map(|v| v as f32).collect::<Vec<_>>();
+        let expected = Float32Array::from(vec![32.0, 288.0, 1568.0, 3872.0]);
+
+        let f16_scores = l2_distance::<Float16Type>(
+            &Float16Array::from_iter_values(from.iter().map(|v| f16::from_f32(*v))),
+            &Float16Array::from_iter_values(to.iter().map(|v| f16::from_f32(*v))),
+            8,
+        )
+        .unwrap();
+        assert_eq!(f16_scores.as_ref(), &expected);
+
+        let bf16_scores = l2_distance::<BFloat16Type>(
+            &FloatArray::from_values(from.iter().map(|v| bf16::from_f32(*v)).collect()),
+            &FloatArray::from_values(to.iter().map(|v| bf16::from_f32(*v)).collect()),
+            8,
+        )
+        .unwrap();
+        assert_eq!(bf16_scores.as_ref(), &expected);
+
+        let f64_scores = l2_distance::<Float64Type>(
+            &Float64Array::from_iter_values(from.iter().map(|v| *v as f64)),
+            &Float64Array::from_iter_values(to.iter().map(|v| *v as f64)),
+            8,
+        )
+        .unwrap();
+        assert_eq!(f64_scores.as_ref(), &expected);
+    }
 }
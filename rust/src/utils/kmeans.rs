@@ -17,7 +17,9 @@ use std::sync::Arc;
 
 use arrow::array::Float32Builder;
 use arrow_arith::arithmetic::{add, divide_scalar};
-use arrow_array::{cast::as_primitive_array, new_empty_array, Array, Float32Array};
+use arrow_array::{
+    cast::as_primitive_array, new_empty_array, types::Float32Type, Array, Float32Array,
+};
 use arrow_schema::DataType;
 use arrow_select::concat::concat;
 use futures::stream::{self, repeat_with, StreamExt, TryStreamExt};
@@ -350,7 +352,7 @@ impl KMeans {
             .zip(repeat_with(|| (data.clone(), self.centroids.clone())))
             .map(|(indices, (data, centroids))| async move {
                 let data = tokio::task::spawn_blocking(move || {
-                    let dist = metric_type.func();
+                    let dist = metric_type.func::<Float32Type>();
                     let mut results = vec![];
                     for idx in indices {
                         let value_arr = data.slice(idx * dimension, dimension);